@@ -0,0 +1,345 @@
+//! Attribute macros of the [repeated-assert](https://docs.rs/repeated-assert) crate.
+//!
+//! Use them through the re-exports of `repeated-assert` with the `macros` feature.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::{
+    parse::Parser, punctuated::Punctuated, Error, Expr, ExprLit, ExprPath, FnArg, Ident, ItemFn,
+    Lit, LitStr, MetaNameValue, Pat, ReturnType, Token,
+};
+
+/// Run the body of a test repeatedly until it passes, see the documentation of `repeated_assert::test`.
+#[proc_macro_attribute]
+pub fn test(args: TokenStream, item: TokenStream) -> TokenStream {
+    attribute(Kind::Test, args, item)
+}
+
+/// Re-run a whole test if it fails, see the documentation of `repeated_assert::flaky`.
+#[proc_macro_attribute]
+pub fn flaky(args: TokenStream, item: TokenStream) -> TokenStream {
+    attribute(Kind::Flaky, args, item)
+}
+
+fn attribute(kind: Kind, args: TokenStream, item: TokenStream) -> TokenStream {
+    let result = Punctuated::<MetaNameValue, Token![,]>::parse_terminated
+        .parse(args)
+        .and_then(|args| Config::parse(kind, args))
+        .and_then(|config| expand(&config, syn::parse(item)?));
+    match result {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+/// Attribute macro being expanded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Test,
+    Flaky,
+}
+
+/// Arguments of the attribute.
+struct Config {
+    kind: Kind,
+    repetitions: Option<Expr>,
+    retries: Option<Expr>,
+    delay: Option<LitStr>,
+    timeout: Option<LitStr>,
+    backoff: Option<Expr>,
+    name: Option<LitStr>,
+    teardown: Option<ExprPath>,
+}
+
+impl Config {
+    fn parse(kind: Kind, args: Punctuated<MetaNameValue, Token![,]>) -> syn::Result<Config> {
+        let mut config = Config {
+            kind,
+            repetitions: None,
+            retries: None,
+            delay: None,
+            timeout: None,
+            backoff: None,
+            name: None,
+            teardown: None,
+        };
+        for arg in args {
+            let key = arg
+                .path
+                .get_ident()
+                .map(Ident::to_string)
+                .unwrap_or_default();
+            match (kind, key.as_str()) {
+                (Kind::Test, "repetitions") => config.repetitions = Some(arg.value),
+                (Kind::Flaky, "retries") => config.retries = Some(arg.value),
+                (_, "delay") => config.delay = Some(string(&arg.value)?),
+                (Kind::Test, "timeout") => config.timeout = Some(string(&arg.value)?),
+                (Kind::Test, "backoff") => config.backoff = Some(arg.value),
+                (_, "name") => config.name = Some(string(&arg.value)?),
+                (Kind::Flaky, "teardown") => config.teardown = Some(path(&arg.value)?),
+                (Kind::Test, _) => {
+                    return Err(Error::new_spanned(
+                        arg.path,
+                        "unknown argument, expected one of `repetitions`, `delay`, `timeout`, `backoff` or `name`",
+                    ))
+                }
+                (Kind::Flaky, _) => {
+                    return Err(Error::new_spanned(
+                        arg.path,
+                        "unknown argument, expected one of `retries`, `delay`, `name` or `teardown`",
+                    ))
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// Build the `Retry` with the configured strategy, named after the test by default.
+    fn retry(&self, test: &Ident) -> TokenStream2 {
+        let mut retry = quote!(::repeated_assert::Retry::new());
+        if let Some(repetitions) = &self.repetitions {
+            retry.extend(quote!(.repetitions(#repetitions)));
+        }
+        if self.kind == Kind::Flaky {
+            // the first run isn't a retry, and flaky tests are re-run immediately by default
+            let retries = self.retries.clone().unwrap_or_else(|| syn::parse_quote!(2));
+            retry.extend(quote!(.repetitions(#retries + 1)));
+            if self.delay.is_none() {
+                retry.extend(quote!(.delay(::core::time::Duration::ZERO)));
+            }
+        }
+        if let Some(delay) = &self.delay {
+            let delay = duration(delay);
+            retry.extend(quote!(.delay(#delay)));
+        }
+        if let Some(timeout) = &self.timeout {
+            let timeout = duration(timeout);
+            retry.extend(quote!(.timeout(#timeout)));
+        }
+        if let Some(backoff) = &self.backoff {
+            retry.extend(quote!(.backoff(#backoff)));
+        }
+        let name = self.name(test);
+        retry.extend(quote!(.name(#name)));
+        retry
+    }
+
+    /// Name of the test, defaults to the name of the function.
+    fn name(&self, test: &Ident) -> LitStr {
+        self.name
+            .clone()
+            .unwrap_or_else(|| LitStr::new(&test.to_string(), test.span()))
+    }
+}
+
+/// Move the body of the test into an inner function, which is called on every try.
+///
+/// This keeps the semantics of `return` and `?` in the body.
+/// Arguments of the test (e.g. `rstest` fixtures and cases) are passed on to the inner function, cloned for every try.
+fn expand(config: &Config, mut item: ItemFn) -> syn::Result<TokenStream2> {
+    let retry = config.retry(&item.sig.ident);
+    let output = match &item.sig.output {
+        ReturnType::Default => quote!(()),
+        ReturnType::Type(_, ty) => quote!(#ty),
+    };
+    let asyncness = item.sig.asyncness;
+    let (params, args) = arguments(&mut item)?;
+    let block = &item.block;
+    let inner = quote! {
+        #asyncness fn __repeated_assert_test(#(#params),*) -> #output #block
+    };
+
+    // `rstest` adds the test attribute itself
+    let has_test_attribute = item.attrs.iter().any(|attr| {
+        attr.path()
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "test" || segment.ident == "rstest")
+    });
+    if !has_test_attribute {
+        if asyncness.is_some() {
+            item.attrs.push(syn::parse_quote!(#[::tokio::test]));
+        } else {
+            item.attrs
+                .push(syn::parse_quote!(#[::core::prelude::v1::test]));
+        }
+    }
+
+    let call = match asyncness {
+        Some(_) => quote!(__repeated_assert_test(#(#args),*).await),
+        None => quote!(__repeated_assert_test(#(#args),*)),
+    };
+    let clones = quote! {
+        #(let #args = ::core::clone::Clone::clone(&#args);)*
+    };
+    let run = match config.kind {
+        Kind::Test => quote! {
+            ::repeated_assert::__private::check(#call)
+        },
+        Kind::Flaky => {
+            let name = config.name(&item.sig.ident);
+            let teardown = config.teardown.as_ref().map(|teardown| {
+                quote! {
+                    // runs when the test finishes or panics
+                    struct Teardown;
+                    impl ::core::ops::Drop for Teardown {
+                        fn drop(&mut self) {
+                            #teardown();
+                        }
+                    }
+                    let _teardown = Teardown;
+                }
+            });
+            quote! {
+                #teardown
+                let result = ::repeated_assert::__private::check(#call);
+                ::repeated_assert::__private::passed(#name, context.attempt());
+                result
+            }
+        }
+    };
+    let run = match (asyncness, config.kind) {
+        (Some(_), Kind::Test) => {
+            quote!(::repeated_assert::__repeated_assert_async!(
+                #retry.run_async(|| { #clones async move { #run } }).await
+            ))
+        }
+        (Some(_), Kind::Flaky) => {
+            quote!(::repeated_assert::__repeated_assert_async!(
+                #retry.run_with_context_async(|context| { #clones async move { #run } }).await
+            ))
+        }
+        (None, Kind::Test) => quote!(#retry.run(|| { #clones #run })),
+        (None, Kind::Flaky) => quote!(#retry.run_with_context(|context| { #clones #run })),
+    };
+    item.block = syn::parse2(quote!({
+        #inner
+        #run
+    }))?;
+    Ok(quote!(#item))
+}
+
+/// Get the parameters of the inner function and the names of the arguments passed to it.
+///
+/// The attributes of the parameters (e.g. `#[case]`) stay on the test function,
+/// which only binds the arguments immutably.
+fn arguments(item: &mut ItemFn) -> syn::Result<(Vec<FnArg>, Vec<Ident>)> {
+    let mut params = Vec::new();
+    let mut args = Vec::new();
+    for input in &mut item.sig.inputs {
+        let FnArg::Typed(typed) = input else {
+            return Err(Error::new_spanned(input, "tests can't take `self`"));
+        };
+        let mut param = typed.clone();
+        param.attrs.clear();
+        let Pat::Ident(pat) = &mut *typed.pat else {
+            return Err(Error::new_spanned(
+                &typed.pat,
+                "expected an identifier, destructure the argument in the body instead",
+            ));
+        };
+        params.push(FnArg::Typed(param));
+        args.push(pat.ident.clone());
+        pat.mutability = None;
+    }
+    Ok((params, args))
+}
+
+fn path(value: &Expr) -> syn::Result<ExprPath> {
+    match value {
+        Expr::Path(path) => Ok(path.clone()),
+        _ => Err(Error::new_spanned(value, "expected the path of a function")),
+    }
+}
+
+fn string(value: &Expr) -> syn::Result<LitStr> {
+    match value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(string),
+            ..
+        }) => Ok(string.clone()),
+        _ => Err(Error::new_spanned(value, "expected a string literal")),
+    }
+}
+
+/// Evaluate the duration `string` at compile time with the parser of the declarative macros,
+/// so all macros accept the same durations (e.g. `"100us"` or `"1m 30s"`).
+fn duration(string: &LitStr) -> TokenStream2 {
+    quote_spanned! {string.span()=>
+        {
+            const DURATION: ::core::time::Duration = ::repeated_assert::__private::duration(#string);
+            DURATION
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // not a glob import, which would shadow the built-in `test` attribute
+    use super::{expand, Config, Kind};
+    use quote::quote;
+    use syn::{parse::Parser, punctuated::Punctuated, MetaNameValue, Token};
+
+    fn config(kind: Kind, args: proc_macro2::TokenStream) -> syn::Result<Config> {
+        let args = Punctuated::<MetaNameValue, Token![,]>::parse_terminated
+            .parse2(args)
+            .unwrap();
+        Config::parse(kind, args)
+    }
+
+    #[test]
+    fn expand_test() {
+        let config = config(Kind::Test, quote!(repetitions = 5, delay = "10ms")).unwrap();
+        let item = syn::parse_quote! {
+            fn eventually() -> Result<(), String> {
+                check()?;
+                Ok(())
+            }
+        };
+
+        let tokens = expand(&config, item).unwrap().to_string();
+
+        assert!(tokens.starts_with("# [:: core :: prelude :: v1 :: test] fn eventually ()"));
+        assert!(tokens.contains("fn __repeated_assert_test () -> Result < () , String >"));
+        assert!(tokens.contains(". repetitions (5) . delay ({ const DURATION : :: core :: time :: Duration = :: repeated_assert :: __private :: duration (\"10ms\") ; DURATION }) . name (\"eventually\")"));
+    }
+
+    #[test]
+    fn expand_flaky() {
+        let config = config(Kind::Flaky, quote!(retries = 3, teardown = cleanup)).unwrap();
+        let item = syn::parse_quote! {
+            fn flaky() {}
+        };
+
+        let tokens = expand(&config, item).unwrap().to_string();
+
+        assert!(
+            tokens.contains(". repetitions (3 + 1) . delay (:: core :: time :: Duration :: ZERO)")
+        );
+        assert!(tokens.contains("cleanup () ;"));
+        assert!(tokens.contains("run_with_context"));
+    }
+
+    #[test]
+    fn expand_arguments() {
+        let config = config(Kind::Test, quote!()).unwrap();
+        let item = syn::parse_quote! {
+            #[rstest]
+            #[case(1)]
+            fn cases(#[case] mut value: u32) {}
+        };
+
+        let tokens = expand(&config, item).unwrap().to_string();
+
+        assert!(tokens.starts_with("# [rstest] # [case (1)] fn cases (# [case] value : u32)"));
+        assert!(tokens.contains("fn __repeated_assert_test (mut value : u32)"));
+        assert!(tokens.contains("let value = :: core :: clone :: Clone :: clone (& value) ;"));
+        assert!(tokens.contains("__repeated_assert_test (value)"));
+    }
+
+    #[test]
+    fn unknown_argument() {
+        assert!(config(Kind::Test, quote!(retries = 5)).is_err());
+        assert!(config(Kind::Flaky, quote!(repetitions = 5)).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{Policy, Retry};
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{
+            AtomicBool, AtomicI16, AtomicI32, AtomicI8, AtomicIsize, AtomicU16, AtomicU32,
+            AtomicU8, AtomicUsize, Ordering,
+        },
+        Arc,
+    },
+};
+
+/// Atomic values that can be loaded by [`eventually_eq_atomic`].
+pub trait AtomicLoad {
+    /// Type of the loaded value.
+    type Value: PartialEq + Debug;
+
+    /// Load the current value.
+    fn load_value(&self) -> Self::Value;
+}
+
+macro_rules! impl_atomic_load {
+    ($($atomic:ty => $value:ty),* $(,)?) => {
+        $(
+            impl AtomicLoad for $atomic {
+                type Value = $value;
+
+                fn load_value(&self) -> $value {
+                    self.load(Ordering::SeqCst)
+                }
+            }
+        )*
+    };
+}
+
+impl_atomic_load! {
+    AtomicBool => bool,
+    AtomicI8 => i8,
+    AtomicI16 => i16,
+    AtomicI32 => i32,
+    AtomicIsize => isize,
+    AtomicU8 => u8,
+    AtomicU16 => u16,
+    AtomicU32 => u32,
+    AtomicUsize => usize,
+}
+
+#[cfg(target_has_atomic = "64")]
+impl_atomic_load! {
+    std::sync::atomic::AtomicI64 => i64,
+    std::sync::atomic::AtomicU64 => u64,
+}
+
+impl<A: AtomicLoad + ?Sized> AtomicLoad for Arc<A> {
+    type Value = A::Value;
+
+    fn load_value(&self) -> A::Value {
+        (**self).load_value()
+    }
+}
+
+/// Wait with the retry strategy of `policy` until `atomic` holds the value `expected`.
+///
+/// Panics with the last observed value if it never held the expected value.
+///
+/// # Examples
+///
+/// ```rust
+/// # use repeated_assert::Policy;
+/// # use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+/// # use std::thread;
+/// # fn spawn_workers(counter: Arc<AtomicUsize>) {
+/// #     for _ in 0..3 {
+/// #         let counter = counter.clone();
+/// #         thread::spawn(move || counter.fetch_add(1, Ordering::SeqCst));
+/// #     }
+/// # }
+/// let counter = Arc::new(AtomicUsize::new(0));
+/// spawn_workers(counter.clone());
+///
+/// repeated_assert::eventually_eq_atomic(&counter, 3, Policy::default());
+/// ```
+#[track_caller]
+pub fn eventually_eq_atomic<A>(atomic: &A, expected: A::Value, policy: Policy)
+where
+    A: AtomicLoad + ?Sized,
+{
+    Retry::with_policy(policy).run(|| {
+        let value = atomic.load_value();
+        assert!(
+            value == expected,
+            "atomic value doesn't match\n  expected: {:?}\nlast value: {:?}",
+            expected,
+            value
+        );
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::policy;
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn eventually_eq_atomic_success() {
+        let counter = Arc::new(AtomicUsize::new(0));
+
+        let worker_counter = counter.clone();
+        thread::spawn(move || {
+            for _ in 0..3 {
+                thread::sleep(Duration::from_millis(10));
+                worker_counter.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        eventually_eq_atomic(&counter, 3, policy());
+    }
+
+    #[test]
+    #[should_panic(expected = "atomic value doesn't match\n  expected: true\nlast value: false")]
+    fn eventually_eq_atomic_failure() {
+        eventually_eq_atomic(&AtomicBool::new(false), true, policy());
+    }
+}
@@ -0,0 +1,245 @@
+//! Run a command repeatedly until it succeeds.
+//!
+//! ```text
+//! repeated-assert --tries 10 --delay 500ms -- curl -f http://localhost:8080/health
+//! ```
+use repeated_assert::Retry;
+use std::{
+    env,
+    ffi::OsString,
+    fmt, io,
+    process::{self, Command, ExitStatus},
+    time::Duration,
+};
+
+const USAGE: &str = "\
+Run a command repeatedly until it succeeds
+
+Usage: repeated-assert [OPTIONS] [--] <COMMAND>...
+
+Options:
+  -n, --tries <N>            Run the command up to N times
+  -d, --delay <DURATION>     Wait DURATION between tries (e.g. 100us, 500ms, 1m30s)
+  -t, --timeout <DURATION>   Stop re-trying once DURATION has passed
+  -b, --backoff <FACTOR>     Multiply the delay by FACTOR after every try
+  -q, --quiet                Don't report failed tries
+  -h, --help                 Print help
+
+The environment variables of the repeated-assert crate (e.g. REPEATED_ASSERT_TIME_SCALE) apply as well.
+Exits with the exit code of the last try if the command never succeeded.";
+
+/// Command line arguments.
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    tries: Option<usize>,
+    delay: Option<Duration>,
+    timeout: Option<Duration>,
+    backoff: Option<f64>,
+    quiet: bool,
+    command: Vec<OsString>,
+}
+
+impl Args {
+    fn parse<I>(args: I) -> Result<Args, String>
+    where
+        I: IntoIterator<Item = OsString>,
+    {
+        let mut args = args.into_iter();
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next() {
+            let flag = match arg.to_str() {
+                Some(flag) if flag.starts_with('-') => flag.to_string(),
+                _ => {
+                    parsed.command.push(arg);
+                    break;
+                }
+            };
+            let mut value = || {
+                args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .ok_or_else(|| format!("missing value of {}", flag))
+            };
+            match flag.as_str() {
+                "--" => break,
+                "-n" | "--tries" => parsed.tries = Some(parse(&flag, &value()?)?),
+                "-d" | "--delay" => parsed.delay = Some(parse_duration(&value()?)?),
+                "-t" | "--timeout" => parsed.timeout = Some(parse_duration(&value()?)?),
+                "-b" | "--backoff" => parsed.backoff = Some(parse_backoff(&flag, &value()?)?),
+                "-q" | "--quiet" => parsed.quiet = true,
+                "-h" | "--help" => return Err(String::new()),
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+        }
+        parsed.command.extend(args);
+        if parsed.command.is_empty() {
+            return Err("missing command".to_string());
+        }
+        Ok(parsed)
+    }
+
+    fn retry(&self) -> Retry<'static> {
+        let mut retry = Retry::new();
+        if let Some(tries) = self.tries {
+            retry = retry.repetitions(tries);
+        }
+        if let Some(delay) = self.delay {
+            retry = retry.delay(delay);
+        }
+        if let Some(timeout) = self.timeout {
+            retry = retry.timeout(timeout);
+        }
+        if let Some(backoff) = self.backoff {
+            retry = retry.backoff(backoff);
+        }
+        retry
+    }
+}
+
+fn parse<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value of {}: {}", flag, value))
+}
+
+/// Parse a backoff factor, which has to be finite and not negative.
+fn parse_backoff(flag: &str, value: &str) -> Result<f64, String> {
+    match parse::<f64>(flag, value)? {
+        factor if factor.is_finite() && factor >= 0.0 => Ok(factor),
+        _ => Err(format!("invalid value of {}: {}", flag, value)),
+    }
+}
+
+/// Parse durations like `500ms`, `1.5s` or `1m30s`, with the same grammar as the durations of the macros.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    repeated_assert::__private::parse_duration(value.as_bytes())
+        .map_err(|error| format!("{}: {}", error, value))
+}
+
+/// Reason a try failed.
+#[derive(Debug)]
+enum Failure {
+    Spawn(io::Error),
+    Status(ExitStatus),
+}
+
+impl Failure {
+    fn exit_code(&self) -> i32 {
+        match self {
+            // like shells, if the command can't be run
+            Failure::Spawn(_) => 127,
+            Failure::Status(status) => status.code().unwrap_or(1),
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Spawn(error) => write!(f, "failed to run command: {}", error),
+            Failure::Status(status) => write!(f, "{}", status),
+        }
+    }
+}
+
+fn main() {
+    let args = match Args::parse(env::args_os().skip(1)) {
+        Ok(args) => args,
+        Err(error) if error.is_empty() => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(error) => {
+            eprintln!("repeated-assert: {}\n\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let program = args.command[0].to_string_lossy().into_owned();
+    let mut tries = 0;
+    let result = args.retry().run_result(|| {
+        tries += 1;
+        let failure = match Command::new(&args.command[0])
+            .args(&args.command[1..])
+            .status()
+        {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(status) => Failure::Status(status),
+            Err(error) => Failure::Spawn(error),
+        };
+        if !args.quiet {
+            eprintln!(
+                "repeated-assert: try {} of `{}` failed: {}",
+                tries, program, failure
+            );
+        }
+        Err(failure)
+    });
+
+    if let Err(failure) = result {
+        eprintln!(
+            "repeated-assert: `{}` failed after {} tries: {}",
+            program, tries, failure
+        );
+        process::exit(failure.exit_code());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(OsString::from))
+    }
+
+    #[test]
+    fn parse_args() {
+        let parsed = args(&[
+            "--tries",
+            "5",
+            "-d",
+            "500ms",
+            "--",
+            "curl",
+            "-f",
+            "localhost",
+        ])
+        .unwrap();
+
+        assert_eq!(parsed.tries, Some(5));
+        assert_eq!(parsed.delay, Some(Duration::from_millis(500)));
+        assert_eq!(parsed.command, ["curl", "-f", "localhost"]);
+        assert_eq!(
+            args(&["-q", "true", "--tries"]).unwrap().command,
+            ["true", "--tries"]
+        );
+        assert_eq!(
+            args(&["--tries", "five", "true"]).unwrap_err(),
+            "invalid value of --tries: five"
+        );
+        assert_eq!(args(&["--tries", "5"]).unwrap_err(), "missing command");
+        assert_eq!(args(&["-b", "1.5", "true"]).unwrap().backoff, Some(1.5));
+        assert_eq!(
+            args(&["-b", "-1", "true"]).unwrap_err(),
+            "invalid value of -b: -1"
+        );
+        assert_eq!(
+            args(&["--backoff", "NaN", "true"]).unwrap_err(),
+            "invalid value of --backoff: NaN"
+        );
+    }
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("100us"), Ok(Duration::from_micros(100)));
+        assert_eq!(parse_duration("1m30s"), Ok(Duration::from_secs(90)));
+        assert_eq!(
+            parse_duration("500").unwrap_err(),
+            "invalid duration: expected a unit (`ns`, `us`, `ms`, `s`, `m` or `h`): 500"
+        );
+        assert!(parse_duration("fast").is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use gag::BufferRedirect;
+use std::io::{self, Read, Write};
+
+/// Output of the tries replayed when the assertions fail, see [`Retry::capture_output`](crate::Retry::capture_output).
+#[cfg_attr(docsrs, doc(cfg(feature = "capture")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Replay {
+    /// Replay the output of the last try.
+    LastTry,
+    /// Replay the output of all tries.
+    AllTries,
+}
+
+/// Output written to stdout and stderr during the tries.
+#[derive(Debug)]
+pub(crate) struct Capture {
+    replay: Replay,
+    tries: Vec<Output>,
+}
+
+#[derive(Debug)]
+struct Output {
+    tries: usize,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+impl Capture {
+    pub(crate) fn new(replay: Replay) -> Capture {
+        Capture {
+            replay,
+            tries: Vec::new(),
+        }
+    }
+
+    /// Call `f`, capturing the output written to stdout and stderr.
+    ///
+    /// If the output is already redirected (e.g. by another thread capturing its tries), it isn't captured.
+    pub(crate) fn run<F, T>(&mut self, tries: usize, f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        let stdout = BufferRedirect::stdout().ok();
+        let stderr = BufferRedirect::stderr().ok();
+        let value = f();
+        let _ = io::stdout().flush();
+        let _ = io::stderr().flush();
+        if self.replay == Replay::LastTry {
+            self.tries.clear();
+        }
+        self.tries.push(Output {
+            tries,
+            stdout: read(stdout),
+            stderr: read(stderr),
+        });
+        value
+    }
+
+    /// Write the captured output to stdout and stderr.
+    pub(crate) fn replay(&mut self) {
+        for output in self.tries.drain(..) {
+            if !output.stdout.is_empty() {
+                println!("---- stdout of try {} ----", output.tries);
+                print!("{}", String::from_utf8_lossy(&output.stdout));
+            }
+            if !output.stderr.is_empty() {
+                eprintln!("---- stderr of try {} ----", output.tries);
+                eprint!("{}", String::from_utf8_lossy(&output.stderr));
+            }
+        }
+    }
+}
+
+/// Stop redirecting and read the captured output.
+fn read(redirect: Option<BufferRedirect>) -> Vec<u8> {
+    let mut output = Vec::new();
+    if let Some(redirect) = redirect {
+        let _ = redirect.into_inner().read_to_end(&mut output);
+    }
+    output
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::{Mutex, MutexGuard, PoisonError};
+
+    /// Serialize the tests redirecting the output, since only one redirect can be active at a time.
+    pub(crate) fn lock() -> MutexGuard<'static, ()> {
+        static LOCK: Mutex<()> = Mutex::new(());
+        LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[test]
+    fn capture_output() {
+        let _lock = lock();
+        let mut capture = Capture::new(Replay::LastTry);
+
+        // written to the file descriptors directly, since the test harness captures `print!`
+        capture.run(1, || {
+            write!(io::stdout(), "out").unwrap();
+            writeln!(io::stderr(), "err").unwrap();
+        });
+
+        assert_eq!(capture.tries[0].stdout, b"out");
+        assert_eq!(capture.tries[0].stderr, b"err\n");
+    }
+
+    #[test]
+    fn keep_last_try() {
+        let _lock = lock();
+        let mut capture = Capture::new(Replay::LastTry);
+
+        for tries in 1..=3 {
+            let value = capture.run(tries, || tries * 2);
+            assert_eq!(value, tries * 2);
+        }
+
+        assert_eq!(capture.tries.len(), 1);
+        assert_eq!(capture.tries[0].tries, 3);
+    }
+
+    #[test]
+    fn keep_all_tries() {
+        let _lock = lock();
+        let mut capture = Capture::new(Replay::AllTries);
+
+        for tries in 1..=3 {
+            capture.run(tries, || {});
+        }
+
+        let tries: Vec<_> = capture.tries.iter().map(|output| output.tries).collect();
+        assert_eq!(tries, [1, 2, 3]);
+        capture.replay();
+        assert!(capture.tries.is_empty());
+    }
+}
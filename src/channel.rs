@@ -0,0 +1,306 @@
+//! Waiting for messages of channels
+//!
+//! Receiving inside a repeated assertion is error-prone, since messages get consumed and lost between tries.
+//! The functions of this module keep the received messages across tries and include them in the panic message.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use repeated_assert::Policy;
+//! # use std::sync::mpsc;
+//! let (tx, rx) = mpsc::channel();
+//! tx.send("starting".to_string()).unwrap();
+//! tx.send("ready on port 8080".to_string()).unwrap();
+//!
+//! let message = repeated_assert::channel::recv_matching(&rx, Policy::default(), |message| {
+//!     message.starts_with("ready")
+//! });
+//! ```
+//!
+//! With the `tokio` feature, the same is available for the channels of `tokio::sync`.
+//!
+//! ```rust
+//! # use repeated_assert::Policy;
+//! # #[derive(Debug, Clone, PartialEq)]
+//! # enum Status { Running }
+//! # #[cfg(feature = "tokio")]
+//! # async fn example(mut rx: tokio::sync::watch::Receiver<Status>) {
+//! let status = repeated_assert::channel::watch_until(&mut rx, Policy::default(), |status| {
+//!     *status == Status::Running
+//! })
+//! .await;
+//! # }
+//! ```
+use crate::{Policy, Retry};
+#[cfg(feature = "tokio")]
+use std::future::Future;
+use std::{
+    fmt::Debug,
+    sync::mpsc::{Receiver, TryRecvError},
+};
+
+/// Receive messages from `rx` with the retry strategy of `policy`, until one of them matches `predicate`.
+///
+/// Returns the first matching message. Messages that don't match are dropped.
+///
+/// # Panics
+///
+/// Panics with the messages that were received if none of them matched.
+#[track_caller]
+pub fn recv_matching<T, P>(rx: &Receiver<T>, policy: Policy, mut predicate: P) -> T
+where
+    T: Debug,
+    P: FnMut(&T) -> bool,
+{
+    let mut seen = Vec::new();
+    Retry::with_policy(policy).run(|| {
+        let disconnected = loop {
+            match rx.try_recv() {
+                Ok(message) if predicate(&message) => return message,
+                Ok(message) => seen.push(message),
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
+            }
+        };
+        panic!(
+            "{}",
+            unmatched(&seen, disconnected.then_some("channel disconnected"))
+        );
+    })
+}
+
+/// Receive messages from the tokio `mpsc` channel `rx` with the retry strategy of `policy`, until one of them matches `predicate`.
+///
+/// # Info
+///
+/// See [`recv_matching`].
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+#[track_caller]
+pub fn recv_matching_async<'a, T, P>(
+    rx: &'a mut tokio::sync::mpsc::Receiver<T>,
+    policy: Policy,
+    mut predicate: P,
+) -> impl Future<Output = T> + 'a
+where
+    T: Debug + 'a,
+    P: FnMut(&T) -> bool + 'a,
+{
+    use tokio::sync::mpsc::error::TryRecvError;
+
+    let mut seen = Vec::new();
+    Retry::with_policy(policy).run_async(move || {
+        let result = loop {
+            match rx.try_recv() {
+                Ok(message) if predicate(&message) => break Ok(message),
+                Ok(message) => seen.push(message),
+                Err(TryRecvError::Empty) => break Err(unmatched(&seen, None)),
+                Err(TryRecvError::Disconnected) => {
+                    break Err(unmatched(&seen, Some("channel disconnected")))
+                }
+            }
+        };
+        async move { result.unwrap_or_else(|message| panic!("{}", message)) }
+    })
+}
+
+/// Receive messages from the tokio `broadcast` channel `rx` with the retry strategy of `policy`, until one of them matches `predicate`.
+///
+/// Messages the receiver lagged behind on are skipped, their number is included in the panic message.
+///
+/// # Info
+///
+/// See [`recv_matching`].
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+#[track_caller]
+pub fn broadcast_recv_matching<'a, T, P>(
+    rx: &'a mut tokio::sync::broadcast::Receiver<T>,
+    policy: Policy,
+    mut predicate: P,
+) -> impl Future<Output = T> + 'a
+where
+    T: Clone + Debug + 'a,
+    P: FnMut(&T) -> bool + 'a,
+{
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    let mut seen = Vec::new();
+    let mut skipped = 0;
+    Retry::with_policy(policy).run_async(move || {
+        let result = loop {
+            match rx.try_recv() {
+                Ok(message) if predicate(&message) => break Ok(message),
+                Ok(message) => seen.push(message),
+                Err(TryRecvError::Lagged(count)) => skipped += count,
+                Err(error) => {
+                    let closed = matches!(error, TryRecvError::Closed);
+                    let note = match (closed, skipped) {
+                        (false, 0) => None,
+                        (false, _) => Some(format!("skipped {} lagged messages", skipped)),
+                        (true, 0) => Some("channel closed".to_string()),
+                        (true, _) => Some(format!(
+                            "channel closed, skipped {} lagged messages",
+                            skipped
+                        )),
+                    };
+                    break Err(unmatched(&seen, note.as_deref()));
+                }
+            }
+        };
+        async move { result.unwrap_or_else(|message| panic!("{}", message)) }
+    })
+}
+
+/// Wait with the retry strategy of `policy` until the value of the tokio `watch` channel `rx` matches `predicate`.
+///
+/// Returns a clone of the matching value.
+///
+/// # Panics
+///
+/// Panics with the last observed value if it never matched.
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+#[track_caller]
+pub fn watch_until<'a, T, P>(
+    rx: &'a tokio::sync::watch::Receiver<T>,
+    policy: Policy,
+    mut predicate: P,
+) -> impl Future<Output = T> + 'a
+where
+    T: Clone + Debug + 'a,
+    P: FnMut(&T) -> bool + 'a,
+{
+    Retry::with_policy(policy).run_async(move || {
+        let value = rx.borrow().clone();
+        let matched = predicate(&value);
+        async move {
+            assert!(
+                matched,
+                "watched value doesn't match, last value: {:?}",
+                value
+            );
+            value
+        }
+    })
+}
+
+/// Format the panic message listing the messages that didn't match.
+fn unmatched<T: Debug>(seen: &[T], note: Option<&str>) -> String {
+    match note {
+        Some(note) => format!(
+            "no matching message received ({}), seen {} messages: {:?}",
+            note,
+            seen.len(),
+            seen
+        ),
+        None => format!(
+            "no matching message received, seen {} messages: {:?}",
+            seen.len(),
+            seen
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::policy;
+    use std::{sync::mpsc, thread, time::Duration};
+
+    #[test]
+    fn recv_matching_success() {
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            for i in 0..5 {
+                tx.send(i).unwrap();
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+
+        assert_eq!(recv_matching(&rx, policy(), |message| *message >= 3), 3);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "no matching message received (channel disconnected), seen 3 messages: [0, 1, 2]"
+    )]
+    fn recv_matching_failure() {
+        let (tx, rx) = mpsc::channel();
+
+        for i in 0..3 {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+
+        recv_matching(&rx, policy(), |message| *message >= 3);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn recv_matching_async_success() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+
+        tokio::spawn(async move {
+            for i in 0..5 {
+                tx.send(i).await.unwrap();
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+
+        let message = recv_matching_async(&mut rx, policy(), |message| *message >= 3).await;
+        assert_eq!(message, 3);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    #[should_panic(expected = "no matching message received, seen 2 messages: [0, 1]")]
+    async fn recv_matching_async_failure() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+        tx.send(0).await.unwrap();
+        tx.send(1).await.unwrap();
+
+        recv_matching_async(&mut rx, policy(), |message| *message >= 3).await;
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    #[should_panic(
+        expected = "no matching message received (skipped 2 lagged messages), seen 2 messages: [2, 3]"
+    )]
+    async fn broadcast_recv_matching_failure() {
+        let (tx, mut rx) = tokio::sync::broadcast::channel(2);
+        for i in 0..4 {
+            tx.send(i).unwrap();
+        }
+
+        broadcast_recv_matching(&mut rx, policy(), |message| *message >= 4).await;
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn watch_until_success() {
+        let (tx, rx) = tokio::sync::watch::channel(0);
+
+        tokio::spawn(async move {
+            for i in 1..5 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                tx.send(i).unwrap();
+            }
+            // keep the channel open
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        });
+
+        assert!(watch_until(&rx, policy(), |value| *value >= 3).await >= 3);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    #[should_panic(expected = "watched value doesn't match, last value: 1")]
+    async fn watch_until_failure() {
+        let (_tx, rx) = tokio::sync::watch::channel(1);
+
+        watch_until(&rx, policy(), |value| *value >= 3).await;
+    }
+}
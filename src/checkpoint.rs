@@ -0,0 +1,320 @@
+use crate::{
+    error::panic_message,
+    hook::{self, IgnoreGuard},
+    Retry,
+};
+use std::{
+    fmt::{self, Write as _},
+    panic::{self, Location},
+    time::Duration,
+};
+
+type Assert<'a> = Box<dyn FnMut() + 'a>;
+
+/// Builder to wait for multiple independent conditions under one retry strategy.
+///
+/// All conditions are polled on every try, until each of them passed once.
+/// If some never passed, the panic message tells which ones.
+///
+/// # Examples
+///
+/// ```rust
+/// # use repeated_assert::Retry;
+/// # use std::time::Duration;
+/// # struct Service;
+/// # impl Service {
+/// #     fn is_ready(&self) -> bool { true }
+/// # }
+/// # let (db, cache) = (Service, Service);
+/// repeated_assert::Checkpoint::new()
+///     .check("db", || {
+///         assert!(db.is_ready());
+///     })
+///     .check("cache", || {
+///         assert!(cache.is_ready());
+///     })
+///     .retry(Retry::new().timeout(Duration::from_secs(5)))
+///     .wait_all();
+/// ```
+pub struct Checkpoint<'a> {
+    checks: Vec<Check<'a>>,
+    retry: Option<Retry<'a>>,
+    location: &'static Location<'static>,
+}
+
+struct Check<'a> {
+    name: String,
+    assert: Assert<'a>,
+    passed: Option<(usize, Duration)>,
+    message: Option<String>,
+}
+
+impl<'a> Checkpoint<'a> {
+    /// Create a new builder without conditions.
+    ///
+    /// The location of the caller is included in the panic message if conditions don't pass.
+    #[track_caller]
+    pub fn new() -> Checkpoint<'a> {
+        Checkpoint {
+            checks: Vec::new(),
+            retry: None,
+            location: Location::caller(),
+        }
+    }
+
+    /// Add the condition `assert` named `name`.
+    pub fn check<N, A, R>(mut self, name: N, mut assert: A) -> Checkpoint<'a>
+    where
+        N: Into<String>,
+        A: FnMut() -> R + 'a,
+    {
+        self.checks.push(Check {
+            name: name.into(),
+            assert: Box::new(move || {
+                assert();
+            }),
+            passed: None,
+            message: None,
+        });
+        self
+    }
+
+    /// Poll the conditions with the retry strategy of `retry`.
+    ///
+    /// Defaults to the [default policy](crate::default_policy).
+    pub fn retry(mut self, retry: Retry<'a>) -> Checkpoint<'a> {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Wait until every condition passed once, panicking with the conditions that never passed otherwise.
+    pub fn wait_all(mut self) {
+        let retry = self.retry.take().unwrap_or_default();
+        let clock = retry.shared_clock();
+        let start = clock.now();
+
+        // add current thread to ignore list
+        let ignore_guard = IgnoreGuard::new();
+
+        let (result, tries, elapsed) = retry.rounds(|tries| {
+            for check in self
+                .checks
+                .iter_mut()
+                .filter(|check| check.passed.is_none())
+            {
+                match panic::catch_unwind(panic::AssertUnwindSafe(&mut check.assert)) {
+                    Ok(()) => check.passed = Some((tries, clock.elapsed(start))),
+                    Err(payload) => check.message = Some(panic_message(&*payload)),
+                }
+            }
+            self.checks
+                .iter()
+                .all(|check| check.passed.is_some())
+                .then_some(())
+        });
+
+        // remove current thread from ignore list
+        drop(ignore_guard);
+        // forget the location of the last failure
+        hook::take_panic_location();
+
+        if result.is_none() {
+            let failed = self
+                .checks
+                .iter()
+                .filter(|check| check.passed.is_none())
+                .count();
+            self.fail(format_args!(
+                "{} of {} conditions never passed after {} tries in {:?}",
+                failed,
+                self.checks.len(),
+                tries,
+                elapsed
+            ));
+        }
+    }
+
+    /// Wait until any condition passes, returning its name.
+    ///
+    /// Panics if none of the conditions passed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[derive(Debug, PartialEq)]
+    /// # enum State { Completed, Cancelled }
+    /// # struct Job;
+    /// # impl Job {
+    /// #     fn state(&self) -> State { State::Completed }
+    /// # }
+    /// # let job = Job;
+    /// let state = repeated_assert::Checkpoint::new()
+    ///     .check("completed", || {
+    ///         assert_eq!(job.state(), State::Completed);
+    ///     })
+    ///     .check("cancelled", || {
+    ///         assert_eq!(job.state(), State::Cancelled);
+    ///     })
+    ///     .wait_any();
+    /// ```
+    pub fn wait_any(mut self) -> String {
+        let retry = self.retry.take().unwrap_or_default();
+        let clock = retry.shared_clock();
+        let start = clock.now();
+
+        // add current thread to ignore list
+        let ignore_guard = IgnoreGuard::new();
+
+        let (result, tries, elapsed) = retry.rounds(|tries| {
+            for (index, check) in self.checks.iter_mut().enumerate() {
+                match panic::catch_unwind(panic::AssertUnwindSafe(&mut check.assert)) {
+                    Ok(()) => {
+                        check.passed = Some((tries, clock.elapsed(start)));
+                        return Some(index);
+                    }
+                    Err(payload) => check.message = Some(panic_message(&*payload)),
+                }
+            }
+            None
+        });
+
+        // remove current thread from ignore list
+        drop(ignore_guard);
+        // forget the location of the last failure
+        hook::take_panic_location();
+
+        match result {
+            Some(index) => self.checks.swap_remove(index).name,
+            None => self.fail(format_args!(
+                "none of {} conditions passed after {} tries in {:?}",
+                self.checks.len(),
+                tries,
+                elapsed
+            )),
+        }
+    }
+
+    /// Panic with the `outcome` and the conditions that never passed.
+    fn fail(&self, outcome: fmt::Arguments<'_>) -> ! {
+        let mut message = format!("repeated-assert: {}", outcome);
+        for check in &self.checks {
+            match (check.passed, &check.message) {
+                (Some((tries, elapsed)), _) => write!(
+                    message,
+                    "\n  `{}` passed on try {} after {:?}",
+                    check.name, tries, elapsed
+                ),
+                (None, Some(last_message)) => write!(
+                    message,
+                    "\n  `{}` never passed: {}",
+                    check.name,
+                    last_message.replace('\n', "\n    ")
+                ),
+                (None, None) => write!(message, "\n  `{}` never passed", check.name),
+            }
+            .unwrap();
+        }
+        write!(message, "\n  called at {}", self.location).unwrap();
+        panic!("{}", message)
+    }
+}
+
+impl Default for Checkpoint<'_> {
+    #[track_caller]
+    fn default() -> Self {
+        Checkpoint::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[test]
+    fn wait_all_success() {
+        let mut a = 0;
+        let mut b = 0;
+
+        Checkpoint::new()
+            .check("a", || {
+                a += 1;
+                assert!(a > 3);
+            })
+            .check("b", || {
+                b += 1;
+                assert!(b > 1);
+            })
+            .retry(Retry::new().repetitions(5).delay(Duration::from_millis(10)))
+            .wait_all();
+
+        // conditions aren't polled anymore once they passed
+        assert_eq!((a, b), (4, 2));
+    }
+
+    #[test]
+    fn wait_any_success() {
+        let mut tries = 0;
+
+        let name = Checkpoint::new()
+            .check("never", || {
+                panic!("not yet");
+            })
+            .check("eventually", || {
+                tries += 1;
+                assert!(tries > 2);
+            })
+            .retry(Retry::new().repetitions(5).delay(Duration::from_millis(10)))
+            .wait_any();
+
+        assert_eq!(name, "eventually");
+    }
+
+    #[test]
+    #[should_panic(expected = "repeated-assert: none of 2 conditions passed after 3 tries")]
+    fn wait_any_failure() {
+        Checkpoint::new()
+            .check("a", || {
+                panic!("not yet");
+            })
+            .check("b", || {
+                panic!("not yet");
+            })
+            .retry(Retry::new().repetitions(3).delay(Duration::from_millis(10)))
+            .wait_any();
+    }
+
+    #[test]
+    #[should_panic(expected = "`eventually` passed on try 3 after 2s")]
+    fn wait_all_clock() {
+        let mut tries = 0;
+
+        Checkpoint::new()
+            .check("eventually", || {
+                tries += 1;
+                assert!(tries > 2);
+            })
+            .check("never", || {
+                panic!("not yet");
+            })
+            .retry(
+                Retry::new()
+                    .repetitions(3)
+                    .delay(Duration::from_secs(1))
+                    .clock(MockClock::new()),
+            )
+            .wait_all();
+    }
+
+    #[test]
+    #[should_panic(expected = "repeated-assert: 1 of 2 conditions never passed after 3 tries")]
+    fn wait_all_failure() {
+        Checkpoint::new()
+            .check("ready", || {})
+            .check("never", || {
+                panic!("not yet");
+            })
+            .retry(Retry::new().repetitions(3).delay(Duration::from_millis(10)))
+            .wait_all();
+    }
+}
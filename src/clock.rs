@@ -0,0 +1,266 @@
+//! Time source of the retry loop
+//!
+//! The retry loop reads the time and waits between tries using a [`Clock`], which can be set with
+//! [`Retry::clock`](crate::Retry::clock). By default it's the [`SystemClock`].
+//!
+//! A [`MockClock`] doesn't sleep, but advances its time instantly, so tests of code using retries can check timeouts
+//! and delays without waiting for them.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use repeated_assert::clock::MockClock;
+//! # use std::time::Duration;
+//! # struct Service;
+//! # impl Service {
+//! #     fn is_ready(&self) -> bool { false }
+//! # }
+//! # let service = Service;
+//! let clock = MockClock::new();
+//!
+//! let result = repeated_assert::Retry::new()
+//!     .clock(clock.clone())
+//!     .delay(Duration::from_secs(1))
+//!     .timeout(Duration::from_secs(60))
+//!     .try_run(|| assert!(service.is_ready()));
+//!
+//! assert!(result.is_err());
+//! assert_eq!(clock.elapsed(), Duration::from_secs(60));
+//! ```
+use crate::Instant;
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::Duration,
+};
+
+/// Source of the current time, which can also wait.
+pub trait Clock: Send + Sync {
+    /// Return the current time.
+    fn now(&self) -> Instant;
+
+    /// Block the current thread for `duration`.
+    fn sleep(&self, duration: Duration);
+
+    /// Time passed since `earlier`, or zero if it's in the future.
+    fn elapsed(&self, earlier: Instant) -> Duration {
+        self.now().saturating_duration_since(earlier)
+    }
+}
+
+/// The real time, using [`Instant::now`] and [`thread::sleep`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// The real time, spinning for the end of short delays to wait more precisely.
+///
+/// Delays below the `threshold` are waited with [`spin_sleep`](https://docs.rs/spin_sleep), which sleeps as far as
+/// the operating system is accurate and spins for the rest. Longer delays use [`thread::sleep`].
+///
+/// # Examples
+///
+/// ```rust
+/// # use repeated_assert::clock::PreciseClock;
+/// # use std::{sync::mpsc, time::Duration};
+/// # #[derive(Debug, PartialEq)]
+/// # enum Message { Ack }
+/// # let (tx, channel) = mpsc::channel();
+/// # tx.send(Message::Ack).unwrap();
+/// repeated_assert::Retry::new()
+///     .clock(PreciseClock::new(Duration::from_millis(1)))
+///     .delay(Duration::from_micros(100))
+///     .run(|| {
+///         assert_eq!(channel.try_recv(), Ok(Message::Ack));
+///     });
+/// ```
+#[cfg(feature = "precise-sleep")]
+#[cfg_attr(docsrs, doc(cfg(feature = "precise-sleep")))]
+#[derive(Debug, Clone, Copy)]
+pub struct PreciseClock {
+    threshold: Duration,
+}
+
+#[cfg(feature = "precise-sleep")]
+impl PreciseClock {
+    /// Wait precisely for delays below `threshold`.
+    pub fn new(threshold: Duration) -> PreciseClock {
+        PreciseClock { threshold }
+    }
+}
+
+#[cfg(feature = "precise-sleep")]
+impl Default for PreciseClock {
+    /// Wait precisely for delays below 10 ms.
+    fn default() -> PreciseClock {
+        PreciseClock::new(Duration::from_millis(10))
+    }
+}
+
+#[cfg(feature = "precise-sleep")]
+impl Clock for PreciseClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        if duration < self.threshold {
+            spin_sleep::sleep(duration);
+        } else {
+            thread::sleep(duration);
+        }
+    }
+}
+
+/// The time of the tokio runtime, which follows [paused time](https://docs.rs/tokio/latest/tokio/time/fn.pause.html).
+///
+/// It's used by the async functions if neither a clock nor a sleeper is set, so timeouts work in tests with
+/// `#[tokio::test(start_paused = true)]`, where the tokio sleeper advances the time instead of waiting.
+/// Blocking sleeps wait in real time.
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tokio;
+
+#[cfg(all(feature = "tokio", not(all(feature = "wasm", target_arch = "wasm32"))))]
+impl Clock for Tokio {
+    fn now(&self) -> Instant {
+        tokio::time::Instant::now().into_std()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Clock for tests, which advances instantly instead of sleeping.
+///
+/// Clones share the same time, so a clone can be passed to [`Retry::clock`](crate::Retry::clock)
+/// and the original used to inspect the sleeps afterwards.
+///
+/// With the `async` feature it's also a [`Sleeper`](crate::sleep::Sleeper), which has to be passed to
+/// [`Retry::sleeper`](crate::Retry::sleeper) as well for the async functions.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug)]
+struct MockState {
+    start: Instant,
+    now: Instant,
+    sleeps: Vec<Duration>,
+}
+
+impl MockClock {
+    /// Create a new clock starting at the current time.
+    pub fn new() -> MockClock {
+        let now = Instant::now();
+        MockClock {
+            state: Arc::new(Mutex::new(MockState {
+                start: now,
+                now,
+                sleeps: Vec::new(),
+            })),
+        }
+    }
+
+    /// Advance the time by `duration`, without recording it as sleep.
+    pub fn advance(&self, duration: Duration) {
+        self.with_state(|state| state.now += duration);
+    }
+
+    /// Time passed since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        self.with_state(|state| state.now - state.start)
+    }
+
+    /// Durations of all sleeps so far, in order.
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.with_state(|state| state.sleeps.clone())
+    }
+
+    fn with_state<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut MockState) -> T,
+    {
+        f(&mut self.state.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> MockClock {
+        MockClock::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.with_state(|state| state.now)
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.with_state(|state| {
+            state.now += duration;
+            state.sleeps.push(duration);
+        });
+    }
+}
+
+#[cfg(feature = "async")]
+impl crate::sleep::Sleeper for MockClock {
+    fn sleep(&self, duration: Duration) -> futures::future::BoxFuture<'static, ()> {
+        Clock::sleep(self, duration);
+        Box::pin(futures::future::ready(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mock_clock() {
+        let clock = MockClock::new();
+        let start = clock.now();
+
+        Clock::sleep(&clock, Duration::from_secs(60));
+        clock.advance(Duration::from_secs(1));
+        clock.clone().sleep(Duration::from_secs(2));
+
+        assert_eq!(clock.elapsed(), Duration::from_secs(63));
+        assert_eq!(Clock::elapsed(&clock, start), Duration::from_secs(63));
+        assert_eq!(
+            clock.sleeps(),
+            [Duration::from_secs(60), Duration::from_secs(2)]
+        );
+    }
+
+    #[test]
+    fn system_clock() {
+        let start = SystemClock.now();
+        SystemClock.sleep(Duration::from_millis(50));
+        assert!(SystemClock.elapsed(start) >= Duration::from_millis(50));
+    }
+
+    #[cfg(feature = "precise-sleep")]
+    #[test]
+    fn precise_clock() {
+        let clock = PreciseClock::default();
+
+        for delay in [Duration::from_micros(200), Duration::from_millis(20)] {
+            let start = clock.now();
+            clock.sleep(delay);
+            assert!(clock.elapsed(start) >= delay);
+        }
+    }
+}
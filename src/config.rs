@@ -0,0 +1,372 @@
+use crate::output;
+use std::{
+    env,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock, RwLock,
+    },
+    time::Duration,
+};
+
+pub(crate) const DEFAULT_REPETITIONS: usize = 10;
+pub(crate) const DEFAULT_DELAY: Duration = Duration::from_millis(50);
+
+/// Environment variable overriding the number of repetitions.
+const REPETITIONS_VAR: &str = "REPEATED_ASSERT_MAX_REPETITIONS";
+/// Environment variable overriding the delay between tries, in milliseconds.
+const DELAY_VAR: &str = "REPEATED_ASSERT_DELAY_MS";
+/// Environment variable setting the initial time scale.
+const TIME_SCALE_VAR: &str = "REPEATED_ASSERT_TIME_SCALE";
+/// Environment variable enabling strict mode.
+const STRICT_VAR: &str = "REPEATED_ASSERT_STRICT";
+
+/// Retry strategy used by [`Retry::new`](crate::Retry::new) and [`that_default`](crate::that_default), see [`set_default_policy`].
+///
+/// Defaults to 10 repetitions with a delay of 50 ms in between tries.
+/// New fields can be added without a breaking change, so a policy is built from the default with the `with_*` methods.
+///
+/// # Examples
+///
+/// ```rust
+/// # use repeated_assert::Policy;
+/// # use std::time::Duration;
+/// let policy = Policy::default()
+///     .with_delay(Duration::from_millis(100))
+///     .with_backoff(2.0)
+///     .with_timeout(Duration::from_secs(5));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Policy {
+    /// Number of repetitions, `None` for 10 or unlimited if a `timeout` is set.
+    pub repetitions: Option<usize>,
+    /// Delay between tries.
+    pub delay: Duration,
+    /// Factor the delay is multiplied with after every try.
+    pub backoff: f64,
+    /// Upper limit of the delay when using a `backoff`.
+    pub max_delay: Option<Duration>,
+    /// Maximum time to re-try.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for Policy {
+    fn default() -> Policy {
+        Policy {
+            repetitions: None,
+            delay: DEFAULT_DELAY,
+            backoff: 1.0,
+            max_delay: None,
+            timeout: None,
+        }
+    }
+}
+
+impl Policy {
+    /// Run the assertions up to `repetitions` times.
+    pub fn with_repetitions(mut self, repetitions: usize) -> Policy {
+        self.repetitions = Some(repetitions);
+        self
+    }
+
+    /// Wait `delay` between tries.
+    pub fn with_delay(mut self, delay: Duration) -> Policy {
+        self.delay = delay;
+        self
+    }
+
+    /// Multiply the delay by `factor` after every try.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is negative, infinite or NaN.
+    pub fn with_backoff(mut self, factor: f64) -> Policy {
+        check_backoff(factor);
+        self.backoff = factor;
+        self
+    }
+
+    /// Limit the delay between tries to `max_delay`.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Policy {
+        self.max_delay = Some(max_delay);
+        self
+    }
+
+    /// Re-try for up to `timeout`, with unlimited repetitions unless they are set as well.
+    pub fn with_timeout(mut self, timeout: Duration) -> Policy {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+/// Panic if the backoff `factor` is negative, infinite or NaN.
+pub(crate) fn check_backoff(factor: f64) {
+    assert!(
+        factor.is_finite() && factor >= 0.0,
+        "backoff factor must be finite and not negative, got {}",
+        factor
+    );
+}
+
+fn policy() -> &'static RwLock<Policy> {
+    static INSTANCE: OnceLock<RwLock<Policy>> = OnceLock::new();
+    INSTANCE.get_or_init(|| RwLock::new(Policy::default()))
+}
+
+/// Set the retry strategy used by all repeated assertions that don't configure it explicitly.
+///
+/// Values passed in code (e.g. the `repetitions` and `delay` of [`that`](crate::that)) take precedence.
+/// Functions taking a `delay` wait exactly that long between tries, without the `backoff` and `max_delay` of the policy,
+/// and a timeout or deadline passed in code replaces the `repetitions` of the policy.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use repeated_assert::Policy;
+/// # use std::{path::Path, time::Duration};
+/// repeated_assert::set_default_policy(
+///     Policy::default()
+///         .with_delay(Duration::from_millis(10))
+///         .with_backoff(2.0)
+///         .with_timeout(Duration::from_secs(5)),
+/// );
+///
+/// repeated_assert::that_default(|| {
+///     assert!(Path::new("should_appear_soon.txt").exists());
+/// });
+/// ```
+///
+/// # Panics
+///
+/// Panics if the `backoff` of `policy` is negative, infinite or NaN.
+pub fn set_default_policy(policy: Policy) {
+    check_backoff(policy.backoff);
+    *self::policy().write().expect("lock policy") = policy;
+}
+
+/// Get the retry strategy set with [`set_default_policy`].
+pub fn default_policy() -> Policy {
+    policy().read().expect("lock policy").clone()
+}
+
+fn time_scale_bits() -> &'static AtomicU64 {
+    static INSTANCE: OnceLock<AtomicU64> = OnceLock::new();
+    INSTANCE.get_or_init(|| AtomicU64::new(env_overrides().time_scale.unwrap_or(1.0).to_bits()))
+}
+
+/// Multiply all delays, timeouts and deadlines with `scale`, e.g. to run the tests under valgrind or coverage instrumentation.
+///
+/// Defaults to the value of the environment variable `REPEATED_ASSERT_TIME_SCALE`, or 1.
+/// Deadlines are scaled relative to the start of the repeated assertions.
+///
+/// # Panics
+///
+/// Panics if `scale` isn't a positive number.
+pub fn set_time_scale(scale: f64) {
+    assert!(
+        scale.is_finite() && scale > 0.0,
+        "time scale must be a positive number"
+    );
+    time_scale_bits().store(scale.to_bits(), Ordering::Relaxed);
+}
+
+/// Get the factor all delays, timeouts and deadlines are multiplied with, see [`set_time_scale`].
+pub fn time_scale() -> f64 {
+    f64::from_bits(time_scale_bits().load(Ordering::Relaxed))
+}
+
+/// Multiply `duration` with the [`time_scale`].
+pub(crate) fn scaled(duration: Duration) -> Duration {
+    Duration::try_from_secs_f64(duration.as_secs_f64() * time_scale()).unwrap_or(Duration::MAX)
+}
+
+/// Check if strict mode is enabled, i.e. failed tries aren't re-tried.
+///
+/// Assertions sampling a time window (e.g. [`never`](crate::never)) or requiring several successes still run all their tries.
+///
+/// Enabled by the environment variable `REPEATED_ASSERT_STRICT`.
+pub(crate) fn strict() -> bool {
+    env_overrides().strict
+}
+
+/// Overrides of the values passed in code, read from the environment once per process.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct EnvOverrides {
+    pub(crate) repetitions: Option<usize>,
+    pub(crate) delay: Option<Duration>,
+    pub(crate) time_scale: Option<f64>,
+    pub(crate) strict: bool,
+}
+
+impl EnvOverrides {
+    fn from_vars<V>(var: V) -> EnvOverrides
+    where
+        V: Fn(&str) -> Option<String>,
+    {
+        EnvOverrides {
+            repetitions: parse_var(REPETITIONS_VAR, var(REPETITIONS_VAR)),
+            delay: parse_var(DELAY_VAR, var(DELAY_VAR)).map(Duration::from_millis),
+            time_scale: parse_var(TIME_SCALE_VAR, var(TIME_SCALE_VAR)).filter(|scale: &f64| {
+                let valid = scale.is_finite() && *scale > 0.0;
+                if !valid {
+                    invalid_var(TIME_SCALE_VAR, &scale.to_string());
+                }
+                valid
+            }),
+            strict: parse_flag(STRICT_VAR, var(STRICT_VAR)),
+        }
+    }
+}
+
+/// Get the overrides of the values passed in code from the environment.
+pub(crate) fn env_overrides() -> &'static EnvOverrides {
+    static INSTANCE: OnceLock<EnvOverrides> = OnceLock::new();
+    INSTANCE.get_or_init(|| EnvOverrides::from_vars(|name| env::var(name).ok()))
+}
+
+/// Parse the value of the environment variable `name`, ignoring it with a warning if it's invalid.
+fn parse_var<T: FromStr>(name: &str, value: Option<String>) -> Option<T> {
+    let value = value?;
+    match value.trim().parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            invalid_var(name, &value);
+            None
+        }
+    }
+}
+
+/// Parse the value of the environment variable `name` as flag, ignoring it with a warning if it's invalid.
+fn parse_flag(name: &str, value: Option<String>) -> bool {
+    match value.as_deref().map(str::trim) {
+        None | Some("") | Some("0") | Some("false") => false,
+        Some("1") | Some("true") => true,
+        Some(value) => {
+            invalid_var(name, value);
+            false
+        }
+    }
+}
+
+/// Warn that the invalid `value` of the environment variable `name` is ignored.
+///
+/// The environment is read once per process, so the warning isn't repeated.
+fn invalid_var(name: &str, value: &str) {
+    output::emit(
+        output::Level::Error,
+        format_args!(
+            "repeated-assert: ignoring invalid value of environment variable {}: {:?}",
+            name, value
+        ),
+    );
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Retry strategy of the tests of the helpers, re-trying quickly.
+    pub(crate) fn policy() -> Policy {
+        Policy {
+            repetitions: Some(10),
+            delay: Duration::from_millis(10),
+            ..Policy::default()
+        }
+    }
+
+    #[test]
+    fn default_policy_roundtrip() {
+        // the default policy is shared with the other tests, so only set it to its current value
+        let policy = default_policy();
+        set_default_policy(policy.clone());
+
+        assert_eq!(default_policy(), policy);
+    }
+
+    #[test]
+    fn policy_builder() {
+        let policy = Policy::default()
+            .with_repetitions(20)
+            .with_delay(Duration::from_millis(100))
+            .with_backoff(2.0)
+            .with_max_delay(Duration::from_secs(1))
+            .with_timeout(Duration::from_secs(5));
+
+        assert_eq!(
+            policy,
+            Policy {
+                repetitions: Some(20),
+                delay: Duration::from_millis(100),
+                backoff: 2.0,
+                max_delay: Some(Duration::from_secs(1)),
+                timeout: Some(Duration::from_secs(5)),
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "backoff factor must be finite and not negative, got -1")]
+    fn policy_backoff_invalid() {
+        let _ = Policy::default().with_backoff(-1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "backoff factor must be finite and not negative, got NaN")]
+    fn default_policy_backoff_invalid() {
+        // panics before the default policy shared with the other tests is changed
+        set_default_policy(Policy {
+            backoff: f64::NAN,
+            ..default_policy()
+        });
+    }
+
+    #[test]
+    fn env_overrides() {
+        let overrides = EnvOverrides::from_vars(|name| match name {
+            REPETITIONS_VAR => Some("20".to_string()),
+            DELAY_VAR => Some(" 500 ".to_string()),
+            TIME_SCALE_VAR => Some("2.5".to_string()),
+            STRICT_VAR => Some("1".to_string()),
+            _ => None,
+        });
+
+        assert_eq!(overrides.repetitions, Some(20));
+        assert_eq!(overrides.delay, Some(Duration::from_millis(500)));
+        assert_eq!(overrides.time_scale, Some(2.5));
+        assert!(overrides.strict);
+        assert_eq!(EnvOverrides::from_vars(|_| None), EnvOverrides::default());
+    }
+
+    #[test]
+    fn env_overrides_invalid() {
+        let overrides = EnvOverrides::from_vars(|name| match name {
+            REPETITIONS_VAR => Some("many".to_string()),
+            DELAY_VAR => Some("fast".to_string()),
+            TIME_SCALE_VAR => Some("0".to_string()),
+            STRICT_VAR => Some("yes".to_string()),
+            _ => None,
+        });
+
+        assert_eq!(overrides, EnvOverrides::default());
+    }
+
+    #[test]
+    fn time_scale_roundtrip() {
+        // the time scale is shared with the other tests, so only set it to its current value
+        let scale = time_scale();
+        set_time_scale(scale);
+
+        assert_eq!(time_scale(), scale);
+        assert_eq!(
+            scaled(Duration::from_secs(1)),
+            Duration::from_secs(1).mul_f64(scale)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "time scale must be a positive number")]
+    fn time_scale_invalid() {
+        set_time_scale(-1.0);
+    }
+}
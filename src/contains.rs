@@ -0,0 +1,157 @@
+use crate::fs::{excerpt, MAX_EXCERPT};
+use std::fmt::{Debug, Write as _};
+
+/// Maximum number of items of a collection included in panic messages.
+const MAX_ITEMS: usize = 20;
+
+/// Haystack of the `contains` form of `repeated_assert!`.
+///
+/// Strings are searched for substrings with [`ContainsStr`], everything else for items with [`ContainsItem`].
+/// The string implementation takes precedence, since it's implemented for `&Haystack` and found first when calling
+/// `check` on a `&&Haystack`.
+pub struct Haystack<'a, H: ?Sized>(pub &'a H);
+
+/// Search strings for substrings.
+pub trait ContainsStr<N: ?Sized> {
+    /// Return the failure message if the haystack doesn't contain `needle`.
+    fn check(&self, needle: &N, expressions: (&str, &str)) -> Result<(), String>;
+}
+
+impl<H, N> ContainsStr<N> for &Haystack<'_, H>
+where
+    H: AsRef<str> + ?Sized,
+    N: AsRef<str> + ?Sized,
+{
+    fn check(&self, needle: &N, expressions: (&str, &str)) -> Result<(), String> {
+        let haystack = self.0.as_ref();
+        let needle = needle.as_ref();
+        if haystack.contains(needle) {
+            return Ok(());
+        }
+        let mut message = failure(expressions, needle);
+        if haystack.len() > MAX_EXCERPT {
+            write!(
+                message,
+                "\nhaystack (last {} of {} bytes): {:?}",
+                MAX_EXCERPT,
+                haystack.len(),
+                excerpt(haystack)
+            )
+            .unwrap();
+        } else {
+            write!(message, "\nhaystack: {:?}", haystack).unwrap();
+        }
+        Err(message)
+    }
+}
+
+/// Search collections for items.
+pub trait ContainsItem<N: ?Sized> {
+    /// Return the failure message if the haystack doesn't contain `needle`.
+    fn check(&self, needle: &N, expressions: (&str, &str)) -> Result<(), String>;
+}
+
+impl<H, N, T> ContainsItem<N> for Haystack<'_, H>
+where
+    H: ?Sized,
+    for<'b> &'b H: IntoIterator<Item = &'b T>,
+    T: PartialEq<N> + Debug,
+    N: Debug + ?Sized,
+{
+    fn check(&self, needle: &N, expressions: (&str, &str)) -> Result<(), String> {
+        if self.0.into_iter().any(|item| item == needle) {
+            return Ok(());
+        }
+        let mut message = failure(expressions, needle);
+        message.push_str("\nhaystack: [");
+        let mut items = self.0.into_iter();
+        for (index, item) in items.by_ref().take(MAX_ITEMS).enumerate() {
+            if index > 0 {
+                message.push_str(", ");
+            }
+            write!(message, "{:?}", item).unwrap();
+        }
+        let remaining = items.count();
+        if remaining > 0 {
+            write!(message, ", ... ({} more)", remaining).unwrap();
+        }
+        message.push(']');
+        Err(message)
+    }
+}
+
+fn failure<N: Debug + ?Sized>((haystack, needle_expression): (&str, &str), needle: &N) -> String {
+    format!(
+        "`{}` doesn't contain `{}`\n  needle: {:?}",
+        haystack, needle_expression, needle
+    )
+}
+
+#[cfg(test)]
+// the double reference selects the implementation, like in the macro
+#[allow(clippy::needless_borrow)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeSet, VecDeque};
+
+    const EXPRESSIONS: (&str, &str) = ("haystack", "needle");
+
+    #[test]
+    fn strings() {
+        let haystack = String::from("server listening on port 8080");
+
+        assert!((&&Haystack(&haystack))
+            .check("listening", EXPRESSIONS)
+            .is_ok());
+        assert!((&&Haystack("abc"))
+            .check(&String::from("b"), EXPRESSIONS)
+            .is_ok());
+        assert_eq!(
+            (&&Haystack(&haystack)).check("ready", EXPRESSIONS),
+            Err("`haystack` doesn't contain `needle`\n  needle: \"ready\"\nhaystack: \"server listening on port 8080\"".to_string())
+        );
+    }
+
+    #[test]
+    fn long_string() {
+        let haystack = "a".repeat(2 * MAX_EXCERPT);
+
+        let message = (&&Haystack(&haystack)).check("b", EXPRESSIONS).unwrap_err();
+        assert!(message.contains(&format!(
+            "haystack (last {} of {} bytes)",
+            MAX_EXCERPT,
+            2 * MAX_EXCERPT
+        )));
+    }
+
+    #[test]
+    fn collections() {
+        let items = vec![1, 2, 3];
+
+        assert!((&&Haystack(&items)).check(&2, EXPRESSIONS).is_ok());
+        assert!((&&Haystack(&items[..])).check(&3, EXPRESSIONS).is_ok());
+        assert!((&&Haystack(&VecDeque::from(vec!["a"])))
+            .check(&"a", EXPRESSIONS)
+            .is_ok());
+        assert!((&&Haystack(&BTreeSet::from([1])))
+            .check(&1, EXPRESSIONS)
+            .is_ok());
+        assert!((&&Haystack(&vec![String::from("a")]))
+            .check(&"a", EXPRESSIONS)
+            .is_ok());
+        assert_eq!(
+            (&&Haystack(&items)).check(&4, EXPRESSIONS),
+            Err(
+                "`haystack` doesn't contain `needle`\n  needle: 4\nhaystack: [1, 2, 3]".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn long_collection() {
+        let items: Vec<_> = (0..MAX_ITEMS + 5).collect();
+
+        let message = (&&Haystack(&items)).check(&100, EXPRESSIONS).unwrap_err();
+        assert!(message.ends_with(", 19, ... (5 more)]"));
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{clock::Clock, Instant};
+use std::{fmt, sync::Arc, time::Duration};
+
+/// Information about the current try, passed to the assertions by [`that_with_context`](crate::that_with_context)
+/// and [`Retry::run_with_context`](crate::Retry::run_with_context).
+#[derive(Clone)]
+pub struct Context {
+    attempt: usize,
+    remaining: Option<usize>,
+    start: Instant,
+    clock: Arc<dyn Clock>,
+}
+
+impl Context {
+    /// `start` is the time of the first try on the `clock` of the retry.
+    pub(crate) fn new(
+        attempt: usize,
+        remaining: Option<usize>,
+        start: Instant,
+        clock: Arc<dyn Clock>,
+    ) -> Context {
+        Context {
+            attempt,
+            remaining,
+            start,
+            clock,
+        }
+    }
+
+    /// Number of the current try, starting at 1.
+    pub fn attempt(&self) -> usize {
+        self.attempt
+    }
+
+    /// Number of tries left after the current one.
+    ///
+    /// Returns `None` if it isn't known in advance, e.g. when re-trying until a timeout.
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining
+    }
+
+    /// Check if this is the last try, i.e. panics won't be ignored.
+    pub fn is_last(&self) -> bool {
+        self.remaining == Some(0)
+    }
+
+    /// Time passed since the first try, on the [clock](crate::Retry::clock) of the retry.
+    pub fn elapsed(&self) -> Duration {
+        self.clock.elapsed(self.start)
+    }
+}
+
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Context")
+            .field("attempt", &self.attempt)
+            .field("remaining", &self.remaining)
+            .field("elapsed", &self.elapsed())
+            .finish()
+    }
+}
+
+/// Information about the failed tries, passed to the catch block by [`Retry::catch_after_with_context`](crate::Retry::catch_after_with_context).
+#[derive(Debug, Clone)]
+pub struct CatchContext {
+    attempts: usize,
+    elapsed: Duration,
+    message: Option<String>,
+}
+
+impl CatchContext {
+    pub(crate) fn new(attempts: usize, elapsed: Duration, message: Option<&str>) -> CatchContext {
+        CatchContext {
+            attempts,
+            elapsed,
+            message: message.map(str::to_string),
+        }
+    }
+
+    /// Number of tries that failed so far.
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    /// Time passed since the first try.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Panic message of the last failed try, if it panicked.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+/// Directive returned by catch blocks to adjust the remaining tries, see [`Retry::catch_after_with_context`](crate::Retry::catch_after_with_context).
+///
+/// Catch blocks returning `()` continue as configured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CatchAction {
+    /// Continue re-trying as configured.
+    Continue,
+    /// Stop re-trying and fail with the given message.
+    Abort(String),
+    /// Run up to the given number of additional tries, after the configured ones.
+    ///
+    /// The additional tries aren't limited by a timeout or deadline.
+    Extend(usize),
+    /// Wait the given delay between the remaining tries.
+    Delay(Duration),
+}
+
+impl From<()> for CatchAction {
+    fn from(_: ()) -> CatchAction {
+        CatchAction::Continue
+    }
+}
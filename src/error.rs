@@ -0,0 +1,69 @@
+use std::{any::Any, error::Error, fmt, time::Duration};
+
+/// Error returned by [`try_that`](crate::try_that) if the assertions didn't pass in time.
+#[derive(Debug, Clone)]
+pub struct RetryError {
+    attempts: usize,
+    elapsed: Duration,
+    message: String,
+    location: Option<String>,
+}
+
+impl RetryError {
+    pub(crate) fn new(
+        attempts: usize,
+        elapsed: Duration,
+        message: String,
+        location: Option<String>,
+    ) -> RetryError {
+        RetryError {
+            attempts,
+            elapsed,
+            message,
+            location,
+        }
+    }
+
+    /// Number of tries that were executed.
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    /// Time passed between the first try and the end of the last try.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Panic message of the last try.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Location of the panic of the last try, if it panicked.
+    pub(crate) fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+}
+
+impl fmt::Display for RetryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "assertions failed after {} tries in {:?}: {}",
+            self.attempts, self.elapsed, self.message
+        )
+    }
+}
+
+impl Error for RetryError {}
+
+/// Extract the message of a panic payload.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "<non-string panic payload>".to_string()
+    }
+}
@@ -0,0 +1,546 @@
+//! Fluent expectations
+//!
+//! [`expect`] takes a function returning the actual value, which is called on every try until the value satisfies a
+//! [`Matcher`]. The matchers of this module cover the common comparisons, other conditions can implement [`Matcher`].
+//!
+//! # Examples
+//!
+//! ```rust
+//! use repeated_assert::expect::*;
+//! # use std::time::Duration;
+//! # struct Cluster;
+//! # impl Cluster {
+//! #     fn leader(&self) -> Option<String> { Some("node-1".to_string()) }
+//! # }
+//! # let (queue, cluster) = (vec![1, 2, 3], Cluster);
+//!
+//! expect(|| queue.len()).to_eventually(eq(3)).within(Duration::from_secs(5));
+//!
+//! let leader = expect(|| cluster.leader())
+//!     .to_eventually(be_some())
+//!     .poll(Duration::from_millis(500))
+//!     .within(Duration::from_secs(30));
+//! ```
+use crate::{Policy, Retry};
+use std::{
+    collections::VecDeque,
+    fmt::{self, Debug},
+    time::Duration,
+};
+
+/// Condition a value has to satisfy, see [`expect`].
+pub trait Matcher<T: ?Sized> {
+    /// Check if `actual` satisfies the condition.
+    fn matches(&self, actual: &T) -> bool;
+
+    /// Describe the condition, completing "expected value to ...", e.g. `equal 3`.
+    fn describe(&self) -> String;
+}
+
+/// Expect the value returned by `actual` to satisfy a matcher.
+///
+/// `actual` is called on every try.
+pub fn expect<F, T>(actual: F) -> Expect<F>
+where
+    F: FnMut() -> T,
+{
+    Expect { actual }
+}
+
+/// Function returning the actual value, see [`expect`].
+pub struct Expect<F> {
+    actual: F,
+}
+
+impl<F> Expect<F> {
+    /// Re-try until the value satisfies `matcher`.
+    ///
+    /// Nothing is checked until the [`Expectation`] is run with [`within`](Expectation::within) or
+    /// [`with_policy`](Expectation::with_policy).
+    pub fn to_eventually<M>(self, matcher: M) -> Expectation<F, M> {
+        Expectation {
+            actual: self.actual,
+            matcher,
+            delay: None,
+        }
+    }
+}
+
+impl<F> fmt::Debug for Expect<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Expect").finish_non_exhaustive()
+    }
+}
+
+/// Expectation that the value eventually satisfies a matcher, see [`Expect::to_eventually`].
+#[must_use = "expectations are only checked by `within` or `with_policy`"]
+pub struct Expectation<F, M> {
+    actual: F,
+    matcher: M,
+    delay: Option<Duration>,
+}
+
+impl<F, M, T> Expectation<F, M>
+where
+    F: FnMut() -> T,
+    M: Matcher<T>,
+    T: Debug,
+{
+    /// Wait `delay` between tries, instead of the delay of the [default policy](crate::default_policy).
+    pub fn poll(mut self, delay: Duration) -> Expectation<F, M> {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Re-try until `timeout` has passed.
+    ///
+    /// Returns the value satisfying the matcher.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the description of the matcher and the last value, if the value never satisfied the matcher.
+    #[track_caller]
+    pub fn within(self, timeout: Duration) -> T {
+        let retry = Retry::new().timeout(timeout);
+        self.run(retry)
+    }
+
+    /// Re-try with the retry strategy of `policy`.
+    ///
+    /// Returns the value satisfying the matcher.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the description of the matcher and the last value, if the value never satisfied the matcher.
+    #[track_caller]
+    pub fn with_policy(self, policy: Policy) -> T {
+        self.run(Retry::with_policy(policy))
+    }
+
+    #[track_caller]
+    fn run(self, retry: Retry<'_>) -> T {
+        let Expectation {
+            mut actual,
+            matcher,
+            delay,
+        } = self;
+        let retry = match delay {
+            Some(delay) => retry.delay(delay),
+            None => retry,
+        };
+        retry.run(|| {
+            let value = actual();
+            if !matcher.matches(&value) {
+                panic!(
+                    "expected value to {}\n  actual: {:?}",
+                    matcher.describe(),
+                    value
+                );
+            }
+            value
+        })
+    }
+}
+
+impl<F, M: Debug> fmt::Debug for Expectation<F, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Expectation")
+            .field("matcher", &self.matcher)
+            .field("delay", &self.delay)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Equality with an expected value, see [`eq`] and [`ne`].
+#[derive(Debug, Clone)]
+pub struct Equal<E> {
+    expected: E,
+    equal: bool,
+}
+
+/// Match values equal to `expected`.
+pub fn eq<E>(expected: E) -> Equal<E> {
+    Equal {
+        expected,
+        equal: true,
+    }
+}
+
+/// Match values not equal to `expected`.
+pub fn ne<E>(expected: E) -> Equal<E> {
+    Equal {
+        expected,
+        equal: false,
+    }
+}
+
+impl<T, E> Matcher<T> for Equal<E>
+where
+    T: PartialEq<E> + ?Sized,
+    E: Debug,
+{
+    fn matches(&self, actual: &T) -> bool {
+        (*actual == self.expected) == self.equal
+    }
+
+    fn describe(&self) -> String {
+        let op = if self.equal { "equal" } else { "not equal" };
+        format!("{} {:?}", op, self.expected)
+    }
+}
+
+/// Ordering relative to an expected value, see [`gt`], [`ge`], [`lt`] and [`le`].
+#[derive(Debug, Clone)]
+pub struct Compare<E> {
+    expected: E,
+    op: Op,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+/// Match values greater than `expected`.
+pub fn gt<E>(expected: E) -> Compare<E> {
+    Compare {
+        expected,
+        op: Op::Gt,
+    }
+}
+
+/// Match values greater than or equal to `expected`.
+pub fn ge<E>(expected: E) -> Compare<E> {
+    Compare {
+        expected,
+        op: Op::Ge,
+    }
+}
+
+/// Match values less than `expected`.
+pub fn lt<E>(expected: E) -> Compare<E> {
+    Compare {
+        expected,
+        op: Op::Lt,
+    }
+}
+
+/// Match values less than or equal to `expected`.
+pub fn le<E>(expected: E) -> Compare<E> {
+    Compare {
+        expected,
+        op: Op::Le,
+    }
+}
+
+impl<T, E> Matcher<T> for Compare<E>
+where
+    T: PartialOrd<E> + ?Sized,
+    E: Debug,
+{
+    fn matches(&self, actual: &T) -> bool {
+        let expected = &self.expected;
+        match self.op {
+            Op::Gt => actual > expected,
+            Op::Ge => actual >= expected,
+            Op::Lt => actual < expected,
+            Op::Le => actual <= expected,
+        }
+    }
+
+    fn describe(&self) -> String {
+        let op = match self.op {
+            Op::Gt => "be greater than",
+            Op::Ge => "be greater than or equal to",
+            Op::Lt => "be less than",
+            Op::Le => "be less than or equal to",
+        };
+        format!("{} {:?}", op, self.expected)
+    }
+}
+
+/// Match strings containing a substring or collections containing an item, see [`contain`].
+#[derive(Debug, Clone)]
+pub struct Contain<N> {
+    needle: N,
+}
+
+/// Match strings containing the substring `needle`, or collections (`Vec`, `VecDeque` and slices) containing the item `needle`.
+pub fn contain<N>(needle: N) -> Contain<N> {
+    Contain { needle }
+}
+
+impl<N> Contain<N> {
+    fn describe_needle(&self) -> String
+    where
+        N: Debug,
+    {
+        format!("contain {:?}", self.needle)
+    }
+}
+
+impl<N: AsRef<str> + Debug> Matcher<str> for Contain<N> {
+    fn matches(&self, actual: &str) -> bool {
+        actual.contains(self.needle.as_ref())
+    }
+
+    fn describe(&self) -> String {
+        self.describe_needle()
+    }
+}
+
+impl<N: AsRef<str> + Debug> Matcher<String> for Contain<N> {
+    fn matches(&self, actual: &String) -> bool {
+        actual.contains(self.needle.as_ref())
+    }
+
+    fn describe(&self) -> String {
+        self.describe_needle()
+    }
+}
+
+impl<T: PartialEq<N>, N: Debug> Matcher<[T]> for Contain<N> {
+    fn matches(&self, actual: &[T]) -> bool {
+        actual.iter().any(|item| *item == self.needle)
+    }
+
+    fn describe(&self) -> String {
+        self.describe_needle()
+    }
+}
+
+impl<T: PartialEq<N>, N: Debug> Matcher<Vec<T>> for Contain<N> {
+    fn matches(&self, actual: &Vec<T>) -> bool {
+        actual.iter().any(|item| *item == self.needle)
+    }
+
+    fn describe(&self) -> String {
+        self.describe_needle()
+    }
+}
+
+impl<T: PartialEq<N>, N: Debug> Matcher<VecDeque<T>> for Contain<N> {
+    fn matches(&self, actual: &VecDeque<T>) -> bool {
+        actual.iter().any(|item| *item == self.needle)
+    }
+
+    fn describe(&self) -> String {
+        self.describe_needle()
+    }
+}
+
+impl<T: ?Sized, N> Matcher<&T> for Contain<N>
+where
+    Contain<N>: Matcher<T>,
+{
+    fn matches(&self, actual: &&T) -> bool {
+        self.matches(*actual)
+    }
+
+    fn describe(&self) -> String {
+        <Self as Matcher<T>>::describe(self)
+    }
+}
+
+/// Match an enum variant, see [`be_some`], [`be_none`], [`be_ok`] and [`be_err`].
+#[derive(Debug, Clone, Copy)]
+pub struct Variant {
+    name: &'static str,
+}
+
+/// Match `Some` value.
+pub fn be_some() -> Variant {
+    Variant { name: "Some" }
+}
+
+/// Match `None`.
+pub fn be_none() -> Variant {
+    Variant { name: "None" }
+}
+
+/// Match `Ok` results.
+pub fn be_ok() -> Variant {
+    Variant { name: "Ok" }
+}
+
+/// Match `Err` results.
+pub fn be_err() -> Variant {
+    Variant { name: "Err" }
+}
+
+impl<T> Matcher<Option<T>> for Variant {
+    fn matches(&self, actual: &Option<T>) -> bool {
+        match actual {
+            Some(_) => self.name == "Some",
+            None => self.name == "None",
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("be `{}`", self.name)
+    }
+}
+
+impl<T, E> Matcher<Result<T, E>> for Variant {
+    fn matches(&self, actual: &Result<T, E>) -> bool {
+        match actual {
+            Ok(_) => self.name == "Ok",
+            Err(_) => self.name == "Err",
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("be `{}`", self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::policy;
+    use std::cell::Cell;
+
+    /// Count the calls, starting with 0.
+    fn counter() -> impl FnMut() -> usize {
+        let calls = Cell::new(0);
+        move || calls.replace(calls.get() + 1)
+    }
+
+    #[test]
+    fn compare_success() {
+        assert_eq!(
+            expect(counter()).to_eventually(eq(3)).with_policy(policy()),
+            3
+        );
+        assert_eq!(
+            expect(counter()).to_eventually(ne(0)).with_policy(policy()),
+            1
+        );
+        assert_eq!(
+            expect(counter()).to_eventually(gt(2)).with_policy(policy()),
+            3
+        );
+        assert_eq!(
+            expect(counter()).to_eventually(ge(2)).with_policy(policy()),
+            2
+        );
+        assert_eq!(
+            expect(counter()).to_eventually(lt(1)).with_policy(policy()),
+            0
+        );
+        assert_eq!(
+            expect(counter()).to_eventually(le(1)).with_policy(policy()),
+            0
+        );
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum State {
+        Starting,
+        Running,
+    }
+
+    #[test]
+    fn equal_success() {
+        let mut calls = counter();
+        let state = expect(|| match calls() {
+            0..=2 => State::Starting,
+            _ => State::Running,
+        })
+        .to_eventually(eq(State::Running))
+        .with_policy(policy());
+        assert_eq!(state, State::Running);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected value to not equal Starting\n  actual: Starting")]
+    fn equal_failure() {
+        expect(|| State::Starting)
+            .to_eventually(ne(State::Starting))
+            .with_policy(policy());
+    }
+
+    #[test]
+    #[should_panic(expected = "expected value to be greater than 100\n  actual: 9")]
+    fn compare_failure() {
+        expect(counter())
+            .to_eventually(gt(100))
+            .with_policy(policy());
+    }
+
+    #[test]
+    fn within_success() {
+        let value = expect(counter())
+            .to_eventually(eq(3))
+            .poll(Duration::from_millis(10))
+            .within(Duration::from_secs(1));
+        assert_eq!(value, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected value to equal 1000")]
+    fn within_failure() {
+        expect(counter())
+            .to_eventually(eq(1000))
+            .poll(Duration::from_millis(10))
+            .within(Duration::from_millis(100));
+    }
+
+    #[test]
+    fn contain_success() {
+        let mut calls = counter();
+        let text = expect(|| format!("calls: {}", calls()))
+            .to_eventually(contain("2"))
+            .with_policy(policy());
+        assert_eq!(text, "calls: 2");
+
+        let mut calls = counter();
+        expect(|| (0..calls()).collect::<Vec<_>>())
+            .to_eventually(contain(2))
+            .with_policy(policy());
+        expect(|| "static")
+            .to_eventually(contain("tat"))
+            .with_policy(policy());
+    }
+
+    #[test]
+    #[should_panic(expected = "expected value to contain \"ready\"\n  actual: \"starting\"")]
+    fn contain_failure() {
+        expect(|| String::from("starting"))
+            .to_eventually(contain("ready"))
+            .with_policy(policy());
+    }
+
+    #[test]
+    fn variant_success() {
+        let mut calls = counter();
+        let value = expect(|| Some(calls()).filter(|calls| *calls > 1))
+            .to_eventually(be_some())
+            .with_policy(policy());
+        assert_eq!(value, Some(2));
+
+        let mut calls = counter();
+        let result = expect(|| if calls() > 1 { Ok(()) } else { Err(()) })
+            .to_eventually(be_ok())
+            .with_policy(policy());
+        assert_eq!(result, Ok(()));
+        let result = expect(|| "x".parse::<u8>())
+            .to_eventually(be_err())
+            .with_policy(policy());
+        assert!(result.is_err());
+        expect(|| None::<u8>)
+            .to_eventually(be_none())
+            .with_policy(policy());
+    }
+
+    #[test]
+    #[should_panic(expected = "expected value to be `Some`\n  actual: None")]
+    fn variant_failure() {
+        expect(|| None::<u8>)
+            .to_eventually(be_some())
+            .with_policy(policy());
+    }
+}
@@ -0,0 +1,166 @@
+#[cfg(feature = "async")]
+use crate::Policy;
+use crate::Retry;
+use std::time::Duration;
+#[cfg(feature = "async")]
+use std::{fmt::Debug, future::Future};
+
+/// Extension trait to run closures as repeated assertions in postfix style.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use repeated_assert::Eventually;
+/// # use std::{path::Path, time::Duration};
+///
+/// (|| assert!(Path::new("should_appear_soon.txt").exists())).eventually(10, Duration::from_millis(50));
+/// ```
+pub trait Eventually<R>: FnMut() -> R + Sized {
+    /// Run the closure up to `repetitions` times with a `delay` in between tries.
+    ///
+    /// # Info
+    ///
+    /// See [`that`](crate::that).
+    #[track_caller]
+    fn eventually(self, repetitions: usize, delay: Duration) -> R {
+        Retry::new()
+            .repetitions(repetitions)
+            .fixed_delay(delay)
+            .run(self)
+    }
+
+    /// Run the closure with the retry strategy of `retry`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use repeated_assert::{Eventually, Retry};
+    /// # use std::{path::Path, time::Duration};
+    /// (|| assert!(Path::new("should_appear_soon.txt").exists()))
+    ///     .eventually_with(Retry::new().timeout(Duration::from_secs(5)));
+    /// ```
+    fn eventually_with(self, retry: Retry<'_>) -> R {
+        retry.run(self)
+    }
+}
+
+impl<F, R> Eventually<R> for F where F: FnMut() -> R {}
+
+/// Extension trait to re-try closures returning futures of results in postfix style.
+///
+/// # Examples
+///
+/// ```rust
+/// use repeated_assert::EventuallyOk;
+/// # use repeated_assert::Policy;
+/// # struct Client;
+/// # impl Client {
+/// #     async fn get_status(&self) -> Result<String, std::io::Error> { Ok("running".to_string()) }
+/// # }
+/// # async fn example(client: Client) {
+///
+/// let status = (|| client.get_status()).eventually_ok(Policy::default()).await;
+/// # }
+/// ```
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub trait EventuallyOk<F, T, E>: FnMut() -> F + Sized
+where
+    F: Future<Output = Result<T, E>>,
+    E: Debug,
+{
+    /// Call the closure and await the future with the retry strategy of `policy`, until it resolves to `Ok`.
+    ///
+    /// Futures resolving to `Err` or panicking (e.g. failed assertions) count as failed tries.
+    /// Panics with the error of the last try if no try succeeded.
+    ///
+    /// # Info
+    ///
+    /// See [`Retry::run_async`].
+    #[track_caller]
+    fn eventually_ok(mut self, policy: Policy) -> impl Future<Output = T> {
+        Retry::with_policy(policy).run_async(move || {
+            let future = self();
+            async move {
+                match future.await {
+                    Ok(value) => value,
+                    Err(error) => panic!("future resolved to `Err`: {:?}", error),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(feature = "async")]
+impl<C, F, T, E> EventuallyOk<F, T, E> for C
+where
+    C: FnMut() -> F,
+    F: Future<Output = Result<T, E>>,
+    E: Debug,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eventually_success() {
+        let mut tries = 0;
+
+        let value = (|| {
+            tries += 1;
+            assert!(tries > 2);
+            tries
+        })
+        .eventually(5, Duration::from_millis(10));
+
+        assert_eq!(value, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "repeated-assert: failed after 2 tries")]
+    fn eventually_with_failure() {
+        (|| panic!("not ready"))
+            .eventually_with(Retry::new().repetitions(2).delay(Duration::from_millis(10)));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn eventually_ok_success() {
+        let mut tries = 0;
+
+        let value = (|| {
+            tries += 1;
+            let tries = tries;
+            async move {
+                if tries < 3 {
+                    Err("not ready")
+                } else {
+                    Ok(tries)
+                }
+            }
+        })
+        .eventually_ok(Policy {
+            repetitions: Some(5),
+            delay: Duration::from_millis(10),
+            ..Policy::default()
+        })
+        .await;
+
+        assert_eq!(value, 3);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    #[should_panic(expected = "future resolved to `Err`: \"not ready\"")]
+    async fn eventually_ok_failure() {
+        (|| async { Err::<(), _>("not ready") })
+            .eventually_ok(Policy {
+                repetitions: Some(2),
+                delay: Duration::from_millis(10),
+                ..Policy::default()
+            })
+            .await;
+    }
+}
@@ -0,0 +1,245 @@
+use crate::report::json_string;
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs, io,
+    panic::Location,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+/// Number of tries of all repeated assertions at a call site, see [`flakiness_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallSite {
+    name: Option<String>,
+    location: String,
+    invocations: usize,
+    retried: usize,
+    failures: usize,
+    attempts: usize,
+    max_attempts: usize,
+}
+
+impl CallSite {
+    fn new(location: &Location<'_>, name: Option<&str>) -> CallSite {
+        CallSite {
+            name: name.map(str::to_string),
+            location: location.to_string(),
+            invocations: 0,
+            retried: 0,
+            failures: 0,
+            attempts: 0,
+            max_attempts: 0,
+        }
+    }
+
+    /// Name of the repeated assertions, see [`Retry::name`](crate::Retry::name).
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Location of the call in the source code.
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    /// Number of repeated assertions at this call site.
+    pub fn invocations(&self) -> usize {
+        self.invocations
+    }
+
+    /// Number of repeated assertions which needed more than one try.
+    pub fn retried(&self) -> usize {
+        self.retried
+    }
+
+    /// Number of repeated assertions which failed.
+    pub fn failures(&self) -> usize {
+        self.failures
+    }
+
+    /// Total number of tries.
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    /// Highest number of tries of a single repeated assertion.
+    pub fn max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+}
+
+/// Number of tries of all repeated assertions in the process, grouped by call site.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlakinessReport {
+    call_sites: Vec<CallSite>,
+}
+
+impl FlakinessReport {
+    /// Call sites, the ones retried most often first.
+    pub fn call_sites(&self) -> &[CallSite] {
+        &self.call_sites
+    }
+
+    /// Format the report as JSON.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"call_sites\":[");
+        for (index, call_site) in self.call_sites.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            write!(
+                json,
+                "{{\"name\":{},\"location\":{},\"invocations\":{},\"retried\":{},\"failures\":{},\"attempts\":{},\"max_attempts\":{}}}",
+                call_site
+                    .name
+                    .as_deref()
+                    .map_or_else(|| "null".to_string(), json_string),
+                json_string(&call_site.location),
+                call_site.invocations,
+                call_site.retried,
+                call_site.failures,
+                call_site.attempts,
+                call_site.max_attempts
+            )
+            .unwrap();
+        }
+        json.push_str("]}");
+        json
+    }
+
+    /// Write the report as JSON to `path`, creating missing parent directories.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_json())
+    }
+}
+
+struct Registry {
+    call_sites: HashMap<&'static Location<'static>, CallSite>,
+    path: Option<PathBuf>,
+}
+
+fn registry() -> &'static Mutex<Registry> {
+    static INSTANCE: OnceLock<Mutex<Registry>> = OnceLock::new();
+    INSTANCE.get_or_init(|| {
+        Mutex::new(Registry {
+            call_sites: HashMap::new(),
+            path: None,
+        })
+    })
+}
+
+/// Get the number of tries of all repeated assertions in the process so far, grouped by call site.
+///
+/// Shows which passing tests are actually re-trying heavily.
+///
+/// # Examples
+///
+/// ```rust
+/// for call_site in repeated_assert::flakiness_report().call_sites() {
+///     println!("{}: {} of {} retried", call_site.location(), call_site.retried(), call_site.invocations());
+/// }
+/// ```
+pub fn flakiness_report() -> FlakinessReport {
+    let registry = registry().lock().expect("lock flakiness registry");
+    report(&registry)
+}
+
+/// Keep the [`flakiness_report`] in the file at `path` up to date until the process exits.
+///
+/// The file is rewritten after every repeated assertion, so it contains the complete report at process exit.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// repeated_assert::flakiness_report_file("target/repeated-assert-report.json");
+/// ```
+pub fn flakiness_report_file<P: Into<PathBuf>>(path: P) {
+    let mut registry = registry().lock().expect("lock flakiness registry");
+    registry.path = Some(path.into());
+    write_report(&registry);
+}
+
+/// Record the number of tries of a repeated assertion.
+pub(crate) fn record(
+    location: &'static Location<'static>,
+    name: Option<&str>,
+    attempts: usize,
+    success: bool,
+) {
+    let mut registry = registry().lock().expect("lock flakiness registry");
+    let call_site = registry
+        .call_sites
+        .entry(location)
+        .or_insert_with(|| CallSite::new(location, name));
+    call_site.invocations += 1;
+    if attempts > 1 {
+        call_site.retried += 1;
+    }
+    if !success {
+        call_site.failures += 1;
+    }
+    call_site.attempts += attempts;
+    call_site.max_attempts = call_site.max_attempts.max(attempts);
+    write_report(&registry);
+}
+
+fn report(registry: &Registry) -> FlakinessReport {
+    let mut call_sites: Vec<_> = registry.call_sites.values().cloned().collect();
+    call_sites.sort_by(|a, b| {
+        (b.retried, b.attempts)
+            .cmp(&(a.retried, a.attempts))
+            .then_with(|| a.location.cmp(&b.location))
+    });
+    FlakinessReport { call_sites }
+}
+
+fn write_report(registry: &Registry) {
+    if let Some(path) = &registry.path {
+        if let Err(error) = report(registry).write(path) {
+            crate::output::emit(
+                crate::output::Level::Info,
+                format_args!(
+                    "repeated-assert: failed to write flakiness report: {}",
+                    error
+                ),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Retry;
+    use std::time::Duration;
+
+    #[test]
+    fn flakiness() {
+        let mut tries = 0;
+        Retry::new()
+            .repetitions(5)
+            .delay(Duration::from_millis(10))
+            .run(|| {
+                tries += 1;
+                assert!(tries > 2);
+            });
+
+        let report = flakiness_report();
+        let call_site = report
+            .call_sites()
+            .iter()
+            .find(|call_site| call_site.location().starts_with(file!()))
+            .unwrap();
+        assert_eq!(call_site.invocations(), 1);
+        assert_eq!(call_site.retried(), 1);
+        assert_eq!(call_site.attempts(), 3);
+        assert!(report.to_json().contains(
+            "\"invocations\":1,\"retried\":1,\"failures\":0,\"attempts\":3,\"max_attempts\":3"
+        ));
+    }
+}
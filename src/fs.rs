@@ -0,0 +1,434 @@
+//! Waiting for files
+//!
+//! The functions of this module wait until a file exists, has a certain size or contains a pattern,
+//! and describe what they found instead if the file never did.
+//!
+//! With the `notify` feature, the parent directory of the file is watched and the file is re-checked as soon as it changes,
+//! instead of waiting out the full delay.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! # use repeated_assert::Policy;
+//! let log = repeated_assert::fs::wait_for_file_containing("server.log", "listening", Policy::default());
+//! ```
+use crate::{Policy, Retry};
+#[cfg(feature = "regex")]
+use std::{
+    collections::VecDeque,
+    io::{Read, Seek, SeekFrom},
+};
+use std::{fs, io, path::Path};
+
+/// Keeps the file system watcher alive.
+#[cfg(feature = "notify")]
+type Watch = Option<notify::RecommendedWatcher>;
+#[cfg(not(feature = "notify"))]
+type Watch = ();
+
+/// Maximum number of bytes of the file contents included in panic messages.
+pub(crate) const MAX_EXCERPT: usize = 1024;
+/// Number of lines of a log file included in panic messages.
+#[cfg(feature = "regex")]
+const TAIL_LINES: usize = 10;
+
+/// Wait with the retry strategy of `policy` until `path` exists.
+///
+/// # Panics
+///
+/// Panics with the contents of the parent directory if the file never appeared.
+#[track_caller]
+pub fn wait_for_file<P: AsRef<Path>>(path: P, policy: Policy) {
+    let path = path.as_ref();
+    let (retry, _watch) = retry(path, policy);
+    retry.run(|| {
+        if !path.exists() {
+            panic!("file {:?} doesn't exist, {}", path, parent_listing(path));
+        }
+    })
+}
+
+/// Wait with the retry strategy of `policy` until the file at `path` contains `pattern`.
+///
+/// Returns the contents of the file.
+///
+/// # Panics
+///
+/// Panics with the last contents or read error if the file never contained `pattern`.
+#[track_caller]
+pub fn wait_for_file_containing<P: AsRef<Path>>(path: P, pattern: &str, policy: Policy) -> String {
+    let path = path.as_ref();
+    let (retry, _watch) = retry(path, policy);
+    retry.run(|| match fs::read_to_string(path) {
+        Ok(contents) if contents.contains(pattern) => contents,
+        Ok(contents) => panic!(
+            "file {:?} doesn't contain {:?}, contents ({} bytes): {:?}",
+            path,
+            pattern,
+            contents.len(),
+            excerpt(&contents)
+        ),
+        Err(error) => panic!("{}", read_error(path, &error)),
+    })
+}
+
+/// Wait with the retry strategy of `policy` until the size of the file at `path` in bytes matches `predicate`.
+///
+/// Returns the size of the file.
+///
+/// # Panics
+///
+/// Panics with the last size or read error if the size never matched.
+#[track_caller]
+pub fn wait_for_file_size<P, S>(path: P, mut predicate: S, policy: Policy) -> u64
+where
+    P: AsRef<Path>,
+    S: FnMut(u64) -> bool,
+{
+    let path = path.as_ref();
+    let (retry, _watch) = retry(path, policy);
+    retry.run(|| match fs::metadata(path) {
+        Ok(metadata) if predicate(metadata.len()) => metadata.len(),
+        Ok(metadata) => panic!(
+            "size of file {:?} doesn't match, current size: {} bytes",
+            path,
+            metadata.len()
+        ),
+        Err(error) => panic!("{}", read_error(path, &error)),
+    })
+}
+
+/// Wait with the retry strategy of `policy` until a line of the log file at `path` matches `regex`.
+///
+/// Only the lines appended since the previous try are read, so large log files aren't scanned over and over.
+/// Returns the matching line, without the line break.
+///
+/// # Panics
+///
+/// Panics with the last lines of the file or the read error if no line matched.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use repeated_assert::Policy;
+/// # use regex::Regex;
+/// let regex = Regex::new(r"listening on port \d+").unwrap();
+/// let line = repeated_assert::fs::wait_for_log_line("server.log", &regex, Policy::default());
+/// ```
+#[cfg(feature = "regex")]
+#[cfg_attr(docsrs, doc(cfg(feature = "regex")))]
+#[track_caller]
+pub fn wait_for_log_line<P: AsRef<Path>>(path: P, regex: &regex::Regex, policy: Policy) -> String {
+    let path = path.as_ref();
+    let (retry, _watch) = retry(path, policy);
+    let mut tail = LogTail::default();
+    retry.run(|| match tail.read(path, regex) {
+        Ok(Some(line)) => line,
+        Ok(None) => panic!(
+            "no line of file {:?} matches /{}/, last lines:\n{}",
+            path,
+            regex,
+            tail.last_lines()
+        ),
+        Err(error) => panic!("{}", read_error(path, &error)),
+    })
+}
+
+/// Lines of a log file read so far.
+#[cfg(feature = "regex")]
+#[derive(Debug, Default)]
+struct LogTail {
+    offset: u64,
+    partial: Vec<u8>,
+    last_lines: VecDeque<String>,
+}
+
+#[cfg(feature = "regex")]
+impl LogTail {
+    /// Read the lines appended since the last read, returning the first one matching `regex`.
+    ///
+    /// An unterminated last line is matched as well, but read again once it's complete.
+    fn read(&mut self, path: &Path, regex: &regex::Regex) -> io::Result<Option<String>> {
+        let mut file = fs::File::open(path)?;
+        if file.metadata()?.len() < self.offset {
+            // the file was truncated or replaced, start over
+            self.offset = 0;
+            self.partial.clear();
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        self.offset += file.read_to_end(&mut self.partial)? as u64;
+
+        // split off the complete lines, without the last line break
+        let lines = match self.partial.iter().rposition(|byte| *byte == b'\n') {
+            Some(end) => {
+                let mut lines = self.partial.drain(..=end).collect::<Vec<_>>();
+                lines.pop();
+                lines
+            }
+            None => Vec::new(),
+        };
+        for line in lines
+            .split(|byte| *byte == b'\n')
+            .take_while(|_| !lines.is_empty())
+        {
+            let line = String::from_utf8_lossy(line);
+            let line = line.strip_suffix('\r').unwrap_or(&line);
+            if regex.is_match(line) {
+                return Ok(Some(line.to_string()));
+            }
+            if self.last_lines.len() == TAIL_LINES {
+                self.last_lines.pop_front();
+            }
+            self.last_lines.push_back(line.to_string());
+        }
+        let partial = String::from_utf8_lossy(&self.partial);
+        Ok(regex.is_match(&partial).then(|| partial.into_owned()))
+    }
+
+    /// Format the last lines read, including an unterminated one.
+    fn last_lines(&self) -> String {
+        let mut lines = String::new();
+        for line in &self.last_lines {
+            lines.push_str("  ");
+            lines.push_str(line);
+            lines.push('\n');
+        }
+        if !self.partial.is_empty() {
+            lines.push_str("  ");
+            lines.push_str(&String::from_utf8_lossy(&self.partial));
+            lines.push('\n');
+        }
+        if lines.is_empty() {
+            lines.push_str("  <empty>\n");
+        }
+        lines
+    }
+}
+
+/// Create the retry strategy waiting for `path`.
+#[track_caller]
+#[cfg(not(feature = "notify"))]
+fn retry(_path: &Path, policy: Policy) -> (Retry<'static>, Watch) {
+    (Retry::with_policy(policy), ())
+}
+
+/// Create the retry strategy waiting for `path`, re-checking as soon as its parent directory changes.
+///
+/// Falls back to waiting out the delay if the directory can't be watched (e.g. because it doesn't exist yet).
+#[track_caller]
+#[cfg(feature = "notify")]
+fn retry(path: &Path, policy: Policy) -> (Retry<'static>, Watch) {
+    use notify::Watcher;
+
+    let retry = Retry::with_policy(policy);
+    let trigger = crate::Trigger::new();
+    let notifier = trigger.notifier();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        // ignore the accesses of the checks themselves
+        if event.map_or(true, |event| !event.kind.is_access()) {
+            notifier.notify();
+        }
+    })
+    .and_then(|mut watcher| {
+        watcher.watch(parent(path), notify::RecursiveMode::NonRecursive)?;
+        Ok(watcher)
+    });
+    match watcher {
+        Ok(watcher) => (retry.trigger(trigger), Some(watcher)),
+        Err(_) => (retry, None),
+    }
+}
+
+/// Get the parent directory of `path`.
+fn parent(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Describe why `path` couldn't be read.
+fn read_error(path: &Path, error: &io::Error) -> String {
+    match error.kind() {
+        io::ErrorKind::NotFound => {
+            format!("file {:?} doesn't exist, {}", path, parent_listing(path))
+        }
+        _ => format!("failed to read file {:?}: {}", path, error),
+    }
+}
+
+/// Describe the contents of the parent directory of `path`.
+fn parent_listing(path: &Path) -> String {
+    let parent = parent(path);
+    match fs::read_dir(parent) {
+        Ok(entries) => {
+            let mut names = entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect::<Vec<_>>();
+            names.sort();
+            format!("directory {:?} contains {:?}", parent, names)
+        }
+        Err(error) => format!("failed to read directory {:?}: {}", parent, error),
+    }
+}
+
+/// Shorten `contents` to its last [`MAX_EXCERPT`] bytes.
+pub(crate) fn excerpt(contents: &str) -> &str {
+    if contents.len() <= MAX_EXCERPT {
+        return contents;
+    }
+    let mut start = contents.len() - MAX_EXCERPT;
+    while !contents.is_char_boundary(start) {
+        start += 1;
+    }
+    &contents[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::policy;
+    use std::{path::PathBuf, thread, time::Duration};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "repeated-assert-fs-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn wait_for_file_success() {
+        let dir = temp_dir("file");
+        let path = dir.join("file.txt");
+
+        let file = path.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            fs::write(file, "").unwrap();
+        });
+
+        wait_for_file(&path, policy());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't exist, directory")]
+    fn wait_for_file_failure() {
+        let dir = temp_dir("missing");
+        fs::write(dir.join("other.txt"), "").unwrap();
+
+        wait_for_file(dir.join("file.txt"), policy());
+    }
+
+    #[test]
+    fn wait_for_file_containing_success() {
+        let dir = temp_dir("containing");
+        let path = dir.join("file.log");
+
+        let file = path.clone();
+        thread::spawn(move || {
+            fs::write(&file, "starting\n").unwrap();
+            thread::sleep(Duration::from_millis(20));
+            fs::write(&file, "starting\nready\n").unwrap();
+        });
+
+        let contents = wait_for_file_containing(&path, "ready", policy());
+        assert!(contents.ends_with("ready\n"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't contain \"ready\", contents (9 bytes): \"starting\\n\"")]
+    fn wait_for_file_containing_failure() {
+        let dir = temp_dir("not-containing");
+        let path = dir.join("file.log");
+        fs::write(&path, "starting\n").unwrap();
+
+        wait_for_file_containing(&path, "ready", policy());
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't match, current size: 3 bytes")]
+    fn wait_for_file_size_failure() {
+        let dir = temp_dir("size");
+        let path = dir.join("file.bin");
+        fs::write(&path, "abc").unwrap();
+
+        wait_for_file_size(&path, |size| size >= 10, policy());
+    }
+
+    #[cfg(feature = "notify")]
+    #[test]
+    fn wait_for_file_notify() {
+        let dir = temp_dir("notify");
+        let path = dir.join("file.txt");
+
+        let file = path.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            fs::write(file, "").unwrap();
+        });
+
+        let start = std::time::Instant::now();
+        wait_for_file(
+            &path,
+            Policy {
+                repetitions: Some(2),
+                delay: Duration::from_secs(10),
+                ..Policy::default()
+            },
+        );
+
+        // woken up by the watcher instead of waiting out the delay
+        assert!(start.elapsed() < Duration::from_secs(5));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn wait_for_log_line_success() {
+        use std::io::Write;
+
+        let dir = temp_dir("log");
+        let path = dir.join("server.log");
+        fs::write(&path, "starting\n").unwrap();
+
+        let file = path.clone();
+        thread::spawn(move || {
+            let mut file = fs::OpenOptions::new().append(true).open(file).unwrap();
+            for line in ["loading config\n", "listening on ", "port 8080\n"] {
+                thread::sleep(Duration::from_millis(10));
+                file.write_all(line.as_bytes()).unwrap();
+            }
+        });
+
+        let regex = regex::Regex::new(r"^listening on port \d+$").unwrap();
+        let line = wait_for_log_line(&path, &regex, policy());
+        assert_eq!(line, "listening on port 8080");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    #[should_panic(expected = "matches /^ready$/, last lines:\n  starting\n  loading config\n")]
+    fn wait_for_log_line_failure() {
+        let dir = temp_dir("log-failure");
+        let path = dir.join("server.log");
+        fs::write(&path, "starting\nloading config\n").unwrap();
+
+        wait_for_log_line(&path, &regex::Regex::new("^ready$").unwrap(), policy());
+    }
+
+    #[test]
+    fn excerpt_utf8() {
+        let contents = "ä".repeat(MAX_EXCERPT);
+        assert_eq!(excerpt(&contents).len(), MAX_EXCERPT);
+        assert_eq!(excerpt("short"), "short");
+    }
+}
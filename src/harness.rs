@@ -0,0 +1,375 @@
+//! Test harness with retry policies
+//!
+//! A custom test harness built on `libtest-mimic`, where every test can declare the [`Policy`] it's re-tried with.
+//! Besides the usual summary, the harness prints how often the re-tried tests had to be run.
+//!
+//! The harness replaces the one of the standard library, so the test target has to disable it in `Cargo.toml`:
+//!
+//! ```toml
+//! [[test]]
+//! name = "integration"
+//! harness = false
+//! ```
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use repeated_assert::harness::{Harness, Test};
+//! # use repeated_assert::Policy;
+//! # use std::time::Duration;
+//! # fn server_starts() {}
+//! # fn cache_expires() {}
+//!
+//! fn main() {
+//!     Harness::new()
+//!         .test(Test::new("server_starts", server_starts))
+//!         .test(
+//!             Test::new("cache_expires", cache_expires).policy(
+//!                 Policy::default()
+//!                     .with_repetitions(20)
+//!                     .with_delay(Duration::from_millis(100)),
+//!             ),
+//!         )
+//!         .run();
+//! }
+//! ```
+use crate::{Policy, Retry, RetryObserver};
+use libtest_mimic::{Arguments, Conclusion, Trial};
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Test run by the [`Harness`].
+pub struct Test {
+    name: String,
+    runner: Box<dyn FnMut() + Send>,
+    policy: Option<Policy>,
+    ignored: bool,
+}
+
+impl Test {
+    /// Create a test called `name`, which fails if `test` panics.
+    pub fn new<N, T>(name: N, test: T) -> Test
+    where
+        N: Into<String>,
+        T: FnMut() + Send + 'static,
+    {
+        Test {
+            name: name.into(),
+            runner: Box::new(test),
+            policy: None,
+            ignored: false,
+        }
+    }
+
+    /// Re-try the test with `policy`, instead of the default policy of the [`Harness`].
+    pub fn policy(mut self, policy: Policy) -> Test {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Only run the test if requested with `--ignored` or `--include-ignored`.
+    pub fn ignored(mut self, ignored: bool) -> Test {
+        self.ignored = ignored;
+        self
+    }
+}
+
+impl fmt::Debug for Test {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Test")
+            .field("name", &self.name)
+            .field("policy", &self.policy)
+            .field("ignored", &self.ignored)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Test harness re-trying the tests according to their policies.
+#[derive(Debug, Default)]
+pub struct Harness {
+    tests: Vec<Test>,
+    policy: Option<Policy>,
+}
+
+impl Harness {
+    /// Create a harness without tests, which runs every test once unless it declares a policy.
+    pub fn new() -> Harness {
+        Harness::default()
+    }
+
+    /// Re-try all tests that don't declare their own policy with `policy`.
+    pub fn policy(mut self, policy: Policy) -> Harness {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Add a test.
+    pub fn test(mut self, test: Test) -> Harness {
+        self.tests.push(test);
+        self
+    }
+
+    /// Run the tests selected by the command line arguments, print the summary and exit the process.
+    pub fn run(self) -> ! {
+        let summary = self.run_with_args(&Arguments::from_args());
+        print!("{}", summary);
+        summary.conclusion().exit()
+    }
+
+    /// Run the tests selected by `args`, without printing the retry statistics or exiting the process.
+    pub fn run_with_args(self, args: &Arguments) -> Summary {
+        let statistics = Arc::new(Mutex::new(Vec::new()));
+        let default_policy = self.policy;
+        let trials = self
+            .tests
+            .into_iter()
+            .map(|test| {
+                let policy = test.policy.or_else(|| default_policy.clone());
+                trial(test.name, test.runner, policy, &statistics).with_ignored_flag(test.ignored)
+            })
+            .collect();
+        let conclusion = libtest_mimic::run(args, trials);
+
+        let mut tests = std::mem::take(&mut *statistics.lock().expect("lock statistics"));
+        tests.sort_by(|a: &TestStatistics, b| a.name.cmp(&b.name));
+        Summary { conclusion, tests }
+    }
+}
+
+/// Create the trial of a test, re-trying it with `policy` or running it once.
+fn trial(
+    name: String,
+    mut runner: Box<dyn FnMut() + Send>,
+    policy: Option<Policy>,
+    statistics: &Arc<Mutex<Vec<TestStatistics>>>,
+) -> Trial {
+    let recorder = Recorder {
+        name: name.clone(),
+        statistics: statistics.clone(),
+    };
+    Trial::test(name.clone(), move || {
+        let retry = match policy {
+            Some(policy) => Retry::with_policy(policy),
+            None => Retry::new().exact_repetitions(1),
+        };
+        retry
+            .name(name)
+            .observer(recorder)
+            .try_run(&mut runner)
+            .map_err(|error| error.to_string().into())
+    })
+}
+
+/// Observer recording the statistics of a test.
+struct Recorder {
+    name: String,
+    statistics: Arc<Mutex<Vec<TestStatistics>>>,
+}
+
+impl Recorder {
+    fn record(&self, attempts: usize, elapsed: Duration, passed: bool) {
+        self.statistics
+            .lock()
+            .expect("lock statistics")
+            .push(TestStatistics {
+                name: self.name.clone(),
+                attempts,
+                elapsed,
+                passed,
+            });
+    }
+}
+
+impl RetryObserver for Recorder {
+    fn on_success(&self, attempts: usize, elapsed: Duration) {
+        self.record(attempts, elapsed, true);
+    }
+
+    fn on_failure(&self, attempts: usize, elapsed: Duration, _message: Option<&str>) {
+        self.record(attempts, elapsed, false);
+    }
+}
+
+/// Result of a test run, returned by [`Harness::run_with_args`].
+///
+/// The `Display` implementation lists the tests that were run more than once.
+#[derive(Debug)]
+pub struct Summary {
+    conclusion: Conclusion,
+    tests: Vec<TestStatistics>,
+}
+
+impl Summary {
+    /// Number of passed, failed, ignored and filtered out tests.
+    pub fn conclusion(&self) -> &Conclusion {
+        &self.conclusion
+    }
+
+    /// Statistics of all tests that were run, sorted by name.
+    pub fn tests(&self) -> &[TestStatistics] {
+        &self.tests
+    }
+
+    /// Statistics of the tests that were run more than once.
+    pub fn retried(&self) -> impl Iterator<Item = &TestStatistics> {
+        self.tests.iter().filter(|test| test.attempts > 1)
+    }
+
+    /// Total number of tries of all tests.
+    pub fn attempts(&self) -> usize {
+        self.tests.iter().map(|test| test.attempts).sum()
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let retried = self.retried().count();
+        if retried == 0 {
+            return Ok(());
+        }
+        writeln!(
+            f,
+            "retry statistics: {} of {} tests re-tried, {} tries in total",
+            retried,
+            self.tests.len(),
+            self.attempts()
+        )?;
+        for test in self.retried() {
+            writeln!(f, "    {}", test)?;
+        }
+        writeln!(f)
+    }
+}
+
+/// Number of tries of a single test, see [`Summary::tests`].
+#[derive(Debug, Clone)]
+pub struct TestStatistics {
+    name: String,
+    attempts: usize,
+    elapsed: Duration,
+    passed: bool,
+}
+
+impl TestStatistics {
+    /// Name of the test.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of tries, including the successful one.
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    /// Time passed between the first try and the end of the last try.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Check if the test passed.
+    pub fn passed(&self) -> bool {
+        self.passed
+    }
+}
+
+impl fmt::Display for TestStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = if self.passed { "passed" } else { "failed" };
+        write!(
+            f,
+            "{} {} after {} tries in {:?}",
+            self.name, outcome, self.attempts, self.elapsed
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::policy;
+    use std::{
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    /// Arguments running the tests one after another, writing the output of libtest-mimic to a log file
+    /// instead of stdout, where it would look like the result of this test suite.
+    fn args(name: &str) -> Arguments {
+        Arguments {
+            test_threads: Some(1),
+            quiet: true,
+            logfile: Some(log_path(name).to_string_lossy().into_owned()),
+            ..Arguments::default()
+        }
+    }
+
+    fn log_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "repeated-assert-harness-{}-{}.log",
+            name,
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn retry_statistics() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let tries = counter.clone();
+
+        let summary = Harness::new()
+            .test(Test::new("once", || {}))
+            .test(
+                Test::new("eventually", move || {
+                    assert!(tries.fetch_add(1, Ordering::SeqCst) >= 2, "not yet");
+                })
+                .policy(policy()),
+            )
+            .test(Test::new("never", || panic!("always fails")).policy(policy()))
+            .test(Test::new("skipped", || panic!("not run")).ignored(true))
+            .run_with_args(&args("statistics"));
+
+        assert_eq!(summary.conclusion().num_passed, 2);
+        assert_eq!(summary.conclusion().num_failed, 1);
+        assert_eq!(summary.conclusion().num_ignored, 1);
+        assert_eq!(summary.tests().len(), 3);
+        assert_eq!(summary.attempts(), 1 + 3 + 10);
+        let retried: Vec<_> = summary.retried().map(TestStatistics::name).collect();
+        assert_eq!(retried, ["eventually", "never"]);
+        let output = summary.to_string();
+        assert!(output.starts_with("retry statistics: 2 of 3 tests re-tried, 14 tries in total\n"));
+        assert!(output.contains("    eventually passed after 3 tries in "));
+        assert!(output.contains("    never failed after 10 tries in "));
+        let log = std::fs::read_to_string(log_path("statistics")).unwrap();
+        assert!(log.contains("---- never ----\nassertions failed after 10 tries in "));
+        assert!(log.contains("test result: FAILED. 2 passed; 1 failed; 1 ignored"));
+    }
+
+    #[test]
+    fn default_policy() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let tries = counter.clone();
+
+        let summary = Harness::new()
+            .policy(policy())
+            .test(Test::new("eventually", move || {
+                assert!(tries.fetch_add(1, Ordering::SeqCst) >= 1, "not yet");
+            }))
+            .run_with_args(&args("default-policy"));
+
+        assert!(!summary.conclusion().has_failed());
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn no_retries() {
+        let summary = Harness::new()
+            .test(Test::new("once", || {}))
+            .run_with_args(&args("no-retries"));
+
+        assert_eq!(summary.retried().count(), 0);
+        assert_eq!(summary.to_string(), "");
+    }
+}
@@ -0,0 +1,141 @@
+use std::fmt::{Debug, Write as _};
+
+/// Maximum number of distinct values included in panic messages.
+const MAX_ENTRIES: usize = 20;
+
+/// Values observed by the tries of [`assert_eventually_eq!`](crate::assert_eventually_eq),
+/// with consecutive equal values combined.
+#[derive(Debug, Default)]
+pub struct History {
+    tries: usize,
+    omitted: usize,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: String,
+    first: usize,
+    last: usize,
+}
+
+impl History {
+    pub fn new() -> History {
+        History::default()
+    }
+
+    /// Record the value of the current try.
+    pub fn record<T: Debug + ?Sized>(&mut self, value: &T) {
+        self.tries += 1;
+        let value = format!("{:?}", value);
+        match self.entries.last_mut() {
+            Some(entry) if entry.value == value => entry.last = self.tries,
+            _ => {
+                if self.entries.len() == MAX_ENTRIES {
+                    self.entries.remove(0);
+                    self.omitted += 1;
+                }
+                self.entries.push(Entry {
+                    value,
+                    first: self.tries,
+                    last: self.tries,
+                });
+            }
+        }
+    }
+
+    /// Describe a failed comparison like `assert_eq!`, followed by the recorded values of `expression`.
+    pub fn describe_failure<L, R>(&self, op: &str, left: &L, right: &R, expression: &str) -> String
+    where
+        L: Debug + ?Sized,
+        R: Debug + ?Sized,
+    {
+        let mut message = format!("assertion `left {} right` failed\n", op);
+        message.push_str(&describe_comparison(left, right));
+        message.push_str(&self.describe_values(expression));
+        message
+    }
+
+    /// List the recorded values of `expression`.
+    pub fn describe_values(&self, expression: &str) -> String {
+        let mut message = format!("values of `{}`:", expression);
+        if self.omitted > 0 {
+            write!(message, "\n  ({} earlier values omitted)", self.omitted).unwrap();
+        }
+        for entry in &self.entries {
+            if entry.first == entry.last {
+                write!(message, "\n  try {}: {}", entry.first, entry.value).unwrap();
+            } else {
+                write!(
+                    message,
+                    "\n  tries {}-{}: {}",
+                    entry.first, entry.last, entry.value
+                )
+                .unwrap();
+            }
+        }
+        message
+    }
+}
+
+/// Print both values, like `assert_eq!`.
+#[cfg(not(feature = "diff"))]
+fn describe_comparison<L, R>(left: &L, right: &R) -> String
+where
+    L: Debug + ?Sized,
+    R: Debug + ?Sized,
+{
+    format!("  left: {:?}\n right: {:?}\n", left, right)
+}
+
+/// Print a colored diff of the pretty-printed values.
+#[cfg(feature = "diff")]
+fn describe_comparison<L, R>(left: &L, right: &R) -> String
+where
+    L: Debug + ?Sized,
+    R: Debug + ?Sized,
+{
+    format!("\n{}\n", pretty_assertions::Comparison::new(left, right))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(feature = "diff"))]
+    #[test]
+    fn combine_equal_values() {
+        let mut history = History::new();
+        for value in [0, 0, 0, 1, 2, 2] {
+            history.record(&value);
+        }
+
+        assert_eq!(
+            history.describe_failure("==", &2, &3, "counter()"),
+            "assertion `left == right` failed\n  left: 2\n right: 3\nvalues of `counter()`:\n  tries 1-3: 0\n  try 4: 1\n  tries 5-6: 2"
+        );
+    }
+
+    #[test]
+    fn omit_early_values() {
+        let mut history = History::new();
+        for value in 0..(MAX_ENTRIES + 5) {
+            history.record(&value);
+        }
+
+        let message = history.describe_failure("==", &0, &1, "x");
+        assert!(message.contains("\n  (5 earlier values omitted)\n  try 6: 5\n"));
+    }
+
+    #[cfg(feature = "diff")]
+    #[test]
+    fn diff() {
+        let mut history = History::new();
+        history.record(&[1, 2, 3]);
+
+        let message = history.describe_failure("==", &[1, 2, 3], &[1, 4, 3], "values()");
+        assert!(message.starts_with("assertion `left == right` failed\n\n"));
+        assert!(message.contains("Diff"));
+        assert!(message.ends_with("\nvalues of `values()`:\n  try 1: [1, 2, 3]"));
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    backtrace::Backtrace,
+    cell::{Cell, RefCell},
+    panic::{self, PanicHookInfo},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+};
+
+type PanicHook = Box<dyn Fn(&PanicHookInfo<'_>) + Sync + Send + 'static>;
+
+thread_local! {
+    /// Number of active ignore guards of the current thread.
+    static IGNORE_COUNT: Cell<usize> = const { Cell::new(0) };
+    static PANIC_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+    static PANIC_BACKTRACE: RefCell<Option<Backtrace>> = const { RefCell::new(None) };
+}
+
+/// Number of active backtrace guards, backtraces of ignored panics are only captured if there are any.
+static CAPTURE_BACKTRACES: AtomicUsize = AtomicUsize::new(0);
+
+/// Fast path to avoid locking [`installed_hook`] for every ignore guard.
+static HOOK_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Panic hook registered by [`install_hook`].
+struct InstalledHook {
+    /// Panic hook that was registered before [`install_hook`] was called.
+    previous_hook: Arc<PanicHook>,
+    /// Address of the registered hook, to check if it's still the current one.
+    address: usize,
+}
+
+fn installed_hook() -> &'static Mutex<Option<InstalledHook>> {
+    static INSTANCE: OnceLock<Mutex<Option<InstalledHook>>> = OnceLock::new();
+    INSTANCE.get_or_init(|| Mutex::new(None))
+}
+
+/// Address of the data of `hook`, which doesn't change when the box is moved.
+fn hook_address(hook: &PanicHook) -> usize {
+    &**hook as *const _ as *const () as usize
+}
+
+/// Install the panic hook used to ignore panics of failed tries.
+///
+/// The hook is installed on demand the first time `repeated_assert` is used.
+/// Call this function at the start of the test suite to install it deterministically,
+/// e.g. before or after other crates that install panic hooks.
+///
+/// The hook chains to the panic hook that was registered before, which is called for all panics that aren't ignored.
+/// Calling this function again has no effect, unless [`uninstall_hook`] was called in between.
+pub fn install_hook() {
+    let mut installed_hook = installed_hook().lock().expect("lock panic hook");
+    if installed_hook.is_some() {
+        return;
+    }
+    // get original panic hook
+    let previous_hook: Arc<PanicHook> = Arc::new(panic::take_hook());
+    let panic_hook = previous_hook.clone();
+    // set custom panic hook
+    let hook: PanicHook = Box::new(move |panic_info| {
+        if !thread_ignored() {
+            // call original panic hook
+            panic_hook(panic_info);
+        } else if let Some(location) = panic_info.location() {
+            // remember location for the final panic message
+            let location = location.to_string();
+            PANIC_LOCATION.with(|last_location| *last_location.borrow_mut() = Some(location));
+            if CAPTURE_BACKTRACES.load(Ordering::Relaxed) > 0 {
+                let backtrace = Backtrace::force_capture();
+                PANIC_BACKTRACE
+                    .with(|last_backtrace| *last_backtrace.borrow_mut() = Some(backtrace));
+            }
+        }
+    });
+    *installed_hook = Some(InstalledHook {
+        previous_hook,
+        address: hook_address(&hook),
+    });
+    HOOK_INSTALLED.store(true, Ordering::Release);
+    panic::set_hook(hook);
+}
+
+/// Restore the panic hook that was registered before [`install_hook`] was called.
+///
+/// If another panic hook was registered after [`install_hook`], nothing is changed,
+/// since that hook most likely chains to the installed one and would be removed as well.
+/// Otherwise the hook is installed again the next time `repeated_assert` is used.
+pub fn uninstall_hook() {
+    let mut installed_hook = installed_hook().lock().expect("lock panic hook");
+    let Some(InstalledHook { address, .. }) = *installed_hook else {
+        return;
+    };
+    let current_hook = panic::take_hook();
+    if hook_address(&current_hook) != address {
+        // leave the chain of panic hooks alone
+        panic::set_hook(current_hook);
+        return;
+    }
+    let previous_hook = installed_hook
+        .take()
+        .expect("installed panic hook")
+        .previous_hook;
+    HOOK_INSTALLED.store(false, Ordering::Release);
+    drop(current_hook);
+    panic::set_hook(Box::new(move |panic_info| previous_hook(panic_info)));
+}
+
+/// Take the location of the last ignored panic of the current thread.
+pub(crate) fn take_panic_location() -> Option<String> {
+    PANIC_LOCATION.with(|location| location.borrow_mut().take())
+}
+
+/// Take the backtrace of the last ignored panic of the current thread, if backtraces were captured.
+pub(crate) fn take_panic_backtrace() -> Option<Backtrace> {
+    PANIC_BACKTRACE.with(|backtrace| backtrace.borrow_mut().take())
+}
+
+/// Check if panics of the current thread are ignored.
+pub(crate) fn thread_ignored() -> bool {
+    IGNORE_COUNT.with(|count| count.get() > 0)
+}
+
+/// Ignores panics of the current thread while alive.
+///
+/// Guards can be nested, panics are ignored until the outermost guard is dropped.
+pub(crate) struct IgnoreGuard;
+
+impl IgnoreGuard {
+    pub(crate) fn new() -> IgnoreGuard {
+        if !HOOK_INSTALLED.load(Ordering::Acquire) {
+            install_hook();
+        }
+        IGNORE_COUNT.with(|count| count.set(count.get() + 1));
+        IgnoreGuard
+    }
+}
+
+impl Drop for IgnoreGuard {
+    fn drop(&mut self) {
+        IGNORE_COUNT.with(|count| count.set(count.get() - 1));
+    }
+}
+
+/// Reports panics of the current thread while alive, even if ignore guards are active.
+///
+/// Used for user callbacks called during the retry loop (e.g. catch blocks and observers), whose panics aren't failed tries.
+pub(crate) struct ReportGuard {
+    /// Number of ignore guards suspended by this guard.
+    count: usize,
+}
+
+impl ReportGuard {
+    pub(crate) fn new() -> ReportGuard {
+        ReportGuard {
+            count: IGNORE_COUNT.with(|count| count.replace(0)),
+        }
+    }
+}
+
+impl Drop for ReportGuard {
+    fn drop(&mut self) {
+        IGNORE_COUNT.with(|count| count.set(count.get() + self.count));
+    }
+}
+
+/// Captures backtraces of ignored panics while alive.
+pub(crate) struct BacktraceGuard;
+
+impl BacktraceGuard {
+    pub(crate) fn new() -> BacktraceGuard {
+        CAPTURE_BACKTRACES.fetch_add(1, Ordering::Relaxed);
+        BacktraceGuard
+    }
+}
+
+impl Drop for BacktraceGuard {
+    fn drop(&mut self) {
+        CAPTURE_BACKTRACES.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Ignore panics while `future` is polled.
+///
+/// The ignore guard only lives during a single poll, so panics are ignored
+/// on whichever thread the future is polled (e.g. on work-stealing runtimes).
+#[cfg(feature = "async")]
+pub(crate) async fn ignore_panics<F>(future: F) -> F::Output
+where
+    F: std::future::Future,
+{
+    let mut future = std::pin::pin!(future);
+    futures::future::poll_fn(|cx| {
+        let _ignore_guard = IgnoreGuard::new();
+        future.as_mut().poll(cx)
+    })
+    .await
+}
+
+/// Report panics while `future` is polled, even if ignore guards are active.
+#[cfg(feature = "async")]
+pub(crate) async fn report_panics<F>(future: F) -> F::Output
+where
+    F: std::future::Future,
+{
+    let mut future = std::pin::pin!(future);
+    futures::future::poll_fn(|cx| {
+        let _report_guard = ReportGuard::new();
+        future.as_mut().poll(cx)
+    })
+    .await
+}
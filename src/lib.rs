@@ -1,7 +1,5 @@
 //! Run assertions multiple times
 //!
-//! **This crate currently requires the nightly version of the Rust compiler.**
-//!
 //! `repeated_assert` runs assertions until they either pass
 //! or the maximum amount of repetitions has been reached.
 //! The current thread will be blocked between tries.
@@ -11,13 +9,55 @@
 //!
 //! # Crate features
 //!
-//! * **async** - Enables the `that_async` and `with_catch_async` functions. It depends on the `futures` and `tokio` crates, which is why it's disabled by default.
+//! * **async** - Enables the `that_async` and `with_catch_async` functions. It depends on the `futures` and `futures-timer` crates, which is why it's disabled by default.
+//!   The async functions work with any runtime, see [`sleep`] to use the timer of a specific runtime.
+//! * **tokio** - Use the timer of the tokio runtime (version 1.x) for the async functions.
+//!   They follow paused time, so they don't wait in tests with `#[tokio::test(start_paused = true)]`.
+//!   Also enables the helpers for tokio channels in the [`channel`] module and [`net::wait_for_tcp_async`].
+//! * **async-std** - Use the timer of the async-std runtime for the async functions.
+//! * **smol** - Use the timer of the smol runtime for the async functions.
+//! * **wasm** - Use the timers of the JavaScript host for the async functions on `wasm32` targets (e.g. with `wasm-bindgen-test`).
+//!   Only the async functions are supported, since threads can't sleep. Panics can only be re-tried if the target supports unwinding.
+//! * **tracing** - Emit a `tracing` span for every retry, recording the number of tries and the outcome, and an event for every failed try.
+//! * **log** - Log the catch block notifications (`info`) and failed tries (`debug`) with the `log` crate instead of printing them to stdout.
+//!   The output can also be redirected with [`set_output`].
+//! * **notify** - Watch the files waited for by the [`fs`] module with the `notify` crate, to re-check them as soon as they change.
+//! * **regex** - Enables [`fs::wait_for_log_line`] to wait for a log line matching a regular expression,
+//!   [`wait_for_match`] to wait for any text to match, and the `matches_re` form of [`repeated_assert!`].
+//! * **http** - Enables [`net::wait_for_http_ok`] to wait for an HTTP endpoint with the `ureq` crate.
+//! * **cli** - Builds the `repeated-assert` binary, to retry shell commands until they succeed (e.g. `repeated-assert --tries 10 --delay 500ms -- curl -f http://localhost:8080/health`).
+//! * **macros** - Enables the [`#[repeated_assert::test]`](test) attribute, to run the body of a test repeatedly until it passes,
+//!   and the [`#[repeated_assert::flaky]`](flaky) attribute, to re-run a whole test if it fails.
+//! * **harness** - Enables the custom test [`harness`] built on `libtest-mimic`, where every test can declare its retry policy
+//!   and the summary includes retry statistics.
+//! * **capture** - Enables [`Retry::capture_output`], to capture the output of every try and only print it if the assertions fail.
+//!   It depends on the `gag` crate to redirect stdout and stderr.
+//! * **diff** - Print a colored diff instead of both values when [`assert_eventually_eq!`] fails, with the `pretty_assertions` crate.
+//! * **googletest** - Enables [`verify_eventually_that!`] and [`expect_eventually_that!`], to re-try matching a value with a
+//!   `googletest` matcher and report the explanation of the matcher if it never matches.
+//! * **insta** - Enables [`assert_eventually_snapshot!`], to re-try an `insta` snapshot assertion until the value matches the snapshot.
+//! * **precise-sleep** - Enables [`clock::PreciseClock`], which spins for the end of short delays with the `spin_sleep` crate,
+//!   for polling more often than the sleep granularity of the operating system allows.
+//!
+//! # Environment variables
+//!
+//! * **REPEATED_ASSERT_MAX_REPETITIONS** - Override the number of repetitions passed in code, e.g. to re-try more often on slow CI machines.
+//! * **REPEATED_ASSERT_DELAY_MS** - Override the delay between tries passed in code, in milliseconds.
+//! * **REPEATED_ASSERT_TIME_SCALE** - Multiply all delays, timeouts and deadlines, e.g. `3` when running under valgrind or coverage instrumentation.
+//!   Can also be changed with [`set_time_scale`].
+//! * **REPEATED_ASSERT_STRICT** - Set to `1` to enable strict mode, which fails repeated assertions on the first failed try
+//!   instead of re-trying them, to reveal tests that only pass because of re-trying (e.g. in a periodic CI job).
+//!
+//! Custom schedules aren't affected by the overrides, but are scaled as well.
+//! Neither are exact numbers of tries, like the samples of [`sample`] and [`at_least`].
+//! Invalid values are ignored with a warning.
 //!
 //! # Examples
 //!
 //! Waiting for a file to appear (re-try up to 10 times, wait 50 ms between tries)
 //!
-//! ```rust,ignore
+//! ```rust,no_run
+//! # use std::{path::Path, time::Duration};
 //! repeated_assert::that(10, Duration::from_millis(50), || {
 //!     assert!(Path::new("should_appear_soon.txt").exists());
 //! });
@@ -25,7 +65,9 @@
 //!
 //! Waiting for variable `x` to equal `3`
 //!
-//! ```rust,ignore
+//! ```rust
+//! # use std::time::Duration;
+//! # let x = 3;
 //! repeated_assert::that(10, Duration::from_millis(50), || {
 //!     assert_eq!(x, 3);
 //! });
@@ -33,7 +75,9 @@
 //!
 //! Temporary variables
 //!
-//! ```rust,ignore
+//! ```rust
+//! # use std::time::Duration;
+//! # fn crc(_path: &str) -> u32 { 1234 }
 //! repeated_assert::that(10, Duration::from_millis(50), || {
 //!     let checksum = crc("should_appear_soon.txt");
 //!     assert_eq!(checksum, 1234);
@@ -42,20 +86,124 @@
 //!
 //! Return result
 //!
-//! ```rust,ignore
+//! ```rust
+//! # use std::time::Duration;
+//! # fn crc(_path: &str) -> std::io::Result<u32> { Ok(1234) }
 //! repeated_assert::that(10, Duration::from_millis(50), || -> Result<_, Box<dyn std::error::Error>> {
 //!     let checksum = crc("should_appear_soon.txt")?;
 //!     assert_eq!(checksum, 1234);
+//!     Ok(())
 //! })?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! Waiting up to 5 s (poll every 50 ms)
+//!
+//! ```rust,no_run
+//! # use std::{path::Path, time::Duration};
+//! repeated_assert::until(Duration::from_secs(5), Duration::from_millis(50), || {
+//!     assert!(Path::new("should_appear_soon.txt").exists());
+//! });
+//! ```
+//!
+//! Waiting up to 5 s with the [`eventually!`] macro, which parses the durations at compile time
+//!
+//! ```rust
+//! # let queue = vec![1, 2, 3];
+//! repeated_assert::eventually!(within: "5s", poll: "100ms", {
+//!     assert_eq!(queue.len(), 3);
+//! });
+//! ```
+//!
+//! Waiting for a value with [`assert_eventually_eq!`], printing the values it had on failure
+//!
+//! ```rust
+//! # let queue = vec![1, 2, 3];
+//! repeated_assert::assert_eventually_eq!(queue.len(), 3, within = 5s);
+//! ```
+//!
+//! Fluent expectation with a matcher (see [`expect`])
+//!
+//! ```rust
+//! # use std::time::Duration;
+//! # let queue = vec![1, 2, 3];
+//! use repeated_assert::expect::{eq, expect};
+//!
+//! expect(|| queue.len()).to_eventually(eq(3)).within(Duration::from_secs(5));
+//! ```
+//!
+//! Custom schedule (see [`schedule`])
+//!
+//! ```rust,no_run
+//! # use std::{path::Path, time::Duration};
+//! let schedule = repeated_assert::schedule::exponential(Duration::from_millis(10), 2.0).take(10);
+//!
+//! repeated_assert::that_scheduled(schedule, || {
+//!     assert!(Path::new("should_appear_soon.txt").exists());
+//! });
+//! ```
+//!
+//! Builder (see [`Retry`])
+//!
+//! ```rust,no_run
+//! # use std::{path::Path, time::Duration};
+//! repeated_assert::Retry::new()
+//!     .delay(Duration::from_millis(10))
+//!     .backoff(2.0)
+//!     .timeout(Duration::from_secs(5))
+//!     .run(|| {
+//!         assert!(Path::new("should_appear_soon.txt").exists());
+//!     });
+//! ```
+//!
+//! Default policy for all call sites (see [`set_default_policy`])
+//!
+//! ```rust,no_run
+//! # use repeated_assert::Policy;
+//! # use std::{path::Path, time::Duration};
+//! repeated_assert::set_default_policy(
+//!     Policy::default()
+//!         .with_delay(Duration::from_millis(10))
+//!         .with_timeout(Duration::from_secs(5)),
+//! );
+//!
+//! repeated_assert::that_default(|| {
+//!     assert!(Path::new("should_appear_soon.txt").exists());
+//! });
+//! ```
+//!
+//! Testing retries without waiting, using a mock clock (see [`clock`])
+//!
+//! ```rust
+//! # use repeated_assert::clock::MockClock;
+//! # use std::time::Duration;
+//! # struct Service;
+//! # impl Service {
+//! #     fn is_ready(&self) -> bool { false }
+//! # }
+//! # let service = Service;
+//! let clock = MockClock::new();
+//!
+//! let result = repeated_assert::Retry::new()
+//!     .clock(clock.clone())
+//!     .timeout(Duration::from_secs(60))
+//!     .try_run(|| assert!(service.is_ready()));
+//!
+//! assert_eq!(clock.elapsed(), Duration::from_secs(60));
 //! ```
 //!
 //! Async
 //!
-//! ```rust,ignore
+//! ```rust
+//! # use std::time::Duration;
+//! # async fn query_db() -> &'static str { "success" }
+//! # #[cfg(feature = "async")]
+//! # async fn example() {
 //! repeated_assert::that_async(10, Duration::from_millis(50), || async {
 //!     let status = query_db().await;
 //!     assert_eq!(status, "success");
 //! }).await;
+//! # }
 //! ```
 //!
 //! # Catch failing tests
@@ -66,7 +214,8 @@
 //!
 //! Poke unreliable service after 5 unsuccessful assertion attempts
 //!
-//! ```rust,ignore
+//! ```rust,no_run
+//! # use std::{path::Path, time::Duration};
 //! repeated_assert::with_catch(10, Duration::from_millis(50), 5,
 //!     || {
 //!         // poke unreliable service
@@ -76,339 +225,1751 @@
 //!     }
 //! );
 //! ```
-use std::{
-    collections::HashSet,
-    panic,
-    sync::{Mutex, OnceLock},
-    thread,
-    time::Duration,
-};
-
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+use std::{ops::ControlFlow, thread, time::Duration};
+
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+use std::time::{Instant, SystemTime};
+// `std::time::Instant` isn't supported on `wasm32-unknown-unknown`
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+use web_time::{Instant, SystemTime};
+
+mod atomic;
+#[cfg(feature = "capture")]
+mod capture;
+pub mod channel;
+mod checkpoint;
+pub mod clock;
+mod config;
+mod contains;
+mod context;
+mod error;
+pub mod expect;
+mod ext;
+mod flakiness;
+pub mod fs;
+#[cfg(feature = "harness")]
+#[cfg_attr(docsrs, doc(cfg(feature = "harness")))]
+pub mod harness;
+mod history;
+mod hook;
 mod macros;
+pub mod net;
+mod observer;
+mod output;
+#[cfg(feature = "regex")]
+mod pattern;
+pub mod process;
+mod report;
+mod retry;
+pub mod schedule;
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub mod sleep;
+mod stages;
+mod trigger;
+
+pub use atomic::{eventually_eq_atomic, AtomicLoad};
+#[cfg(feature = "capture")]
+pub use capture::Replay;
+pub use checkpoint::Checkpoint;
+pub use config::{default_policy, set_default_policy, set_time_scale, time_scale, Policy};
+pub use context::{CatchAction, CatchContext, Context};
+pub use error::RetryError;
+pub use ext::Eventually;
+#[cfg(feature = "async")]
+pub use ext::EventuallyOk;
+pub use flakiness::{flakiness_report, flakiness_report_file, CallSite, FlakinessReport};
+pub use hook::{install_hook, uninstall_hook};
+pub use observer::{add_global_observer, Attempt, RetryObserver};
+pub use output::{reset_output, set_output};
+#[cfg(feature = "regex")]
+pub use pattern::wait_for_match;
+pub use report::{AttemptReport, Report, ReportOutput, SampleReport};
+pub use retry::Retry;
+pub use stages::Stages;
+pub use trigger::{Notifier, Trigger};
+
+/// Run the body of a test repeatedly until it passes.
+///
+/// The body is moved into a function that is called on every try, so `return` and `?` keep working as usual.
+/// Tests returning `Err` count as failed tries.
+///
+/// Arguments (all optional):
+///
+/// * `repetitions` - maximum number of tries
+/// * `delay` - delay between tries, e.g. `"100us"`, `"2s"` or `"1m 30s"` (the durations of [`eventually!`])
+/// * `timeout` - maximum time to re-try
+/// * `backoff` - factor the delay is multiplied with after every try
+/// * `name` - label of the assertions, defaults to the name of the test
+///
+/// The `#[test]` attribute is added unless the function has one already.
+/// For async functions `#[tokio::test]` is added, other runtimes can be used by adding their test attribute below.
+/// Async functions require the `async` feature (or one of the runtime features).
+///
+/// The attribute can be combined with `#[rstest]`, above or below it. The arguments of the test (fixtures and cases)
+/// are cloned for every try, so they have to implement `Clone`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use rstest::rstest;
+/// # use std::path::Path;
+/// # async fn query_db() -> &'static str { "success" }
+/// #[repeated_assert::test(repetitions = 10, delay = "50ms")]
+/// fn file_appears() -> std::io::Result<()> {
+///     let contents = std::fs::read_to_string("should_appear_soon.txt")?;
+///     assert_eq!(contents, "done");
+///     Ok(())
+/// }
+///
+/// #[repeated_assert::test(timeout = "5s")]
+/// async fn service_responds() {
+///     assert_eq!(query_db().await, "success");
+/// }
+///
+/// #[rstest]
+/// #[case("a.txt")]
+/// #[case("b.txt")]
+/// #[repeated_assert::test(repetitions = 10)]
+/// fn files_appear(#[case] path: &str) {
+///     assert!(Path::new(path).exists());
+/// }
+/// ```
+#[cfg(feature = "macros")]
+#[cfg_attr(docsrs, doc(cfg(feature = "macros")))]
+pub use repeated_assert_macros::test;
+
+/// Re-run a whole test, including its setup, if it fails.
+///
+/// Unlike [`test`](macro@test), which is meant for tests that wait for something to happen,
+/// this is meant for tests that fail randomly. The failed runs are quiet, and passing after a retry is
+/// reported in the [`flakiness_report`] and as diagnostic message (see [`set_output`]).
+///
+/// Arguments (all optional):
+///
+/// * `retries` - number of times the test is re-run after it failed, defaults to 2
+/// * `delay` - delay between runs, e.g. `"50ms"`, `"2s"` or `"1m"`, defaults to none
+/// * `name` - label of the test, defaults to the name of the test
+/// * `teardown` - function called after every run, even if it failed
+///
+/// # Examples
+///
+/// ```rust
+/// # struct Database;
+/// # impl Database {
+/// #     fn create(_path: &str) -> Database { Database }
+/// #     fn migrate(&self) -> Result<(), String> { Ok(()) }
+/// #     fn version(&self) -> u32 { 3 }
+/// # }
+/// fn remove_database() {
+///     let _ = std::fs::remove_file("test.db");
+/// }
+///
+/// #[repeated_assert::flaky(retries = 3, teardown = remove_database)]
+/// fn migrations() {
+///     let db = Database::create("test.db");
+///     db.migrate().unwrap();
+///     assert_eq!(db.version(), 3);
+/// }
+/// ```
+#[cfg(feature = "macros")]
+#[cfg_attr(docsrs, doc(cfg(feature = "macros")))]
+pub use repeated_assert_macros::flaky;
+
+// allows the tests to use the attribute macros, which refer to `::repeated_assert`
+#[cfg(all(test, feature = "macros"))]
+extern crate self as repeated_assert;
+
+/// Items used by the code generated by the macros.
+#[doc(hidden)]
+pub mod __private {
+    use std::{fmt::Debug, time::Duration};
+
+    pub use crate::contains::{ContainsItem, ContainsStr, Haystack};
+    pub use crate::history::History;
+    #[cfg(feature = "regex")]
+    pub use crate::pattern::check as matches_re;
+    #[cfg(feature = "googletest")]
+    pub use googletest;
+    #[cfg(feature = "insta")]
+    pub use insta;
+    #[cfg(feature = "regex")]
+    pub use regex::Regex;
+
+    /// Return types of tests supported by the attribute macros.
+    pub trait TestResult {
+        /// Panic if the test failed.
+        fn check(self) -> Self;
+    }
+
+    impl TestResult for () {
+        fn check(self) {}
+    }
+
+    impl<T, E: Debug> TestResult for Result<T, E> {
+        fn check(self) -> Self {
+            if let Err(error) = &self {
+                panic!("test returned `Err`: {:?}", error);
+            }
+            self
+        }
+    }
+
+    pub fn check<R: TestResult>(result: R) -> R {
+        result.check()
+    }
+
+    /// Report that the flaky test `name` passed on run `attempt`.
+    pub fn passed(name: &str, attempt: usize) {
+        if attempt > 1 {
+            crate::output::emit(
+                crate::output::Level::Info,
+                format_args!(
+                    "repeated-assert: flaky test `{}` passed on run {}",
+                    name, attempt
+                ),
+            );
+        }
+    }
+
+    /// Get the value of the `ok` form of `repeated_assert!`, or the failure message.
+    pub fn ok<T, E: Debug>(result: Result<T, E>, expression: &str) -> Result<T, String> {
+        result.map_err(|error| format!("`{}` returned `Err`: {:?}", expression, error))
+    }
+
+    /// Get the value of the `err` form of `repeated_assert!`, or the failure message.
+    pub fn err<T: Debug, E>(result: Result<T, E>, expression: &str) -> Result<E, String> {
+        match result {
+            Ok(value) => Err(format!("`{}` returned `Ok`: {:?}", expression, value)),
+            Err(error) => Ok(error),
+        }
+    }
+
+    /// Get the value of the `some` form of `repeated_assert!`, or the failure message.
+    pub fn some<T>(option: Option<T>, expression: &str) -> Result<T, String> {
+        option.ok_or_else(|| format!("`{}` returned `None`", expression))
+    }
+
+    /// Check the `none` form of `repeated_assert!`, returning the failure message.
+    pub fn none<T: Debug>(option: Option<T>, expression: &str) -> Result<(), String> {
+        match option {
+            Some(value) => Err(format!("`{}` returned `Some`: {:?}", expression, value)),
+            None => Ok(()),
+        }
+    }
+
+    /// Check that `left` and `right` differ by at most `epsilon`, returning the failure message otherwise.
+    pub fn approx(left: f64, right: f64, epsilon: f64, description: &str) -> Result<(), String> {
+        let delta = (left - right).abs();
+        // written this way to fail for NaN
+        if delta <= epsilon {
+            return Ok(());
+        }
+        let mut message = String::from("assertion `|left - right| <= epsilon` failed");
+        if !description.is_empty() {
+            message.push_str(": ");
+            message.push_str(description);
+        }
+        message.push_str(&format!(
+            "\n   left: {:?}\n  right: {:?}\n  delta: {:?}\nepsilon: {:?}",
+            left, right, delta, epsilon
+        ));
+        Err(message)
+    }
+
+    /// Parse durations like `5s`, `"100ms"`, `1.5s` or `1m 30s` passed to the macros.
+    ///
+    /// Evaluated at compile time by the macros, so invalid durations are compile errors.
+    pub const fn duration(value: &str) -> Duration {
+        let mut bytes = value.as_bytes();
+        // string literals are passed with their quotes
+        if let [b'"', inner @ .., b'"'] = bytes {
+            bytes = inner;
+        }
+        match parse_duration(bytes) {
+            Ok(duration) => duration,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Parse durations like `100us`, `1.5s` or `1m 30s`, the grammar of all durations of the macros and the CLI.
+    ///
+    /// A duration is a sequence of numbers with a unit (`ns`, `us`, `ms`, `s`, `m` or `h`), optionally separated by spaces.
+    pub const fn parse_duration(bytes: &[u8]) -> Result<Duration, &'static str> {
+        let mut nanos: u128 = 0;
+        let mut components = 0;
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b' ' {
+                i += 1;
+                continue;
+            }
+            let mut number: u128 = 0;
+            let mut divisor: u128 = 1;
+            let mut digits = 0;
+            let mut fraction = false;
+            while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+                if bytes[i] == b'.' {
+                    if fraction {
+                        return Err("invalid duration: number with multiple `.`");
+                    }
+                    fraction = true;
+                } else if digits == 20 {
+                    return Err("invalid duration: number too large");
+                } else {
+                    number = number * 10 + (bytes[i] - b'0') as u128;
+                    digits += 1;
+                    if fraction {
+                        divisor *= 10;
+                    }
+                }
+                i += 1;
+            }
+            if digits == 0 {
+                return Err("invalid duration: expected a number");
+            }
+            let start = i;
+            while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+                i += 1;
+            }
+            let unit = bytes.split_at(start).1.split_at(i - start).0;
+            let unit_nanos: u128 = match unit {
+                b"ns" => 1,
+                b"us" => 1_000,
+                b"ms" => 1_000_000,
+                b"s" => 1_000_000_000,
+                b"m" | b"min" => 60_000_000_000,
+                b"h" => 3_600_000_000_000,
+                _ => {
+                    return Err(
+                        "invalid duration: expected a unit (`ns`, `us`, `ms`, `s`, `m` or `h`)",
+                    )
+                }
+            };
+            nanos += number * unit_nanos / divisor;
+            components += 1;
+        }
+        if components == 0 {
+            return Err("invalid duration: expected a number");
+        }
+        if nanos / 1_000_000_000 > u64::MAX as u128 {
+            return Err("invalid duration: too large");
+        }
+        Ok(Duration::new(
+            (nanos / 1_000_000_000) as u64,
+            (nanos % 1_000_000_000) as u32,
+        ))
+    }
+}
+
+/// Run the provided function `assert` up to `repetitions` times with a `delay` in between tries.
+///
+/// Panics (including failed assertions) will be caught and ignored until the last try is executed.
+/// The panic of the last try is raised again with the number of tries and the call site added to its message.
+/// Panics with other payloads than strings (e.g. from [`std::panic::panic_any`]) are resumed unchanged,
+/// after printing the number of tries and the call site to stderr.
+///
+/// # Examples
+///
+/// Waiting for a file to appear (re-try up to 10 times, wait 50 ms between tries)
+///
+/// ```rust,no_run
+/// # use std::{path::Path, time::Duration};
+/// repeated_assert::that(10, Duration::from_millis(50), || {
+///     assert!(Path::new("should_appear_soon.txt").exists());
+/// });
+/// ```
+///
+/// # Info
+///
+/// Behind the scene `std::panic::set_hook` is used to set a custom panic handler.
+/// For every iteration but the last, panics are ignored and re-tried after a delay.
+/// Only when the last iteration is reached, panics are handled by the panic handler that was registered prior to calling `repeated_assert`.
+/// The panic message of the last iteration is extended by the number of tries, the elapsed time and the location of the call to `repeated_assert`.
+///
+/// `assert` may mutate captured state (e.g. a counter or a client connection), which is kept between tries.
+/// If a try panics while the state is modified, the next try sees the partial modification.
+///
+/// The panic handler can only be registerd for the entire process, and it is done on demand the first time `repeated_assert` is used
+/// (or explicitly with [`install_hook`]).
+/// `repeated_assert` works with multiple threads. Panics are only ignored on the thread running the assertions (tracked with a thread-local counter),
+/// so unnamed threads are supported and panics of other threads don't contend on a lock.
+#[track_caller]
+pub fn that<A, R>(repetitions: usize, delay: Duration, assert: A) -> R
+where
+    A: FnMut() -> R,
+{
+    Retry::new()
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .run(assert)
+}
+
+/// Run the provided function `assert` up to `repetitions` times with a `delay` in between tries, passing information about the current try.
+///
+/// # Examples
+///
+/// Log the current try
+///
+/// ```rust,no_run
+/// # use std::{path::Path, time::Duration};
+/// repeated_assert::that_with_context(10, Duration::from_millis(50), |context| {
+///     println!("try {} after {:?}", context.attempt(), context.elapsed());
+///     assert!(Path::new("should_appear_soon.txt").exists());
+/// });
+/// ```
+///
+/// # Info
+///
+/// See [`that`].
+#[track_caller]
+pub fn that_with_context<A, R>(repetitions: usize, delay: Duration, assert: A) -> R
+where
+    A: FnMut(&Context) -> R,
+{
+    Retry::new()
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .run_with_context(assert)
+}
+
+/// Run the provided function `assert` every `delay` for the whole `duration`, requiring it to pass every time.
+///
+/// Panics as soon as the assertions fail. Returns the value of the last try.
+///
+/// # Examples
+///
+/// Make sure the message isn't delivered twice
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # fn received_messages() -> Vec<String> { Vec::new() }
+/// repeated_assert::consistently(Duration::from_secs(1), Duration::from_millis(50), || {
+///     assert!(received_messages().len() <= 1);
+/// });
+/// ```
+///
+/// # Info
+///
+/// The first try is executed immediately, the last one after `duration` passed at the latest.
+#[track_caller]
+pub fn consistently<A, R>(duration: Duration, delay: Duration, assert: A) -> R
+where
+    A: FnMut() -> R,
+{
+    Retry::new()
+        .timeout(duration)
+        .fixed_delay(delay)
+        .run_consistently(assert)
+}
+
+/// Run the provided function `assert` exactly `repetitions` times with a `delay` in between tries, requiring it to pass at least `successes` times.
+///
+/// Returns the ratio of passed tries.
+///
+/// # Examples
+///
+/// Requests should be served by the new backend most of the time
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # struct Response { backend: &'static str }
+/// # fn send_request() -> Response { Response { backend: "new" } }
+/// let ratio = repeated_assert::at_least(8, 10, Duration::from_millis(50), || {
+///     assert_eq!(send_request().backend, "new");
+/// });
+/// ```
+///
+/// # Info
+///
+/// Useful for probabilistic systems (e.g. sampling or load balancing), where not every try has to pass.
+#[track_caller]
+pub fn at_least<A, R>(successes: usize, repetitions: usize, delay: Duration, assert: A) -> f64
+where
+    A: FnMut() -> R,
+{
+    Retry::new()
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .run_at_least(successes, assert)
+}
+
+/// Run the provided function `assert` exactly `repetitions` times with a `delay` in between tries, measuring the pass rate without panicking.
+///
+/// # Examples
+///
+/// Quantify the flakiness before deciding on thresholds
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # struct Response { status: u16 }
+/// # fn send_request() -> Response { Response { status: 200 } }
+/// let report = repeated_assert::sample(100, Duration::from_millis(10), || {
+///     assert_eq!(send_request().status, 200);
+/// });
+///
+/// println!("pass rate: {:.2}", report.pass_rate());
+/// for message in report.failure_messages() {
+///     println!("{}", message);
+/// }
+/// ```
+#[track_caller]
+pub fn sample<A, R>(repetitions: usize, delay: Duration, assert: A) -> SampleReport
+where
+    A: FnMut() -> R,
+{
+    Retry::new()
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .sample(assert)
+}
+
+/// Run the provided function `assert` up to `repetitions` times with a `delay` in between tries until it passes,
+/// then keep running it every `delay` for the whole `window`, requiring it to pass every time.
+///
+/// Returns the value of the last try.
+///
+/// # Examples
+///
+/// Catch services that briefly report ready and then crash
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # fn service_status() -> &'static str { "ready" }
+/// repeated_assert::eventually_then_always(10, Duration::from_millis(50), Duration::from_secs(1), || {
+///     assert_eq!(service_status(), "ready");
+/// });
+/// ```
+///
+/// # Info
+///
+/// See [`that`] and [`consistently`].
+#[track_caller]
+pub fn eventually_then_always<A, R>(
+    repetitions: usize,
+    delay: Duration,
+    window: Duration,
+    assert: A,
+) -> R
+where
+    A: FnMut() -> R,
+{
+    Retry::new()
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .run_then_always(window, assert)
+}
+
+/// Check the provided `condition` every `delay` for the whole `duration`, panicking as soon as it becomes true.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # fn received_messages() -> Vec<String> { Vec::new() }
+/// repeated_assert::never(Duration::from_secs(1), Duration::from_millis(50), || {
+///     received_messages().len() > 1
+/// });
+/// ```
+///
+/// # Info
+///
+/// See [`consistently`].
+#[track_caller]
+pub fn never<C>(duration: Duration, delay: Duration, mut condition: C)
+where
+    C: FnMut() -> bool,
+{
+    consistently(duration, delay, || {
+        assert!(!condition(), "condition became true");
+    })
+}
+
+/// Run the provided function `assert` with the [default policy](default_policy).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use std::path::Path;
+/// repeated_assert::that_default(|| {
+///     assert!(Path::new("should_appear_soon.txt").exists());
+/// });
+/// ```
+///
+/// # Info
+///
+/// See [`that`] and [`set_default_policy`].
+#[track_caller]
+pub fn that_default<A, R>(assert: A) -> R
+where
+    A: FnMut() -> R,
+{
+    Retry::new().run(assert)
+}
+
+/// Run the provided function `assert` up to `repetitions` times with a `delay` in between tries, returning the time until it passed alongside the value.
+///
+/// Use [`Retry::max_latency`] to fail if it passed too late.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::{collections::HashMap, time::Duration};
+/// # let cache: HashMap<&str, String> = HashMap::new();
+/// let ((), elapsed) = repeated_assert::that_timed(10, Duration::from_millis(50), || {
+///     assert_eq!(cache.get("key"), None);
+/// });
+///
+/// println!("cache invalidation took {:?}", elapsed);
+/// ```
+///
+/// # Info
+///
+/// See [`that`].
+#[track_caller]
+pub fn that_timed<A, R>(repetitions: usize, delay: Duration, assert: A) -> (R, Duration)
+where
+    A: FnMut() -> R,
+{
+    Retry::new()
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .run_timed(assert)
+}
+
+/// Run the provided function `assert` up to `repetitions` times with a `delay` in between tries, labeled with `name`.
+///
+/// The name is included in all diagnostics, reports and the final panic message,
+/// which helps to tell multiple repeated assertions apart.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # fn count_rows() -> usize { 1 }
+/// repeated_assert::that_named("db row appears", 10, Duration::from_millis(50), || {
+///     assert_eq!(count_rows(), 1);
+/// });
+/// ```
+///
+/// # Info
+///
+/// See [`that`].
+#[track_caller]
+pub fn that_named<N, A, R>(name: N, repetitions: usize, delay: Duration, assert: A) -> R
+where
+    N: Into<String>,
+    A: FnMut() -> R,
+{
+    Retry::new()
+        .name(name)
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .run(assert)
+}
+
+/// Run the provided function `assert` up to `repetitions` times with a `delay` in between tries, returning statistics about the tries alongside the value.
+///
+/// # Examples
+///
+/// Track how close the assertions come to the retry budget
+///
+/// ```rust,no_run
+/// # use std::{path::Path, time::Duration};
+/// let ((), report) = repeated_assert::that_with_report(10, Duration::from_millis(50), || {
+///     assert!(Path::new("should_appear_soon.txt").exists());
+/// });
+///
+/// println!("needed {} of 10 tries in {:?}", report.attempts(), report.elapsed());
+/// ```
+///
+/// # Info
+///
+/// See [`that`].
+#[track_caller]
+pub fn that_with_report<A, R>(repetitions: usize, delay: Duration, assert: A) -> (R, Report)
+where
+    A: FnMut() -> R,
+{
+    Retry::new()
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .run_with_report(assert)
+}
+
+/// Run the provided function `assert` up to `repetitions` times with a `delay` in between tries, passing `state` which is kept between tries.
+///
+/// Returns the state after the successful try.
+///
+/// # Examples
+///
+/// Collect log lines until the expected one appeared
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # fn read_new_log_lines() -> Vec<String> { vec!["server ready".to_string()] }
+/// let lines = repeated_assert::that_with_state(10, Duration::from_millis(50), Vec::new(), |lines| {
+///     lines.extend(read_new_log_lines());
+///     assert!(lines.iter().any(|line| line.contains("ready")));
+/// });
+/// ```
+///
+/// # Info
+///
+/// See [`that`].
+#[track_caller]
+pub fn that_with_state<T, A>(repetitions: usize, delay: Duration, state: T, assert: A) -> T
+where
+    A: FnMut(&mut T),
+{
+    Retry::new()
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .run_with_state(state, assert)
+}
+
+/// Run the provided function `assert` with the delays between tries taken from `schedule`.
+///
+/// Every item of `schedule` is the delay before the next try.
+/// When `schedule` is exhausted, `assert` is run one last time without catching panics.
+///
+/// The schedule has to be `Send`, because it is stored in the [`Retry`], which is `Send` so the futures of its async
+/// run methods can be spawned on multi-threaded runtimes. Finite schedules with thread-local state (e.g. `Rc` or `Cell`)
+/// can be collected into a `Vec` first.
+///
+/// # Examples
+///
+/// Re-try with increasing delays (10 ms, 20 ms, 40 ms, ...) up to 10 times
+///
+/// ```rust,no_run
+/// # use std::{path::Path, time::Duration};
+/// let schedule = repeated_assert::schedule::exponential(Duration::from_millis(10), 2.0).take(10);
+///
+/// repeated_assert::that_scheduled(schedule, || {
+///     assert!(Path::new("should_appear_soon.txt").exists());
+/// });
+/// ```
+///
+/// # Info
+///
+/// See [`that`].
+#[track_caller]
+pub fn that_scheduled<S, A, R>(schedule: S, assert: A) -> R
+where
+    S: IntoIterator<Item = Duration>,
+    S::IntoIter: Send,
+    A: FnMut() -> R,
+{
+    Retry::new().schedule(schedule).run(assert)
+}
+
+/// Run the provided function `assert` up to `repetitions` times with a `delay` in between tries, without panicking.
+///
+/// Panics (including failed assertions) will be caught for all tries, including the last one.
+/// If the last try fails, a [`RetryError`] containing the number of tries, the elapsed time and the last panic message is returned.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use std::{path::Path, time::Duration};
+/// let result = repeated_assert::try_that(10, Duration::from_millis(50), || {
+///     assert!(Path::new("should_appear_soon.txt").exists());
+/// });
+///
+/// if let Err(error) = result {
+///     println!("file didn't appear after {:?}", error.elapsed());
+/// }
+/// ```
+///
+/// # Info
+///
+/// See [`that`].
+#[track_caller]
+pub fn try_that<A, R>(repetitions: usize, delay: Duration, assert: A) -> Result<R, RetryError>
+where
+    A: FnMut() -> R,
+{
+    Retry::new()
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .try_run(assert)
+}
+
+/// Run the provided function `assert` up to `repetitions` times with a `delay` in between tries, stopping early if it returns [`ControlFlow::Break`].
+///
+/// Failed assertions are re-tried as usual, while [`ControlFlow::Break`] signals a permanent failure,
+/// which is returned as error without further tries. [`ControlFlow::Continue`] signals success.
+///
+/// # Examples
+///
+/// Don't wait for a host which doesn't exist
+///
+/// ```rust,no_run
+/// # use std::{io, net::TcpStream, ops::ControlFlow, time::Duration};
+/// let result = repeated_assert::that_abortable(10, Duration::from_millis(50), || {
+///     match TcpStream::connect("localhost:8080") {
+///         Ok(stream) => ControlFlow::Continue(stream),
+///         Err(error) if error.kind() == io::ErrorKind::InvalidInput => ControlFlow::Break(error),
+///         Err(error) => panic!("failed to connect: {}", error),
+///     }
+/// });
+/// ```
+///
+/// # Info
+///
+/// See [`that`].
+#[track_caller]
+pub fn that_abortable<A, B, R>(repetitions: usize, delay: Duration, assert: A) -> Result<R, B>
+where
+    A: FnMut() -> ControlFlow<B, R>,
+{
+    Retry::new()
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .run_abortable(assert)
+}
+
+/// Call the provided function `poll` up to `repetitions` times with a `delay` in between tries, until it returns `Some` value.
+///
+/// Returns the value, or panics if `poll` returned `None` for all tries.
+///
+/// # Examples
+///
+/// Waiting for a message to arrive
+///
+/// ```rust
+/// # use std::{sync::mpsc, time::Duration};
+/// # let (sender, receiver) = mpsc::channel();
+/// # sender.send("hello").unwrap();
+/// let message = repeated_assert::poll_until(10, Duration::from_millis(50), || receiver.try_recv().ok());
+/// ```
+///
+/// # Info
+///
+/// See [`that`].
+#[track_caller]
+pub fn poll_until<P, T>(repetitions: usize, delay: Duration, poll: P) -> T
+where
+    P: FnMut() -> Option<T>,
+{
+    Retry::new()
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .poll(poll)
+}
+
+/// Call the provided function `poll` up to `repetitions` times with a `delay` in between tries, until it returns `Some` value, without panicking.
+///
+/// Returns a [`RetryError`] if `poll` returned `None` (or panicked) for all tries.
+///
+/// # Info
+///
+/// See [`try_that`].
+#[track_caller]
+pub fn try_poll_until<P, T>(repetitions: usize, delay: Duration, poll: P) -> Result<T, RetryError>
+where
+    P: FnMut() -> Option<T>,
+{
+    Retry::new()
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .try_poll(poll)
+}
+
+/// Call the provided function `operation` up to `repetitions` times with a `delay` in between tries, until it returns `Ok`.
+///
+/// Returns the first `Ok` value, or the error of the last try.
+/// Unlike [`that`], panics are not caught, so only errors are re-tried.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use std::{net::TcpStream, time::Duration};
+/// let stream = repeated_assert::retry_result(10, Duration::from_millis(50), || {
+///     TcpStream::connect("localhost:8080")
+/// })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[track_caller]
+pub fn retry_result<O, T, E>(repetitions: usize, delay: Duration, operation: O) -> Result<T, E>
+where
+    O: FnMut() -> Result<T, E>,
+{
+    Retry::new()
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .run_result(operation)
+}
+
+/// Run the provided function `assert` until `timeout` has passed with a `delay` in between tries.
+///
+/// Panics (including failed assertions) will be caught until the last try, which reports them like [`that`].
+/// The last try is executed once `timeout` has passed, even if `assert` takes longer than expected.
+///
+/// # Examples
+///
+/// Waiting up to 5 s for a file to appear (poll every 50 ms)
+///
+/// ```rust,no_run
+/// # use std::{path::Path, time::Duration};
+/// repeated_assert::until(Duration::from_secs(5), Duration::from_millis(50), || {
+///     assert!(Path::new("should_appear_soon.txt").exists());
+/// });
+/// ```
+///
+/// # Info
+///
+/// See [`that`].
+#[track_caller]
+pub fn until<A, R>(timeout: Duration, delay: Duration, assert: A) -> R
+where
+    A: FnMut() -> R,
+{
+    Retry::new().fixed_delay(delay).timeout(timeout).run(assert)
+}
+
+/// Run the provided function `assert` until `deadline` is reached with a `delay` in between tries.
+///
+/// # Info
+///
+/// See [`until`].
+#[track_caller]
+pub fn until_deadline<A, R>(deadline: Instant, delay: Duration, assert: A) -> R
+where
+    A: FnMut() -> R,
+{
+    Retry::new()
+        .fixed_delay(delay)
+        .deadline(deadline)
+        .run(assert)
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+#[track_caller]
+pub fn that_default_async<A, F, R>(assert: A) -> impl std::future::Future<Output = R>
+where
+    A: FnMut() -> F,
+    F: std::future::Future<Output = R>,
+{
+    Retry::new().run_async(assert)
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+#[track_caller]
+pub fn that_async<A, F, R>(
+    repetitions: usize,
+    delay: Duration,
+    assert: A,
+) -> impl std::future::Future<Output = R>
+where
+    A: FnMut() -> F,
+    F: std::future::Future<Output = R>,
+{
+    Retry::new()
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .run_async(assert)
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+#[track_caller]
+pub fn that_scheduled_async<S, A, F, R>(
+    schedule: S,
+    assert: A,
+) -> impl std::future::Future<Output = R>
+where
+    S: IntoIterator<Item = Duration>,
+    S::IntoIter: Send,
+    A: FnMut() -> F,
+    F: std::future::Future<Output = R>,
+{
+    let location = std::panic::Location::caller();
+    async move {
+        Retry::new()
+            .location(location)
+            .schedule(schedule)
+            .run_async(assert)
+            .await
+    }
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+#[track_caller]
+pub fn try_that_async<A, F, R>(
+    repetitions: usize,
+    delay: Duration,
+    assert: A,
+) -> impl std::future::Future<Output = Result<R, RetryError>>
+where
+    A: FnMut() -> F,
+    F: std::future::Future<Output = R>,
+{
+    Retry::new()
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .try_run_async(assert)
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+#[track_caller]
+pub fn until_async<A, F, R>(
+    timeout: Duration,
+    delay: Duration,
+    assert: A,
+) -> impl std::future::Future<Output = R>
+where
+    A: FnMut() -> F,
+    F: std::future::Future<Output = R>,
+{
+    Retry::new()
+        .fixed_delay(delay)
+        .timeout(timeout)
+        .run_async(assert)
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+#[track_caller]
+pub fn until_deadline_async<A, F, R>(
+    deadline: Instant,
+    delay: Duration,
+    assert: A,
+) -> impl std::future::Future<Output = R>
+where
+    A: FnMut() -> F,
+    F: std::future::Future<Output = R>,
+{
+    Retry::new()
+        .fixed_delay(delay)
+        .deadline(deadline)
+        .run_async(assert)
+}
+
+/// Run the provided function `assert` up to `repetitions` times with a `delay` in between tries.
+/// Execute the provided function `catch` after `repetitions_catch` failed tries in order to trigger an alternate strategy.
+///
+/// Panics (including failed assertions) will be caught until the last try, which reports them like [`that`].
+/// A panic in `catch` is reported and ends the retries, like in the catch blocks of [`Retry::catch_after`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use std::{path::Path, time::Duration};
+/// repeated_assert::with_catch(10, Duration::from_millis(50), 5,
+///     || {
+///         // poke unreliable service
+///     },
+///     || {
+///         assert!(Path::new("should_appear_soon.txt").exists());
+///     }
+/// );
+/// ```
+///
+/// # Info
+///
+/// Use [`Retry::catch_after_with_context`] to get the number of failed tries, the elapsed time and the last panic message in the catch block.
+///
+/// See [`that`].
+#[track_caller]
+pub fn with_catch<A, C, R>(
+    repetitions: usize,
+    delay: Duration,
+    repetitions_catch: usize,
+    catch: C,
+    assert: A,
+) -> R
+where
+    A: FnMut() -> R,
+    C: FnOnce(),
+{
+    Retry::new()
+        .repetitions(repetitions)
+        .fixed_delay(delay)
+        .run_with_catch(repetitions_catch, catch, assert)
+}
+
+/// Run the provided function `assert` with the delays between tries taken from `schedule`.
+/// Execute the provided function `catch` after `repetitions_catch` failed tries in order to trigger an alternate strategy.
+///
+/// The schedule has to be `Send`, see [`that_scheduled`].
+///
+/// # Info
+///
+/// See [`that_scheduled`] and [`with_catch`].
+#[track_caller]
+pub fn with_catch_scheduled<S, A, C, R>(
+    schedule: S,
+    repetitions_catch: usize,
+    catch: C,
+    assert: A,
+) -> R
+where
+    S: IntoIterator<Item = Duration>,
+    S::IntoIter: Send,
+    A: FnMut() -> R,
+    C: FnOnce(),
+{
+    Retry::new()
+        .schedule(schedule)
+        .run_with_catch(repetitions_catch, catch, assert)
+}
+
+/// Run the provided async function `assert` up to `repetitions` times with a `delay` in between tries.
+/// Execute the provided async function `catch` after `repetitions_catch` failed tries in order to trigger an alternate strategy.
+///
+/// # Info
+///
+/// Use [`Retry::catch_after`] for a sync catch block (e.g. a blocking shell command) with async assertions,
+/// or [`Retry::catch_after_async`] for an async catch block with sync assertions.
+///
+/// See [`with_catch`].
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+#[track_caller]
+pub fn with_catch_async<A, F, C, G, R>(
+    repetitions: usize,
+    delay: Duration,
+    repetitions_catch: usize,
+    catch: C,
+    assert: A,
+) -> impl std::future::Future<Output = R>
+where
+    A: FnMut() -> F,
+    F: std::future::Future<Output = R>,
+    C: FnOnce() -> G,
+    G: std::future::Future<Output = ()>,
+{
+    with_catch_scheduled_async(
+        schedule::fixed(repetitions, delay),
+        repetitions_catch,
+        catch,
+        assert,
+    )
+}
+
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+#[track_caller]
+pub fn with_catch_scheduled_async<S, A, F, C, G, R>(
+    schedule: S,
+    repetitions_catch: usize,
+    catch: C,
+    assert: A,
+) -> impl std::future::Future<Output = R>
+where
+    S: IntoIterator<Item = Duration>,
+    S::IntoIter: Send,
+    A: FnMut() -> F,
+    F: std::future::Future<Output = R>,
+    C: FnOnce() -> G,
+    G: std::future::Future<Output = ()>,
+{
+    let location = std::panic::Location::caller();
+    async move {
+        Retry::new()
+            .location(location)
+            .schedule(schedule)
+            .run_with_catch_async(repetitions_catch, catch, assert)
+            .await
+    }
+}
+
+fn print_catch_message(name: Option<&str>) {
+    let thread_name = thread::current()
+        .name()
+        .unwrap_or("<unnamed thread>")
+        .to_string();
+    match name {
+        Some(name) => output::emit(
+            output::Level::Info,
+            format_args!(
+                "{}: executing repeated-assert catch block of `{}`",
+                thread_name, name
+            ),
+        ),
+        None => output::emit(
+            output::Level::Info,
+            format_args!("{}: executing repeated-assert catch block", thread_name),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate as repeated_assert;
+    use std::ops::ControlFlow;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    #[cfg(feature = "macros")]
+    #[repeated_assert::test(delay = "100us", timeout = "1m 30s")]
+    fn attribute_durations() {}
+
+    #[cfg(feature = "macros")]
+    #[repeated_assert::test(repetitions = 5, delay = "10ms")]
+    fn attribute_success() -> Result<(), String> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static TRIES: AtomicUsize = AtomicUsize::new(0);
+
+        let tries = TRIES.fetch_add(1, Ordering::SeqCst) + 1;
+        if tries < 3 {
+            return Err(format!("try {}", tries));
+        }
+        assert_eq!(tries, 3);
+        Ok(())
+    }
+
+    #[cfg(feature = "macros")]
+    #[repeated_assert::test(repetitions = 3, delay = "10ms")]
+    #[should_panic(expected = "repeated-assert: `attribute_failure` failed after 3 tries")]
+    fn attribute_failure() {
+        panic!("not ready");
+    }
+
+    #[cfg(feature = "macros")]
+    mod flaky {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        static TEARDOWNS: AtomicUsize = AtomicUsize::new(0);
+
+        fn teardown() {
+            TEARDOWNS.fetch_add(1, Ordering::SeqCst);
+        }
+
+        #[crate::flaky(retries = 3, teardown = teardown)]
+        fn flaky_success() {
+            let runs = RUNS.fetch_add(1, Ordering::SeqCst) + 1;
+            // every run tears down, including the failed ones
+            assert_eq!(TEARDOWNS.load(Ordering::SeqCst), runs - 1);
+            assert!(runs > 2);
+        }
+
+        #[crate::flaky(retries = 1)]
+        #[should_panic(expected = "repeated-assert: `flaky_failure` failed after 2 tries")]
+        fn flaky_failure() {
+            panic!("always fails");
+        }
+    }
+
+    #[cfg(feature = "macros")]
+    mod rstest_cases {
+        use rstest::{fixture, rstest};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static TRIES_BELOW: AtomicUsize = AtomicUsize::new(0);
+        static TRIES_ABOVE: AtomicUsize = AtomicUsize::new(0);
+
+        #[fixture]
+        fn threshold() -> usize {
+            3
+        }
+
+        #[rstest]
+        #[case(&TRIES_BELOW)]
+        #[repeated_assert::test(repetitions = 5, delay = "10ms")]
+        fn attribute_below_rstest(#[case] tries: &AtomicUsize, threshold: usize) {
+            assert!(tries.fetch_add(1, Ordering::SeqCst) + 1 >= threshold);
+        }
+
+        #[repeated_assert::test(repetitions = 5, delay = "10ms")]
+        #[rstest]
+        #[case(&TRIES_ABOVE, String::from("cloned"))]
+        fn attribute_above_rstest(
+            #[case] tries: &AtomicUsize,
+            #[case] mut value: String,
+            threshold: usize,
+        ) {
+            // every try gets a fresh clone of the arguments
+            assert_eq!(value, "cloned");
+            value.push('!');
+            assert!(tries.fetch_add(1, Ordering::SeqCst) + 1 >= threshold);
+        }
+    }
+
+    #[cfg(all(feature = "macros", feature = "async"))]
+    #[repeated_assert::test(repetitions = 5, delay = "10ms")]
+    async fn attribute_success_async() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static TRIES: AtomicUsize = AtomicUsize::new(0);
+
+        let tries = TRIES.fetch_add(1, Ordering::SeqCst) + 1;
+        futures::future::ready(()).await;
+        assert!(tries >= 3);
+    }
+
+    static STEP_MS: u64 = 100;
+
+    fn spawn_thread(x: Arc<Mutex<i32>>) {
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(10 * STEP_MS));
+            if let Ok(mut x) = x.lock() {
+                *x += 1;
+            }
+        });
+    }
+
+    // #[test]
+    // fn slow() {
+    //     let x = Arc::new(Mutex::new(0));
+
+    //     spawn_thread(x.clone());
+
+    //     repeated_assert::that(10, Duration::from_millis(10 * STEP_MS), || {
+    //         assert!(*x.lock().unwrap() > 5);
+    //     });
+    // }
+
+    // #[test]
+    // fn panic() {
+    //     let x = Arc::new(Mutex::new(0));
+
+    //     spawn_thread(x.clone());
+
+    //     repeated_assert::that(3, Duration::from_millis(10 * STEP_MS), || {
+    //         assert!(*x.lock().unwrap() > 5);
+    //     });
+    // }
+
+    #[test]
+    fn single_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert::that(5, Duration::from_millis(5 * STEP_MS), || {
+            assert!(*x.lock().unwrap() > 0);
+        });
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn single_success_async() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert::that_async(5, Duration::from_millis(5 * STEP_MS), || async {
+            assert!(*x.lock().unwrap() > 0);
+        })
+        .await;
+    }
+
+    #[test]
+    fn unnamed_thread_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        thread::spawn(move || {
+            repeated_assert::that(5, Duration::from_millis(5 * STEP_MS), || {
+                assert!(*x.lock().unwrap() > 0);
+            });
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn nested_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert::that(5, Duration::from_millis(5 * STEP_MS), || {
+            repeated_assert::that(2, Duration::from_millis(STEP_MS), || {});
+            // the outer loop still ignores panics after the inner loop finished
+            assert!(crate::hook::thread_ignored());
+            assert!(*x.lock().unwrap() > 0);
+        });
+        assert!(!crate::hook::thread_ignored());
+    }
+
+    #[test]
+    #[should_panic(expected = "assertion failed: *x.lock().unwrap() > 0")]
+    fn nested_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert::that(2, Duration::from_millis(STEP_MS), || {
+            repeated_assert::that(2, Duration::from_millis(STEP_MS), || {
+                assert!(*x.lock().unwrap() > 0);
+            });
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "assertion failed: *x.lock().unwrap() > 0")]
+    fn single_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert::that(3, Duration::from_millis(STEP_MS), || {
+            assert!(*x.lock().unwrap() > 0);
+        });
+    }
+
+    #[test]
+    fn scheduled_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        let schedule =
+            repeated_assert::schedule::exponential(Duration::from_millis(STEP_MS), 2.0).take(5);
+
+        repeated_assert::that_scheduled(schedule, || {
+            assert!(*x.lock().unwrap() > 0);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "assertion failed: *x.lock().unwrap() > 0")]
+    fn scheduled_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        let schedule = [STEP_MS, STEP_MS, 2 * STEP_MS].map(Duration::from_millis);
+
+        repeated_assert::that_scheduled(schedule, || {
+            assert!(*x.lock().unwrap() > 0);
+        });
+    }
+
+    #[test]
+    fn try_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        let result = repeated_assert::try_that(5, Duration::from_millis(5 * STEP_MS), || {
+            assert!(*x.lock().unwrap() > 0);
+        });
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn try_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        let result = repeated_assert::try_that(3, Duration::from_millis(STEP_MS), || {
+            assert!(*x.lock().unwrap() > 0);
+        });
+        let error = result.unwrap_err();
+        assert_eq!(error.attempts(), 3);
+        assert_eq!(error.message(), "assertion failed: *x.lock().unwrap() > 0");
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn try_failure_async() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        let result = repeated_assert::try_that_async(3, Duration::from_millis(STEP_MS), || async {
+            assert!(*x.lock().unwrap() > 0);
+        })
+        .await;
+        let error = result.unwrap_err();
+        assert_eq!(error.attempts(), 3);
+        assert_eq!(error.message(), "assertion failed: *x.lock().unwrap() > 0");
+    }
+
+    #[test]
+    fn until_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert::until(
+            Duration::from_millis(20 * STEP_MS),
+            Duration::from_millis(STEP_MS),
+            || {
+                assert!(*x.lock().unwrap() > 0);
+            },
+        );
+    }
 
-fn ignore_threads() -> &'static Mutex<HashSet<String>> {
-    static INSTANCE: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
-    INSTANCE.get_or_init(|| {
-        // get original panic hook
-        let panic_hook = panic::take_hook();
-        // set custom panic hook
-        panic::set_hook(Box::new(move |panic_info| {
-            let ignore_threads = ignore_threads().lock().expect("lock ignore threads");
-            if let Some(thread_name) = thread::current().name() {
-                if !ignore_threads.contains(thread_name) {
-                    // call original panic hook
-                    panic_hook(panic_info);
-                }
-            } else {
-                // call original panic hook
-                panic_hook(panic_info);
-            }
-        }));
-        Mutex::new(HashSet::new())
-    })
-}
+    #[test]
+    fn mutable_success() {
+        let mut tries = 0;
 
-struct IgnoreGuard;
+        repeated_assert::that(5, Duration::from_millis(STEP_MS / 10), || {
+            tries += 1;
+            assert!(tries >= 3);
+        });
 
-impl IgnoreGuard {
-    fn new() -> IgnoreGuard {
-        if let Some(thread_name) = thread::current().name() {
-            ignore_threads()
-                .lock()
-                .expect("lock ignore threads")
-                .insert(thread_name.to_string());
-        }
-        IgnoreGuard
+        assert_eq!(tries, 3);
     }
-}
 
-impl Drop for IgnoreGuard {
-    fn drop(&mut self) {
-        if let Some(thread_name) = thread::current().name() {
-            ignore_threads()
-                .lock()
-                .expect("lock ignore threads")
-                .remove(thread_name);
-        }
+    #[test]
+    fn state_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        let values = repeated_assert::that_with_state(
+            5,
+            Duration::from_millis(5 * STEP_MS),
+            Vec::new(),
+            |values| {
+                values.push(*x.lock().unwrap());
+                assert!(*values.last().unwrap() > 0);
+            },
+        );
+
+        assert!(values.len() > 1);
+        assert_eq!(values[0], 0);
     }
-}
 
-/// Run the provided function `assert` up to `repetitions` times with a `delay` in between tries.
-///
-/// Panics (including failed assertions) will be caught and ignored until the last try is executed.
-///
-/// # Examples
-///
-/// Waiting for a file to appear (re-try up to 10 times, wait 50 ms between tries)
-///
-/// ```rust,ignore
-/// repeated_assert::that(10, Duration::from_millis(50), || {
-///     assert!(Path::new("should_appear_soon.txt").exists());
-/// });
-/// ```
-///
-/// # Info
-///
-/// Behind the scene `std::panic::set_hook` is used to set a custom panic handler.
-/// For every iteration but the last, panics are ignored and re-tried after a delay.
-/// Only when the last iteration is reached, panics are handled by the panic handler that was registered prior to calling `repeated_assert`.
-///
-/// The panic handler can only be registerd for the entire process, and it is done on demand the first time `repeated_assert` is used.
-/// `repeated_assert` works with multiple threads. Each thread is identified by its name, which is automatically set for tests.
-pub fn that<A, R>(repetitions: usize, delay: Duration, assert: A) -> R
-where
-    A: Fn() -> R,
-{
-    // add current thread to ignore list
-    let ignore_guard = IgnoreGuard::new();
-
-    for _ in 0..(repetitions - 1) {
-        // run assertions, catching panics
-        let result = panic::catch_unwind(panic::AssertUnwindSafe(&assert));
-        // return if assertions succeeded
-        if let Ok(value) = result {
-            return value;
-        }
-        // or sleep until the next try
-        thread::sleep(delay);
+    #[test]
+    fn consistently_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert::consistently(
+            Duration::from_millis(5 * STEP_MS),
+            Duration::from_millis(STEP_MS),
+            || {
+                assert_eq!(*x.lock().unwrap(), 0);
+            },
+        );
     }
 
-    // remove current thread from ignore list
-    drop(ignore_guard);
+    #[test]
+    #[should_panic(expected = "condition became true")]
+    fn never_failure() {
+        let x = Arc::new(Mutex::new(0));
 
-    // run assertions without catching panics
-    assert()
-}
+        spawn_thread(x.clone());
 
-#[cfg(feature = "async")]
-// #[doc(cfg(feature = "async"))]
-pub async fn that_async<A, F, R>(repetitions: usize, delay: Duration, assert: A) -> R
-where
-    A: Fn() -> F,
-    F: std::future::Future<Output = R>,
-{
-    use futures::future::FutureExt;
+        repeated_assert::never(
+            Duration::from_millis(20 * STEP_MS),
+            Duration::from_millis(STEP_MS),
+            || *x.lock().unwrap() > 0,
+        );
+    }
 
-    // add current thread to ignore list
-    let ignore_guard = IgnoreGuard::new();
+    #[test]
+    fn report_success() {
+        let x = Arc::new(Mutex::new(0));
 
-    for _ in 0..(repetitions - 1) {
-        // run assertions, catching panics
-        let result = panic::AssertUnwindSafe(assert()).catch_unwind().await;
-        // return if assertions succeeded
-        if let Ok(value) = result {
-            return value;
-        }
-        // or sleep until the next try
-        tokio::time::sleep(delay).await;
+        spawn_thread(x.clone());
+
+        let (value, report) =
+            repeated_assert::that_with_report(5, Duration::from_millis(5 * STEP_MS), || {
+                let x = *x.lock().unwrap();
+                assert!(x > 0);
+                x
+            });
+
+        assert_eq!(value, 1);
+        assert!(report.attempts() > 1);
+        assert!(report.elapsed() >= Duration::from_millis(5 * STEP_MS));
+        let (last, failed) = report.tries().split_last().unwrap();
+        assert!(last.is_success());
+        assert_eq!(last.number(), report.attempts());
+        assert!(failed
+            .iter()
+            .all(|attempt| attempt.message() == Some("assertion failed: x > 0")));
     }
 
-    // remove current thread from ignore list
-    drop(ignore_guard);
+    #[test]
+    #[should_panic(expected = "assertion failed: *values.last().unwrap() > 0")]
+    fn state_failure() {
+        let x = Arc::new(Mutex::new(0));
 
-    // run assertions without catching panics
-    assert().await
-}
+        spawn_thread(x.clone());
 
-/// Run the provided function `assert` up to `repetitions` times with a `delay` in between tries.
-/// Execute the provided function `catch` after `repetitions_catch` failed tries in order to trigger an alternate strategy.
-///
-/// Panics (including failed assertions) will be caught and ignored until the last try is executed.
-///
-/// # Examples
-///
-/// ```rust,ignore
-/// repeated_assert::with_catch(10, Duration::from_millis(50), 5,
-///     || {
-///         // poke unreliable service
-///     },
-///     || {
-///         assert!(Path::new("should_appear_soon.txt").exists());
-///     }
-/// );
-/// ```
-///
-/// # Info
-///
-/// See [`that`].
-pub fn with_catch<A, C, R>(
-    repetitions: usize,
-    delay: Duration,
-    repetitions_catch: usize,
-    catch: C,
-    assert: A,
-) -> R
-where
-    A: Fn() -> R,
-    C: FnOnce(),
-{
-    let ignore_guard = IgnoreGuard::new();
-
-    for _ in 0..repetitions_catch {
-        // run assertions, catching panics
-        let result = panic::catch_unwind(panic::AssertUnwindSafe(&assert));
-        // return if assertions succeeded
-        if let Ok(value) = result {
-            return value;
-        }
-        // or sleep until the next try
-        thread::sleep(delay);
+        repeated_assert::that_with_state(3, Duration::from_millis(STEP_MS), Vec::new(), |values| {
+            values.push(*x.lock().unwrap());
+            assert!(*values.last().unwrap() > 0);
+        });
     }
 
-    let thread_name = thread::current()
-        .name()
-        .unwrap_or("<unnamed thread>")
-        .to_string();
-    println!("{}: executing repeated-assert catch block", thread_name);
-    catch();
-
-    for _ in repetitions_catch..(repetitions - 1) {
-        // run assertions, catching panics
-        let result = panic::catch_unwind(panic::AssertUnwindSafe(&assert));
-        // return if assertions succeeded
-        if let Ok(value) = result {
-            return value;
-        }
-        // or sleep until the next try
-        thread::sleep(delay);
+    #[test]
+    fn poll_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        let value = repeated_assert::poll_until(5, Duration::from_millis(5 * STEP_MS), || {
+            Some(*x.lock().unwrap()).filter(|x| *x > 0)
+        });
+
+        assert!(value > 0);
     }
 
-    // remove current thread from ignore list
-    drop(ignore_guard);
+    #[test]
+    #[should_panic(expected = "polled function returned `None`")]
+    fn poll_failure() {
+        let x = Arc::new(Mutex::new(0));
 
-    // run assertions without catching panics
-    assert()
-}
+        spawn_thread(x.clone());
 
-#[cfg(feature = "async")]
-// #[doc(cfg(feature = "async"))]
-pub async fn with_catch_async<A, F, C, G, R>(
-    repetitions: usize,
-    delay: Duration,
-    repetitions_catch: usize,
-    catch: C,
-    assert: A,
-) -> R
-where
-    A: Fn() -> F,
-    F: std::future::Future<Output = R>,
-    C: FnOnce() -> G,
-    G: std::future::Future<Output = ()>,
-{
-    use futures::future::FutureExt;
+        repeated_assert::poll_until(3, Duration::from_millis(STEP_MS), || {
+            Some(*x.lock().unwrap()).filter(|x| *x > 0)
+        });
+    }
 
-    let ignore_guard = IgnoreGuard::new();
+    #[test]
+    fn try_poll_failure() {
+        let error =
+            repeated_assert::try_poll_until(3, Duration::from_millis(STEP_MS), || None::<i32>)
+                .unwrap_err();
 
-    for _ in 0..repetitions_catch {
-        // run assertions, catching panics
-        let result = panic::AssertUnwindSafe(assert()).catch_unwind().await;
-        // return if assertions succeeded
-        if let Ok(value) = result {
-            return value;
-        }
-        // or sleep until the next try
-        tokio::time::sleep(delay).await;
+        assert_eq!(error.attempts(), 3);
+        assert_eq!(error.message(), "polled function returned `None`");
     }
 
-    let thread_name = thread::current()
-        .name()
-        .unwrap_or("<unnamed thread>")
-        .to_string();
-    println!("{}: executing repeated-assert catch block", thread_name);
-    catch().await;
-
-    for _ in repetitions_catch..(repetitions - 1) {
-        // run assertions, catching panics
-        let result = panic::AssertUnwindSafe(assert()).catch_unwind().await;
-        // return if assertions succeeded
-        if let Ok(value) = result {
-            return value;
-        }
-        // or sleep until the next try
-        tokio::time::sleep(delay).await;
+    #[test]
+    fn result_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        let result = repeated_assert::retry_result(5, Duration::from_millis(5 * STEP_MS), || {
+            let x = *x.lock().unwrap();
+            if x > 0 {
+                Ok(x)
+            } else {
+                Err(format!("x is {}", x))
+            }
+        });
+
+        assert!(result.unwrap() > 0);
     }
 
-    // remove current thread from ignore list
-    drop(ignore_guard);
+    #[test]
+    fn result_failure() {
+        let mut tries = 0;
 
-    // run assertions without catching panics
-    assert().await
-}
+        let result = repeated_assert::retry_result(3, Duration::from_millis(STEP_MS), || {
+            tries += 1;
+            Err::<(), _>(tries)
+        });
 
-#[cfg(test)]
-mod tests {
-    use crate as repeated_assert;
-    use std::sync::{Arc, Mutex};
-    use std::thread;
-    use std::time::Duration;
+        assert_eq!(result, Err(3));
+    }
 
-    static STEP_MS: u64 = 100;
+    #[test]
+    fn abortable_success() {
+        let x = Arc::new(Mutex::new(0));
 
-    fn spawn_thread(x: Arc<Mutex<i32>>) {
-        thread::spawn(move || loop {
-            thread::sleep(Duration::from_millis(10 * STEP_MS));
-            if let Ok(mut x) = x.lock() {
-                *x += 1;
-            }
+        spawn_thread(x.clone());
+
+        let result = repeated_assert::that_abortable(5, Duration::from_millis(5 * STEP_MS), || {
+            let x = *x.lock().unwrap();
+            assert!(x > 0);
+            ControlFlow::<(), _>::Continue(x)
         });
+
+        assert!(result.unwrap() > 0);
     }
 
-    // #[test]
-    // fn slow() {
-    //     let x = Arc::new(Mutex::new(0));
+    #[test]
+    fn abortable_break() {
+        let mut tries = 0;
 
-    //     spawn_thread(x.clone());
+        let result = repeated_assert::that_abortable(5, Duration::from_millis(STEP_MS), || {
+            tries += 1;
+            assert!(tries > 1);
+            ControlFlow::<_, ()>::Break("permanent failure")
+        });
 
-    //     repeated_assert::that(10, Duration::from_millis(10 * STEP_MS), || {
-    //         assert!(*x.lock().unwrap() > 5);
-    //     });
-    // }
+        assert_eq!(result, Err("permanent failure"));
+        assert_eq!(tries, 2);
+    }
 
-    // #[test]
-    // fn panic() {
-    //     let x = Arc::new(Mutex::new(0));
+    #[test]
+    fn context_success() {
+        let attempts = Mutex::new(Vec::new());
 
-    //     spawn_thread(x.clone());
+        repeated_assert::that_with_context(5, Duration::from_millis(STEP_MS / 10), |context| {
+            attempts
+                .lock()
+                .unwrap()
+                .push((context.attempt(), context.remaining()));
+            assert!(context.is_last());
+        });
 
-    //     repeated_assert::that(3, Duration::from_millis(10 * STEP_MS), || {
-    //         assert!(*x.lock().unwrap() > 5);
-    //     });
-    // }
+        assert_eq!(
+            *attempts.lock().unwrap(),
+            vec![
+                (1, Some(4)),
+                (2, Some(3)),
+                (3, Some(2)),
+                (4, Some(1)),
+                (5, Some(0))
+            ]
+        );
+    }
 
     #[test]
-    fn single_success() {
+    #[should_panic(expected = "assertion failed: *x.lock().unwrap() > 0")]
+    fn until_failure() {
         let x = Arc::new(Mutex::new(0));
 
         spawn_thread(x.clone());
 
-        repeated_assert::that(5, Duration::from_millis(5 * STEP_MS), || {
-            assert!(*x.lock().unwrap() > 0);
-        });
+        repeated_assert::until(
+            Duration::from_millis(3 * STEP_MS),
+            Duration::from_millis(STEP_MS),
+            || {
+                assert!(*x.lock().unwrap() > 0);
+            },
+        );
     }
 
     #[cfg(feature = "async")]
     #[tokio::test]
-    async fn single_success_async() {
+    async fn until_success_async() {
         let x = Arc::new(Mutex::new(0));
 
         spawn_thread(x.clone());
 
-        repeated_assert::that_async(5, Duration::from_millis(5 * STEP_MS), || async {
-            assert!(*x.lock().unwrap() > 0);
-        })
+        repeated_assert::until_async(
+            Duration::from_millis(20 * STEP_MS),
+            Duration::from_millis(STEP_MS),
+            || async {
+                assert!(*x.lock().unwrap() > 0);
+            },
+        )
         .await;
     }
 
-    #[test]
-    #[should_panic(expected = "assertion failed: *x.lock().unwrap() > 0")]
-    fn single_failure() {
+    #[cfg(feature = "async")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn spawned_success_async() {
         let x = Arc::new(Mutex::new(0));
 
         spawn_thread(x.clone());
 
-        repeated_assert::that(3, Duration::from_millis(STEP_MS), || {
-            assert!(*x.lock().unwrap() > 0);
-        });
+        tokio::spawn(async move {
+            repeated_assert::that_async(5, Duration::from_millis(5 * STEP_MS), || async {
+                assert!(*x.lock().unwrap() > 0);
+            })
+            .await;
+        })
+        .await
+        .unwrap();
     }
 
     #[cfg(feature = "async")]
@@ -538,6 +2099,70 @@ mod tests {
         );
     }
 
+    #[test]
+    fn catch_borrowed() {
+        use std::cell::Cell;
+
+        let tries = Cell::new(0);
+        let caught = Cell::new(0);
+
+        repeated_assert::with_catch(
+            5,
+            Duration::from_millis(STEP_MS),
+            2,
+            || caught.set(tries.get()),
+            || {
+                tries.set(tries.get() + 1);
+                assert!(caught.get() > 0);
+            },
+        );
+
+        assert_eq!(caught.get(), 2);
+        assert_eq!(tries.get(), 3);
+    }
+
+    #[test]
+    fn catch_panic() {
+        use std::panic;
+
+        // the free function and the builder execute catch blocks the same way
+        let mut reported = Vec::new();
+        let with_catch = panic::catch_unwind::<_, ()>(panic::AssertUnwindSafe(|| {
+            repeated_assert::with_catch(
+                5,
+                Duration::from_millis(STEP_MS),
+                2,
+                || {
+                    reported.push(!repeated_assert::hook::thread_ignored());
+                    panic!("catch failed");
+                },
+                || panic!("not ready"),
+            )
+        }))
+        .unwrap_err();
+        let catch_after = panic::catch_unwind::<_, ()>(panic::AssertUnwindSafe(|| {
+            repeated_assert::Retry::new()
+                .repetitions(5)
+                .delay(Duration::from_millis(STEP_MS))
+                .catch_after::<_, ()>(2, || {
+                    reported.push(!repeated_assert::hook::thread_ignored());
+                    panic!("catch failed");
+                })
+                .run(|| panic!("not ready"))
+        }))
+        .unwrap_err();
+
+        assert_eq!(reported, [true, true]);
+        assert_eq!(
+            repeated_assert::error::panic_message(&*with_catch),
+            "catch failed"
+        );
+        assert_eq!(
+            repeated_assert::error::panic_message(&*catch_after),
+            "catch failed"
+        );
+    }
+
     #[cfg(feature = "async")]
     #[tokio::test]
     async fn catch_async() {
@@ -558,4 +2183,25 @@ mod tests {
         )
         .await;
     }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn catch_borrowed_async() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let x = Rc::new(Cell::new(0));
+
+        repeated_assert::with_catch_async(
+            5,
+            Duration::from_millis(STEP_MS),
+            2,
+            || {
+                let x = x.clone();
+                async move { x.set(1) }
+            },
+            || async { assert_eq!(x.get(), 1) },
+        )
+        .await;
+    }
 }
@@ -1,109 +1,736 @@
-#![allow(deprecated)]
-
-/// An assertion macro that tries to assert expressions multiple times
+/// Run a sequence of assertions repeatedly until they all pass, see [`that`](crate::that).
+///
+/// The first line sets the number of tries and the delay between them, optionally followed by the number of failed tries
+/// after which the catch block is executed once (see [`with_catch`](crate::with_catch)).
+/// It is followed by statements, each terminated by `;`:
+///
+/// * `if condition;` - assert that `condition` is true
+/// * `if let pattern = expression;` - assert that `expression` matches `pattern`, binding its variables for the following statements
+/// * `eq left, right;` - assert that `left` equals `right`
+/// * `ne left, right;` - assert that `left` doesn't equal `right`
+/// * `gt left, right;`, `lt left, right;`, `ge left, right;`, `le left, right;` - compare `left` with `right`
+/// * `matches value, pattern;` - assert that `value` matches `pattern` (with an optional `if` guard), printing the value otherwise
+/// * `approx left, right, epsilon;` - assert that the numbers `left` and `right` differ by at most `epsilon`
+/// * `contains haystack, needle;` - assert that the string `haystack` contains the substring `needle`,
+///   or that the collection `haystack` (anything iterable by reference) contains the item `needle`
+/// * `matches_re text, r"regex";` - assert that `text` matches the regular expression, which has to be a string literal
+///   (requires the `regex` feature)
+/// * `let pattern = expression;` - bind a variable for the following statements
+/// * `ok expression;`, `err expression;`, `some expression;`, `none expression;` - assert that `expression` returns the variant
+/// * `let pattern = ok expression;` (or `err`, `some`) - assert that `expression` returns the variant, binding its value for the following statements
+/// * `{ ... };` - execute a block, e.g. to log the current state
+///
+/// Failed comparisons print both values, like `assert_eq!`.
 ///
-/// ## Examples
+/// Assertions can be followed by a custom message with format arguments, like `assert!`, e.g.
+/// `if queue.is_empty(), "queue never drained: {:?}", queue;`. It replaces the stringified expression of comparisons
+/// and precedes the default message of the other assertions.
+///
+/// # Examples
 ///
 /// Wait for a file to appear, calculate the checksum and then assert the checksum is to equal to `1234` (re-try up to 10 times, wait 50 ms between tries)
 ///
-/// ```rust,ignore
+/// ```rust,no_run
+/// # use repeated_assert::repeated_assert;
+/// # use std::{path::Path, time::Duration};
+/// # fn crc(_path: &str) -> u32 { 1234 }
 /// repeated_assert!{ 10, Duration::from_millis(50);
 ///     if Path::new("should_appear_soon.txt").exists();
 ///     let checksum = crc("should_appear_soon.txt");
 ///     eq checksum, 1234;
 /// };
 /// ```
-#[deprecated(
-    since = "0.2.0",
-    note = "Use `repeated_assert::that` or `repeated_assert::with_catch` instead."
-)]
+///
+/// Wait for a queue to drain, printing its contents if it doesn't
+///
+/// ```rust
+/// # use repeated_assert::repeated_assert;
+/// # use std::time::Duration;
+/// # let queue: Vec<u32> = Vec::new();
+/// repeated_assert!{ 10, Duration::from_millis(50);
+///     eq queue.len(), 0, "queue never drained: {:?}", queue;
+/// };
+/// ```
+///
+/// Wait for a message, poking the unreliable service after 5 failed tries
+///
+/// ```rust
+/// # use repeated_assert::repeated_assert;
+/// # use std::{sync::mpsc, time::Duration};
+/// # struct Message { body: String }
+/// # struct Service;
+/// # impl Service {
+/// #     fn poke(&self) {}
+/// # }
+/// # struct Inbox(mpsc::Receiver<Message>);
+/// # impl Inbox {
+/// #     fn try_recv(&self) -> Option<Message> { self.0.try_recv().ok() }
+/// # }
+/// # let (tx, rx) = mpsc::channel();
+/// # tx.send(Message { body: "hello".to_string() }).unwrap();
+/// # let (service, inbox) = (Service, Inbox(rx));
+/// repeated_assert!{ 10, Duration::from_millis(50), 5, { service.poke(); };
+///     if let Some(message) = inbox.try_recv();
+///     ne message.body, "";
+/// };
+/// ```
 #[macro_export]
 macro_rules! repeated_assert {
     ($repetitions:expr, $delay:expr; $($tt:tt)*) => {
-        for i in 0..$repetitions {
-            if i == $repetitions - 1 {
-                __repeated_assert!{ @final, $($tt)* }
-            } else {
-                if __repeated_assert!{ $($tt)* } {
-                    break;
-                }
-                ::std::thread::sleep($delay);
-            }
-        }
+        $crate::that($repetitions, $delay, || {
+            $crate::__repeated_assert!{ $($tt)* }
+        })
     };
     ($repetitions:expr, $delay:expr, $repetitions_catch:expr, $catch:block; $($tt:tt)*) => {
-        for i in 0..$repetitions {
-            if i == $repetitions - 1 {
-                __repeated_assert!{ @final, $($tt)* }
-            } else if i == $repetitions_catch {
-                let thread_name = ::std::thread::current().name().unwrap_or("unnamed thread").to_owned();
-                println!("{}: executing repeated-assert catch block", thread_name);
-                $catch
-                ::std::thread::sleep($delay);
-            } else {
-                if __repeated_assert!{ $($tt)* } {
-                    break;
-                }
-                ::std::thread::sleep($delay);
-            }
-        }
+        $crate::with_catch($repetitions, $delay, $repetitions_catch, || $catch, || {
+            $crate::__repeated_assert!{ $($tt)* }
+        })
     };
 }
 
 #[doc(hidden)]
 #[macro_export]
 macro_rules! __repeated_assert {
-    (@final, if $expr:expr;) => {
-        assert!($expr);
+    () => {};
+    (if let $($pat:pat)|+ = $expr:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        // a `let` statement drops the temporaries (e.g. lock guards) before the next assertion
+        let ($($pat)|+) = $expr else {
+            $crate::__repeated_assert_fail!(
+                ::std::format!(
+                    "`{}` doesn't match `{}`",
+                    stringify!($expr),
+                    stringify!($($pat)|+)
+                )
+                $(, $fmt $(, $arg)*)?
+            );
+        };
+        $crate::__repeated_assert!{ $($tt)* }
     };
-    (@final, eq $left:expr, $right:expr;) => {
-        assert_eq!($left, $right, stringify!($left != $right));
+    (if $expr:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        // format the message first, which drops the temporaries of the arguments (e.g. lock guards) before panicking
+        assert!($expr $(, "{}", ::std::format!($fmt $(, $arg)*))?);
+        $crate::__repeated_assert!{ $($tt)* }
     };
-    (@final, if $expr:expr; $($tt:tt)+) => {
-        assert!($expr);
-        __repeated_assert!{ @final, $($tt)+ }
+    (eq $left:expr, $right:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        $crate::__repeated_assert_compare!($left, ==, $right, stringify!($left != $right) $(, $fmt $(, $arg)*)?);
+        $crate::__repeated_assert!{ $($tt)* }
     };
-    (@final, eq $left:expr, $right:expr; $($tt:tt)+) => {
-        assert_eq!($left, $right, stringify!($left != $right));
-        __repeated_assert!{ @final, $($tt)+ }
+    (ne $left:expr, $right:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        $crate::__repeated_assert_compare!($left, !=, $right, stringify!($left == $right) $(, $fmt $(, $arg)*)?);
+        $crate::__repeated_assert!{ $($tt)* }
     };
-    (@final, let $($pat:pat)|+ = $expr:expr; $($tt:tt)+) => {
-        match $expr {
-            $($pat)|+ => { __repeated_assert!{ @final, $($tt)+ } }
-        }
+    (gt $left:expr, $right:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        $crate::__repeated_assert_compare!($left, >, $right, stringify!($left <= $right) $(, $fmt $(, $arg)*)?);
+        $crate::__repeated_assert!{ $($tt)* }
+    };
+    (lt $left:expr, $right:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        $crate::__repeated_assert_compare!($left, <, $right, stringify!($left >= $right) $(, $fmt $(, $arg)*)?);
+        $crate::__repeated_assert!{ $($tt)* }
     };
-    (if $expr:expr;) => {
-        $expr
+    (ge $left:expr, $right:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        $crate::__repeated_assert_compare!($left, >=, $right, stringify!($left < $right) $(, $fmt $(, $arg)*)?);
+        $crate::__repeated_assert!{ $($tt)* }
     };
-    (eq $left:expr, $right:expr;) => {
-        $left == $right
+    (le $left:expr, $right:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        $crate::__repeated_assert_compare!($left, <=, $right, stringify!($left > $right) $(, $fmt $(, $arg)*)?);
+        $crate::__repeated_assert!{ $($tt)* }
     };
-    (if $expr:expr; $($tt:tt)+) => {
-        if $expr {
-            __repeated_assert!{ $($tt)+ }
-        } else {
-            false
+    (approx $left:expr, $right:expr, $epsilon:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        // check in a `let` statement, which drops the temporaries (e.g. lock guards) before panicking
+        let failure = $crate::__private::approx(
+            ::std::convert::Into::<f64>::into($left),
+            ::std::convert::Into::<f64>::into($right),
+            ::std::convert::Into::<f64>::into($epsilon),
+            concat!(
+                "|",
+                stringify!($left),
+                " - ",
+                stringify!($right),
+                "| > ",
+                stringify!($epsilon)
+            ),
+        );
+        if let ::std::result::Result::Err(message) = failure {
+            $crate::__repeated_assert_fail!(message $(, $fmt $(, $arg)*)?);
         }
+        $crate::__repeated_assert!{ $($tt)* }
     };
-    (eq $left:expr, $right:expr; $($tt:tt)+) => {
-        if $left == $right {
-            __repeated_assert!{ $($tt)+ }
-        } else {
-            false
+    (contains $haystack:expr, $needle:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        // check in a `let` statement, which drops the temporaries (e.g. lock guards) before panicking
+        let failure = {
+            #[allow(unused_imports)]
+            use $crate::__private::{ContainsItem as _, ContainsStr as _};
+            (&&$crate::__private::Haystack(&$haystack))
+                .check(&$needle, (stringify!($haystack), stringify!($needle)))
+        };
+        if let ::std::result::Result::Err(message) = failure {
+            $crate::__repeated_assert_fail!(message $(, $fmt $(, $arg)*)?);
         }
+        $crate::__repeated_assert!{ $($tt)* }
     };
-    (let $($pat:pat)|+ = $expr:expr; $($tt:tt)+) => {
-        match $expr {
-            $($pat)|+ => { __repeated_assert!{ $($tt)+ } }
+    (matches_re $text:expr, $regex:literal $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        // check in a `let` statement, which drops the temporaries (e.g. lock guards) before panicking
+        let failure = {
+            // compile the regular expression only once per call site
+            static REGEX: ::std::sync::OnceLock<$crate::__private::Regex> =
+                ::std::sync::OnceLock::new();
+            let regex = REGEX.get_or_init(|| {
+                $crate::__private::Regex::new($regex).expect("invalid regular expression")
+            });
+            $crate::__private::matches_re(
+                ::std::convert::AsRef::<str>::as_ref(&$text),
+                regex,
+                concat!("`", stringify!($text), "`"),
+            )
+        };
+        if let ::std::result::Result::Err(message) = failure {
+            $crate::__repeated_assert_fail!(message $(, $fmt $(, $arg)*)?);
         }
+        $crate::__repeated_assert!{ $($tt)* }
+    };
+    (matches $value:expr, $($tt:tt)*) => {
+        $crate::__repeated_assert!{ @matches [$value] [] $($tt)* }
+    };
+    // collect the tokens of the pattern up to the `;` or the message, since patterns can't be followed by `;` in macros
+    (@matches [$value:expr] [$($pattern:tt)*] ; $($tt:tt)*) => {
+        $crate::__repeated_assert!{ @matched [$value] [$($pattern)*] [] $($tt)* }
+    };
+    (@matches [$value:expr] [$($pattern:tt)*] , $fmt:literal $(, $arg:expr)*; $($tt:tt)*) => {
+        $crate::__repeated_assert!{ @matched [$value] [$($pattern)*] [, $fmt $(, $arg)*] $($tt)* }
+    };
+    (@matches [$value:expr] [$($pattern:tt)*] $next:tt $($tt:tt)*) => {
+        $crate::__repeated_assert!{ @matches [$value] [$($pattern)* $next] $($tt)* }
+    };
+    (@matched [$value:expr] [$($pattern:tt)*] [$($message:tt)*] $($tt:tt)*) => {
+        // format the message in a `let` statement, which drops the temporaries (e.g. lock guards) before panicking
+        let failure = match $value {
+            $($pattern)* => ::std::option::Option::None,
+            #[allow(unreachable_patterns)]
+            ref value => ::std::option::Option::Some(::std::format!(
+                "`{}` doesn't match `{}`\n value: {:?}",
+                stringify!($value),
+                stringify!($($pattern)*),
+                value
+            )),
+        };
+        if let ::std::option::Option::Some(message) = failure {
+            $crate::__repeated_assert_fail!(message $($message)*);
+        }
+        $crate::__repeated_assert!{ $($tt)* }
+    };
+    (let $($pat:pat)|+ = ok $expr:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        $crate::__repeated_assert!{ @unwrap [$($pat)|+] [ok] [$expr] [$(, $fmt $(, $arg)*)?] $($tt)* }
+    };
+    (let $($pat:pat)|+ = err $expr:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        $crate::__repeated_assert!{ @unwrap [$($pat)|+] [err] [$expr] [$(, $fmt $(, $arg)*)?] $($tt)* }
+    };
+    (let $($pat:pat)|+ = some $expr:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        $crate::__repeated_assert!{ @unwrap [$($pat)|+] [some] [$expr] [$(, $fmt $(, $arg)*)?] $($tt)* }
+    };
+    (ok $expr:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        $crate::__repeated_assert!{ @unwrap [_] [ok] [$expr] [$(, $fmt $(, $arg)*)?] $($tt)* }
+    };
+    (err $expr:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        $crate::__repeated_assert!{ @unwrap [_] [err] [$expr] [$(, $fmt $(, $arg)*)?] $($tt)* }
+    };
+    (some $expr:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        $crate::__repeated_assert!{ @unwrap [_] [some] [$expr] [$(, $fmt $(, $arg)*)?] $($tt)* }
+    };
+    (none $expr:expr $(, $fmt:literal $(, $arg:expr)*)?; $($tt:tt)*) => {
+        $crate::__repeated_assert!{ @unwrap [()] [none] [$expr] [$(, $fmt $(, $arg)*)?] $($tt)* }
+    };
+    (@unwrap [$($pat:pat)|+] [$variant:ident] [$expr:expr] [$($message:tt)*] $($tt:tt)*) => {
+        // unwrap in a separate `let` statement, which drops the temporaries (e.g. lock guards) before panicking
+        let value = $crate::__private::$variant($expr, stringify!($expr));
+        #[allow(unused_parens)]
+        let ($($pat)|+) = match value {
+            ::std::result::Result::Ok(value) => value,
+            ::std::result::Result::Err(message) => $crate::__repeated_assert_fail!(message $($message)*),
+        };
+        $crate::__repeated_assert!{ $($tt)* }
+    };
+    (let $($pat:pat)|+ = $expr:expr; $($tt:tt)*) => {
+        #[allow(unused_parens)]
+        let ($($pat)|+) = $expr;
+        $crate::__repeated_assert!{ $($tt)* }
+    };
+    ($block:block; $($tt:tt)*) => {
+        $block
+        $crate::__repeated_assert!{ $($tt)* }
+    };
+}
+
+/// Compare two values like `assert_eq!`, printing both if the comparison fails.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __repeated_assert_compare {
+    ($left:expr, $op:tt, $right:expr, $message:expr $(, $fmt:literal $(, $arg:expr)*)?) => {
+        // format the values in a `let` statement, which drops the temporaries (e.g. lock guards) before panicking
+        let failure = match (&$left, &$right) {
+            (left, right) if !(*left $op *right) => ::std::option::Option::Some((
+                ::std::format!("{:?}", left),
+                ::std::format!("{:?}", right),
+            )),
+            _ => ::std::option::Option::None,
+        };
+        if let ::std::option::Option::Some((left, right)) = failure {
+            panic!(
+                "assertion `left {} right` failed: {}\n  left: {}\n right: {}",
+                stringify!($op),
+                $crate::__repeated_assert_message!($message $(, $fmt $(, $arg)*)?),
+                left,
+                right
+            );
+        }
+    };
+}
+
+/// Panic with the failure `message`, preceded by the custom message of the statement (if any).
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __repeated_assert_fail {
+    ($message:expr) => {
+        panic!("{}", $message)
+    };
+    ($message:expr, $fmt:literal $(, $arg:expr)*) => {
+        // format the message first, which drops the temporaries of the arguments (e.g. lock guards) before panicking
+        panic!("{}\n{}", ::std::format!($fmt $(, $arg)*), $message)
+    };
+}
+
+/// Use the custom message of the statement (if any) instead of the default `message`.
+///
+/// The custom message is formatted first, which drops the temporaries of the arguments (e.g. lock guards) before panicking.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __repeated_assert_message {
+    ($message:expr) => {
+        $message
+    };
+    ($message:expr, $fmt:literal $(, $arg:expr)*) => {
+        ::std::format!($fmt $(, $arg)*)
+    };
+}
+
+/// Expand the retry loop of an async attribute test, which needs `Retry::run_async` of the `async` feature.
+#[cfg(feature = "async")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __repeated_assert_async {
+    ($($tt:tt)*) => {
+        $($tt)*
+    };
+}
+
+/// Expand the retry loop of an async attribute test, which needs `Retry::run_async` of the `async` feature.
+#[cfg(not(feature = "async"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __repeated_assert_async {
+    ($($tt:tt)*) => {
+        ::std::compile_error!(
+            "async tests require the `async` feature of repeated-assert (or a runtime feature like `tokio`)"
+        )
+    };
+}
+
+/// Run the block repeatedly until it passes, with the retry strategy given as human-readable durations.
+///
+/// Options (all optional, written as `key: value` or `key = value`):
+///
+/// * `within` - maximum time to re-try, e.g. `"5s"` or `5s`
+/// * `poll` - delay between tries, e.g. `"100ms"`
+/// * `tries` - maximum number of tries
+/// * `backoff` - growth of the delay between tries, either `exponential(initial, factor)` (optionally limited with
+///   `exponential(initial, factor, max delay)`) or `fixed(delay)`, e.g. `exponential(50ms, 2.0, max 1s)`
+/// * `jitter` - randomize the delay between tries, either `full` or `equal` (see [`Jitter`](crate::schedule::Jitter))
+/// * `spin` - number of tries without sleeping before the delays start (see [`Retry::spin_first`](crate::Retry::spin_first))
+///
+/// Durations are numbers with one of the units `ns`, `us`, `ms`, `s`, `m` or `h`, including fractions (`1.5s`) and
+/// combinations (`"1m 30s"`). They're parsed at compile time, so invalid durations don't compile.
+/// Options that aren't given are taken from the [default policy](crate::default_policy).
+///
+/// # Examples
+///
+/// ```rust
+/// # use repeated_assert::eventually;
+/// # struct Service;
+/// # impl Service {
+/// #     fn is_healthy(&self) -> bool { true }
+/// # }
+/// # let (queue, service) = (vec![1, 2, 3], Service);
+/// eventually!(within: "5s", poll: "100ms", {
+///     assert_eq!(queue.len(), 3);
+/// });
+///
+/// eventually!(within: "10s", backoff: exponential(50ms, 2.0, max 1s), jitter: full, {
+///     assert!(service.is_healthy());
+/// });
+/// ```
+///
+/// # Info
+///
+/// See [`that`](crate::that).
+#[macro_export]
+macro_rules! eventually {
+    ($($key:ident $sep:tt $value:tt $(($($args:tt)*))?,)* $body:block $(,)?) => {
+        $crate::__retry!($crate::Retry::new(); $($key $sep $value $(($($args)*))?),*).run(|| $body)
     };
 }
 
+/// Assert that an expression eventually equals the expected value, evaluating the expression on every try.
+///
+/// If it never does, both sides are printed like with `assert_eq!` (or as a colored diff with the `diff` feature),
+/// followed by the values the expression had on the tries.
+/// Returns the value of the expression. The expected value is evaluated only once.
+///
+/// Takes the same options as [`eventually!`], after the expected value.
+///
+/// # Examples
+///
+/// ```rust
+/// # use repeated_assert::assert_eventually_eq;
+/// # #[derive(Debug, PartialEq)]
+/// # enum Status { Ready }
+/// # fn read_status() -> Status { Status::Ready }
+/// # let queue = vec![1, 2, 3];
+/// assert_eventually_eq!(queue.len(), 3, within = 5s);
+///
+/// assert_eventually_eq!(read_status(), Status::Ready, within = 10s, poll = 500ms);
+/// ```
+#[macro_export]
+macro_rules! assert_eventually_eq {
+    ($actual:expr, $expected:expr $(, $key:ident $sep:tt $value:tt $(($($args:tt)*))?)* $(,)?) => {{
+        let expected = &$expected;
+        let mut history = $crate::__private::History::new();
+        $crate::__retry!($crate::Retry::new(); $($key $sep $value $(($($args)*))?),*).run(|| {
+            let actual = $actual;
+            history.record(&actual);
+            if actual != *expected {
+                panic!(
+                    "{}",
+                    history.describe_failure("==", &actual, expected, stringify!($actual))
+                );
+            }
+            actual
+        })
+    }};
+}
+
+/// Assert that a number eventually differs from the expected value by at most `epsilon`, evaluating it on every try.
+///
+/// Meant for values converging towards the expected one, e.g. moving averages of metrics.
+/// If it never gets close enough, both sides and their final delta are printed, followed by the values the expression had on the tries.
+/// Returns the value of the expression. Works with all types convertible into `f64`.
+///
+/// Takes the same options as [`eventually!`], after `epsilon`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use repeated_assert::assert_eventually_approx_eq;
+/// # fn average_latency_ms() -> f64 { 20.2 }
+/// assert_eventually_approx_eq!(average_latency_ms(), 20.0, 0.5, within = 10s);
+/// ```
+#[macro_export]
+macro_rules! assert_eventually_approx_eq {
+    ($actual:expr, $expected:expr, $epsilon:expr $(, $key:ident $sep:tt $value:tt $(($($args:tt)*))?)* $(,)?) => {{
+        let expected = ::std::convert::Into::<f64>::into($expected);
+        let epsilon = ::std::convert::Into::<f64>::into($epsilon);
+        let mut history = $crate::__private::History::new();
+        $crate::__retry!($crate::Retry::new(); $($key $sep $value $(($($args)*))?),*).run(|| {
+            let actual = $actual;
+            history.record(&actual);
+            let failure = $crate::__private::approx(
+                ::std::convert::Into::<f64>::into(actual),
+                expected,
+                epsilon,
+                "",
+            );
+            if let ::std::result::Result::Err(message) = failure {
+                panic!("{}\n{}", message, history.describe_values(stringify!($actual)));
+            }
+            actual
+        })
+    }};
+}
+
+/// Assert that a condition stays false for a whole time window, checking it on every try.
+///
+/// Panics as soon as the condition becomes true.
+///
+/// Options (written as `key = value` or `key: value`):
+///
+/// * `for` - length of the time window, e.g. `2s`
+/// * `poll` - delay between checks, e.g. `100ms`
+/// * `backoff`, `jitter` - as for [`eventually!`]
+///
+/// Without `for`, the condition is checked on every try of the [default policy](crate::default_policy).
+///
+/// # Examples
+///
+/// ```rust
+/// # use repeated_assert::assert_never;
+/// # fn received_messages() -> Vec<String> { vec!["hello".to_string()] }
+/// assert_never!(received_messages().len() > 1, for = 2s, poll = 100ms);
+/// ```
+///
+/// # Info
+///
+/// See [`never`](crate::never).
+#[macro_export]
+macro_rules! assert_never {
+    ($condition:expr $(, $key:ident $sep:tt $value:tt $(($($args:tt)*))?)* $(,)?) => {
+        $crate::__retry!($crate::Retry::new(); $($key $sep $value $(($($args)*))?),*).run_consistently(|| {
+            if $condition {
+                panic!("condition became true: {}", stringify!($condition));
+            }
+        })
+    };
+}
+
+/// Assert that an expression eventually equals the expected value `at`, and then keeps it for the time window `hold`.
+///
+/// The expression is evaluated every `poll` delay in both phases. On failure, both sides are printed like with `assert_eq!`,
+/// followed by the values the expression had on the tries. Returns the value of the expression.
+///
+/// Takes the same options as [`eventually!`] after `hold`, which apply to reaching the value.
+///
+/// # Examples
+///
+/// ```rust
+/// # use repeated_assert::assert_stabilizes;
+/// # fn counter() -> u32 { 5 }
+/// # fn replicas() -> u32 { 3 }
+/// assert_stabilizes!(counter(), at = 5, hold = 1s);
+///
+/// assert_stabilizes!(replicas(), at = 3, hold = 2s, within = 30s, poll = 500ms);
+/// ```
+///
+/// # Info
+///
+/// See [`eventually_then_always`](crate::eventually_then_always).
+#[macro_export]
+macro_rules! assert_stabilizes {
+    ($actual:expr, at = $expected:expr, hold = $hold:tt $(, $key:ident $sep:tt $value:tt $(($($args:tt)*))?)* $(,)?) => {{
+        let expected = &$expected;
+        let mut history = $crate::__private::History::new();
+        $crate::__retry!($crate::Retry::new(); $($key $sep $value $(($($args)*))?),*).run_then_always(
+            $crate::__duration!($hold),
+            || {
+                let actual = $actual;
+                history.record(&actual);
+                if actual != *expected {
+                    panic!(
+                        "{}",
+                        history.describe_failure("==", &actual, expected, stringify!($actual))
+                    );
+                }
+                actual
+            },
+        )
+    }};
+}
+
+/// Assert that an expression eventually matches an `insta` snapshot, evaluating the expression on every try
+/// with the retry strategy of the [`Policy`](crate::Policy).
+///
+/// The snapshot is either named (`assert_eventually_snapshot!("name", value, policy)`) or inline
+/// (`assert_eventually_snapshot!(value, @"snapshot", policy)`). Snapshots named automatically by `insta` aren't supported,
+/// since every try would be compared with the next numbered snapshot.
+///
+/// The tries are compared like with `insta::assert_snapshot!`, so the pending snapshot of the last try is kept for
+/// `cargo insta review` if the value never matches. Pending snapshots of failed tries are removed if a later try matches.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use repeated_assert::{assert_eventually_snapshot, Policy};
+/// # struct Cluster;
+/// # impl Cluster {
+/// #     fn status(&self) -> String { "3 nodes ready".to_string() }
+/// # }
+/// # let cluster = Cluster;
+/// assert_eventually_snapshot!("cluster_status", cluster.status(), Policy::default());
+///
+/// assert_eventually_snapshot!(cluster.status(), @"3 nodes ready", Policy::default());
+/// ```
+#[cfg(feature = "insta")]
+#[cfg_attr(docsrs, doc(cfg(feature = "insta")))]
+#[macro_export]
+macro_rules! assert_eventually_snapshot {
+    ($value:expr, @$snapshot:literal, $policy:expr $(,)?) => {
+        $crate::Retry::with_policy($policy).run(|| {
+            $crate::__private::insta::assert_snapshot!($value, @$snapshot);
+        })
+    };
+    ($name:expr, $value:expr, $policy:expr $(,)?) => {
+        $crate::Retry::with_policy($policy).run(|| {
+            $crate::__private::insta::assert_snapshot!($name, $value);
+        })
+    };
+}
+
+/// Call `actual` until the returned value matches the `googletest` matcher, with the retry strategy of the
+/// [`Policy`](crate::Policy).
+///
+/// Returns a `googletest::Result`, which contains the explanation of the matcher for the last value if it never matched.
+/// The matcher expression is evaluated on every try. Like with `googletest::verify_that!`, the value has to be `Copy`
+/// (e.g. a number or a reference).
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// #[gtest]
+/// fn queue_drains() -> googletest::Result<()> {
+///     verify_eventually_that!(|| queue.len(), eq(0), Policy::default())?;
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "googletest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "googletest")))]
+#[macro_export]
+macro_rules! verify_eventually_that {
+    ($actual:expr, $matcher:expr, $policy:expr $(,)?) => {{
+        use $crate::__private::googletest::GoogleTestSupport as _;
+        // `actual` may be `FnMut`
+        #[allow(unused_mut)]
+        let mut actual = $actual;
+        $crate::Retry::with_policy($policy)
+            .run_result(|| $crate::__private::googletest::verify_that!(actual(), $matcher))
+            .failure_message(concat!(
+                "`",
+                stringify!($actual),
+                "` never returned a matching value"
+            ))
+    }};
+}
+
+/// Like [`verify_eventually_that!`], but records the failure without aborting the test, like `googletest::expect_that!`.
+///
+/// Has to be used in a test annotated with `#[gtest]`.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// #[gtest]
+/// fn cluster_starts() {
+///     expect_eventually_that!(|| cluster.ready_nodes(), ge(3), Policy::default());
+///     expect_eventually_that!(|| cluster.leader(), some(anything()), Policy::default());
+/// }
+/// ```
+#[cfg(feature = "googletest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "googletest")))]
+#[macro_export]
+macro_rules! expect_eventually_that {
+    ($actual:expr, $matcher:expr, $policy:expr $(,)?) => {{
+        use $crate::__private::googletest::GoogleTestSupport as _;
+        $crate::verify_eventually_that!($actual, $matcher, $policy).and_log_failure();
+    }};
+}
+
+/// Apply the options of the declarative macros to a `Retry`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __retry {
+    ($retry:expr; $(,)?) => {
+        $retry
+    };
+    ($retry:expr; $key:ident : $value:ident ($($args:tt)*) $(, $($rest:tt)*)?) => {
+        $crate::__retry!($crate::__retry_option!($retry, $key, $value($($args)*)); $($($rest)*)?)
+    };
+    ($retry:expr; $key:ident = $value:ident ($($args:tt)*) $(, $($rest:tt)*)?) => {
+        $crate::__retry!($crate::__retry_option!($retry, $key, $value($($args)*)); $($($rest)*)?)
+    };
+    ($retry:expr; $key:ident : $value:tt $(, $($rest:tt)*)?) => {
+        $crate::__retry!($crate::__retry_option!($retry, $key, $value); $($($rest)*)?)
+    };
+    ($retry:expr; $key:ident = $value:tt $(, $($rest:tt)*)?) => {
+        $crate::__retry!($crate::__retry_option!($retry, $key, $value); $($($rest)*)?)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __retry_option {
+    ($retry:expr, within, $value:tt) => {
+        $retry.timeout($crate::__duration!($value))
+    };
+    ($retry:expr, for, $value:tt) => {
+        $retry.timeout($crate::__duration!($value))
+    };
+    ($retry:expr, poll, $value:tt) => {
+        $retry.delay($crate::__duration!($value))
+    };
+    ($retry:expr, tries, $value:tt) => {
+        $retry.repetitions($value)
+    };
+    ($retry:expr, backoff, $kind:ident ($($args:tt)*)) => {
+        $crate::__retry_backoff!($retry, $kind, $($args)*)
+    };
+    ($retry:expr, spin, $value:tt) => {
+        $retry.spin_first($value)
+    };
+    ($retry:expr, jitter, full) => {
+        $retry.jitter($crate::schedule::Jitter::Full)
+    };
+    ($retry:expr, jitter, equal) => {
+        $retry.jitter($crate::schedule::Jitter::Equal)
+    };
+    ($retry:expr, jitter, $($value:tt)*) => {
+        compile_error!(concat!(
+            "unknown jitter `",
+            stringify!($($value)*),
+            "`, expected `full` or `equal`"
+        ))
+    };
+    ($retry:expr, $key:ident, $($value:tt)*) => {
+        compile_error!(concat!(
+            "unknown option `",
+            stringify!($key),
+            "`, expected one of `within`, `for`, `poll`, `tries`, `backoff`, `jitter` or `spin`"
+        ))
+    };
+}
+
+/// Apply the `backoff` option of the declarative macros to a `Retry`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __retry_backoff {
+    ($retry:expr, exponential, $initial:tt, $factor:expr $(, max $max:tt)?) => {
+        $retry
+            .delay($crate::__duration!($initial))
+            .backoff($factor)
+            $(.max_delay($crate::__duration!($max)))?
+    };
+    ($retry:expr, fixed, $delay:tt) => {
+        $retry.delay($crate::__duration!($delay)).backoff(1.0)
+    };
+    ($retry:expr, $kind:ident, $($args:tt)*) => {
+        compile_error!(concat!(
+            "invalid backoff `",
+            stringify!($kind($($args)*)),
+            "`, expected `exponential(initial, factor)`, `exponential(initial, factor, max delay)` or `fixed(delay)`"
+        ))
+    };
+}
+
+/// Parse a duration like `5s` or `"100ms"` at compile time.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __duration {
+    ($value:tt) => {{
+        const DURATION: ::std::time::Duration = $crate::__private::duration(stringify!($value));
+        DURATION
+    }};
+}
+
 #[cfg(test)]
 mod tests {
+    use std::panic;
     use std::sync::{Arc, Mutex};
     use std::thread;
-    use std::time::Duration;
+    use std::time::{Duration, Instant};
 
     static STEP_MS: u64 = 100;
 
@@ -208,6 +835,566 @@ mod tests {
         };
     }
 
+    #[test]
+    fn if_let_success() {
+        let x = Arc::new(Mutex::new(0));
+        let mut checks = 0;
+
+        spawn_thread(x.clone());
+
+        repeated_assert! { 5, Duration::from_millis(5 * STEP_MS);
+            {
+                checks += 1;
+            };
+            if let 1..=10 | 20 = *x.lock().unwrap();
+            let y = *x.lock().unwrap();
+            ne y, 0;
+        };
+
+        assert!(checks > 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "`*x.lock().unwrap()` doesn't match `1..=10`")]
+    fn if_let_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert! { 3, Duration::from_millis(STEP_MS);
+            if let 1..=10 = *x.lock().unwrap();
+        };
+    }
+
+    #[test]
+    fn compare_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert! { 5, Duration::from_millis(5 * STEP_MS);
+            gt *x.lock().unwrap(), 0;
+            ge *x.lock().unwrap(), 1;
+            lt *x.lock().unwrap(), 10;
+            le *x.lock().unwrap(), 9;
+        };
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "assertion `left > right` failed: *x.lock().unwrap() <= 0\n  left: 0\n right: 0"
+    )]
+    fn compare_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert! { 3, Duration::from_millis(STEP_MS);
+            gt *x.lock().unwrap(), 0;
+        };
+    }
+
+    #[test]
+    fn message_success() {
+        let x = Arc::new(Mutex::new(0));
+        let queue = vec![1, 2];
+
+        spawn_thread(x.clone());
+
+        repeated_assert! { 5, Duration::from_millis(5 * STEP_MS);
+            if *x.lock().unwrap() > 0, "x is still {}", *x.lock().unwrap();
+            if let 1..=10 = *x.lock().unwrap(), "x out of range";
+            eq queue.len(), 2, "queue: {:?}", queue;
+            matches queue.first(), Some(1 | 2), "first: {queue:?}";
+            let first = some queue.first(), "queue is empty";
+            contains queue, *first, "queue: {:?}", queue;
+            approx *x.lock().unwrap(), 1.0, 10.0, "x = {}", *x.lock().unwrap();
+        };
+    }
+
+    #[test]
+    #[should_panic(expected = "x is still 0")]
+    fn message_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert! { 3, Duration::from_millis(STEP_MS);
+            if *x.lock().unwrap() > 0, "x is still {}", *x.lock().unwrap();
+        };
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "assertion `left == right` failed: queue never drained: [1, 2]\n  left: 2\n right: 0"
+    )]
+    fn compare_message_failure() {
+        let queue = Mutex::new(vec![1, 2]);
+
+        repeated_assert! { 3, Duration::from_millis(10);
+            eq queue.lock().unwrap().len(), 0, "queue never drained: {:?}", queue.lock().unwrap();
+        };
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "state of x = 0\n`state` doesn't match `State::Running(_)`\n value: Starting"
+    )]
+    fn matches_message_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert! { 3, Duration::from_millis(STEP_MS);
+            let state = match *x.lock().unwrap() {
+                0 => State::Starting,
+                x => State::Running(x),
+            };
+            matches state, State::Running(_), "state of x = {}", *x.lock().unwrap();
+        };
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid input\n`\"x\".parse::<u8>()` returned `Err`: ParseIntError")]
+    fn unwrap_message_failure() {
+        repeated_assert! { 3, Duration::from_millis(10);
+            ok "x".parse::<u8>(), "invalid input";
+        };
+    }
+
+    #[derive(Debug)]
+    enum State {
+        Starting,
+        Running(i32),
+    }
+
+    #[test]
+    fn matches_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert! { 5, Duration::from_millis(5 * STEP_MS);
+            let state = match *x.lock().unwrap() {
+                0 => State::Starting,
+                x => State::Running(x),
+            };
+            matches state, State::Running(x) if x > 0;
+        };
+    }
+
+    #[test]
+    #[should_panic(expected = "`state` doesn't match `State::Running(_)`\n value: Starting")]
+    fn matches_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert! { 3, Duration::from_millis(STEP_MS);
+            let state = match *x.lock().unwrap() {
+                0 => State::Starting,
+                x => State::Running(x),
+            };
+            matches state, State::Running(_);
+        };
+    }
+
+    #[test]
+    fn unwrap_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert! { 5, Duration::from_millis(5 * STEP_MS);
+            let value = some x.lock().unwrap().checked_sub(1).filter(|x| *x >= 0);
+            let parsed = ok value.to_string().parse::<u8>();
+            err "-1".parse::<u8>();
+            none parsed.checked_sub(1).filter(|_| false);
+            eq parsed, 0;
+        };
+    }
+
+    #[test]
+    #[should_panic(expected = "`\"x\".parse::<u8>()` returned `Err`: ParseIntError")]
+    fn unwrap_failure() {
+        repeated_assert! { 3, Duration::from_millis(10);
+            ok "x".parse::<u8>();
+        };
+    }
+
+    #[test]
+    fn contains_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert! { 5, Duration::from_millis(5 * STEP_MS);
+            let output = format!("x = {}", x.lock().unwrap());
+            contains output, "x = 1";
+            contains vec![0, *x.lock().unwrap()], 1;
+        };
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "`output` doesn't contain `\"x = 1\"`\n  needle: \"x = 1\"\nhaystack: \"x = 0\""
+    )]
+    fn contains_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert! { 3, Duration::from_millis(STEP_MS);
+            let output = format!("x = {}", x.lock().unwrap());
+            contains output, "x = 1";
+        };
+    }
+
+    #[test]
+    fn approx_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert! { 5, Duration::from_millis(5 * STEP_MS);
+            approx *x.lock().unwrap(), 1.05, 0.1;
+        };
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "assertion `|left - right| <= epsilon` failed: |*x.lock().unwrap() - 1.05| > 0.1\n   left: 0.0\n  right: 1.05\n  delta: 1.05\nepsilon: 0.1"
+    )]
+    fn approx_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert! { 3, Duration::from_millis(STEP_MS);
+            approx *x.lock().unwrap(), 1.05, 0.1;
+        };
+    }
+
+    #[test]
+    fn assert_eventually_approx_eq_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        let value = assert_eventually_approx_eq!(
+            *x.lock().unwrap() as f32 / 2.0,
+            0.5,
+            0.01,
+            within = 2s,
+            poll = 100ms
+        );
+        assert_eq!(value, 0.5);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "  delta: 0.5\nepsilon: 0.01\nvalues of `*x.lock().unwrap() as f32 / 2.0`:\n  tries 1-3: 0.0"
+    )]
+    fn assert_eventually_approx_eq_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        assert_eventually_approx_eq!(
+            *x.lock().unwrap() as f32 / 2.0,
+            0.5,
+            0.01,
+            tries = 3,
+            poll = 100ms
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn matches_re_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert! { 5, Duration::from_millis(5 * STEP_MS);
+            let output = format!("x = {}", x.lock().unwrap());
+            matches_re output, r"x = [1-9]";
+        };
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    #[should_panic(expected = "`output` doesn't match /x = [1-9]/\n  text: \"x = 0\"")]
+    fn matches_re_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        repeated_assert! { 3, Duration::from_millis(STEP_MS);
+            let output = format!("x = {}", x.lock().unwrap());
+            matches_re output, r"x = [1-9]";
+        };
+    }
+
+    #[test]
+    fn durations() {
+        use crate::__private::duration;
+
+        assert_eq!(__duration!(5s), Duration::from_secs(5));
+        assert_eq!(__duration!("100ms"), Duration::from_millis(100));
+        assert_eq!(duration("1.5s"), Duration::from_millis(1500));
+        assert_eq!(duration("1m 30s"), Duration::from_secs(90));
+        assert_eq!(duration("2h"), Duration::from_secs(7200));
+        assert_eq!(duration("250us"), Duration::from_micros(250));
+        assert_eq!(duration("1m30s"), Duration::from_secs(90));
+        assert_eq!(
+            crate::__private::parse_duration(b"1.5.0s"),
+            Err("invalid duration: number with multiple `.`")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid duration: expected a unit")]
+    fn duration_without_unit() {
+        crate::__private::duration("100");
+    }
+
+    #[test]
+    fn eventually_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        let value = eventually!(within: "2s", poll: "100ms", {
+            let x = *x.lock().unwrap();
+            assert!(x > 0);
+            x
+        });
+        assert!(value > 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "assertion failed: *x.lock().unwrap() > 0")]
+    fn eventually_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        eventually!(tries = 3, poll = 100ms, {
+            assert!(*x.lock().unwrap() > 0);
+        });
+    }
+
+    #[test]
+    fn eventually_backoff() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        eventually!(within: 2s, backoff: exponential(50ms, 2.0, max 200ms), jitter: full, {
+            assert!(*x.lock().unwrap() > 0);
+        });
+    }
+
+    #[test]
+    fn eventually_backoff_delays() {
+        let start = Instant::now();
+
+        let result = panic::catch_unwind(|| {
+            eventually!(tries = 4, backoff = exponential(100ms, 2.0, max 200ms), {
+                panic!("not ready");
+            })
+        });
+
+        // 100 ms, then 200 ms twice (instead of 400 ms)
+        assert!(result.is_err());
+        assert!(start.elapsed() >= Duration::from_millis(500));
+        assert!(start.elapsed() < Duration::from_millis(700));
+    }
+
+    #[test]
+    fn eventually_spin() {
+        let start = Instant::now();
+        let mut tries = 0;
+
+        eventually!(tries = 2, poll = 10s, spin = 100, {
+            tries += 1;
+            assert!(tries == 100);
+        });
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn assert_never_fixed_backoff() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        assert_never!(*x.lock().unwrap() > 0, for = 500ms, backoff = fixed(100ms));
+    }
+
+    #[test]
+    fn assert_eventually_eq_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        let value = assert_eventually_eq!(*x.lock().unwrap(), 1, within = 2s, poll = 100ms);
+        assert_eq!(value, 1);
+    }
+
+    #[test]
+    #[cfg_attr(
+        not(feature = "diff"),
+        should_panic(
+            expected = "assertion `left == right` failed\n  left: 0\n right: 1\nvalues of `*x.lock().unwrap()`:\n  tries 1-3: 0"
+        )
+    )]
+    #[cfg_attr(
+        feature = "diff",
+        should_panic(expected = "values of `*x.lock().unwrap()`:\n  tries 1-3: 0")
+    )]
+    fn assert_eventually_eq_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        assert_eventually_eq!(*x.lock().unwrap(), 1, tries = 3, poll = 100ms);
+    }
+
+    #[test]
+    fn assert_never_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        assert_never!(*x.lock().unwrap() > 0, for = 500ms, poll = 100ms);
+    }
+
+    #[test]
+    #[should_panic(expected = "condition became true: *x.lock().unwrap() > 0")]
+    fn assert_never_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        assert_never!(*x.lock().unwrap() > 0, for = 2s, poll = 100ms);
+    }
+
+    #[test]
+    fn assert_stabilizes_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        let value = assert_stabilizes!(
+            *x.lock().unwrap(),
+            at = 1,
+            hold = 500ms,
+            within = 2s,
+            poll = 100ms
+        );
+        assert_eq!(value, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "stopped holding on try")]
+    fn assert_stabilizes_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        assert_stabilizes!(*x.lock().unwrap(), at = 0, hold = 2s, poll = 100ms);
+    }
+
+    #[cfg(feature = "insta")]
+    #[test]
+    fn assert_eventually_snapshot_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        assert_eventually_snapshot!(
+            "eventually_ready",
+            format!("x = {}", x.lock().unwrap().min(1)),
+            crate::Policy {
+                repetitions: Some(5),
+                delay: Duration::from_millis(5 * STEP_MS),
+                ..crate::Policy::default()
+            }
+        );
+    }
+
+    #[cfg(feature = "insta")]
+    #[test]
+    #[should_panic(expected = "snapshot assertion for 'never_ready' failed")]
+    fn assert_eventually_snapshot_failure() {
+        let snapshots = std::env::temp_dir().join("repeated-assert-snapshots");
+        let mut settings = insta::Settings::clone_current();
+        settings.set_snapshot_path(snapshots);
+        let _guard = settings.bind_to_scope();
+
+        assert_eventually_snapshot!(
+            "never_ready",
+            "x = 0",
+            crate::Policy {
+                repetitions: Some(3),
+                delay: Duration::from_millis(10),
+                ..crate::Policy::default()
+            }
+        );
+    }
+
+    #[cfg(feature = "googletest")]
+    #[googletest::test]
+    fn expect_eventually_that_success() {
+        use googletest::prelude::*;
+
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        expect_eventually_that!(
+            || *x.lock().unwrap(),
+            all!(gt(0), lt(10)),
+            crate::Policy {
+                repetitions: Some(5),
+                delay: Duration::from_millis(5 * STEP_MS),
+                ..crate::Policy::default()
+            }
+        );
+    }
+
+    #[cfg(feature = "googletest")]
+    #[test]
+    fn verify_eventually_that_failure() {
+        use googletest::prelude::*;
+
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        let failure = verify_eventually_that!(
+            || *x.lock().unwrap(),
+            gt(0),
+            crate::Policy {
+                repetitions: Some(3),
+                delay: Duration::from_millis(STEP_MS),
+                ..crate::Policy::default()
+            }
+        )
+        .unwrap_err();
+
+        assert!(failure
+            .description
+            .contains("Expected: is greater than 0\nActual: 0"));
+        assert_eq!(
+            failure.custom_message.as_deref(),
+            Some("`|| *x.lock().unwrap()` never returned a matching value")
+        );
+    }
+
     #[test]
     fn catch() {
         let x = Arc::new(Mutex::new(-1_000));
@@ -220,4 +1407,15 @@ mod tests {
             if *x.lock().unwrap() > 0;
         };
     }
+
+    #[test]
+    fn catch_borrowed() {
+        let x = std::cell::Cell::new(0);
+
+        repeated_assert! { 5, Duration::from_millis(STEP_MS), 2, {
+                x.set(1);
+            };
+            eq x.get(), 1;
+        };
+    }
 }
@@ -0,0 +1,321 @@
+//! Waiting for network services
+//!
+//! # Examples
+//!
+//! Waiting for a spawned server to accept connections
+//!
+//! ```rust,no_run
+//! # use repeated_assert::Policy;
+//! # use std::process::Command;
+//! let server = Command::new("my-server").spawn().unwrap();
+//!
+//! repeated_assert::net::wait_for_tcp("127.0.0.1:8080", Policy::default());
+//! ```
+//!
+//! With the `http` feature, waiting for a health check endpoint
+//!
+//! ```rust,no_run
+//! # use repeated_assert::Policy;
+//! # #[cfg(feature = "http")]
+//! repeated_assert::net::wait_for_http_ok("http://127.0.0.1:8080/health", Policy::default());
+//! ```
+use crate::{Policy, Retry};
+use std::{
+    fmt::Display,
+    io,
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+/// Maximum time a single connection attempt may take.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Try to connect to `address` with the retry strategy of `policy`, until the connection is accepted.
+///
+/// Every connection attempt times out after one second. Returns the connection.
+///
+/// # Panics
+///
+/// Panics with the reason of the last failed connection attempt (e.g. connection refused or timed out).
+#[track_caller]
+pub fn wait_for_tcp<A>(address: A, policy: Policy) -> TcpStream
+where
+    A: ToSocketAddrs + Display,
+{
+    Retry::with_policy(policy).run(|| match connect(&address) {
+        Ok(stream) => stream,
+        Err(error) => panic!("failed to connect to {}: {}", address, describe(&error)),
+    })
+}
+
+/// Try to connect to `address` with the retry strategy of `policy`, until the connection is accepted.
+///
+/// # Info
+///
+/// See [`wait_for_tcp`].
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+#[track_caller]
+pub fn wait_for_tcp_async<A>(
+    address: A,
+    policy: Policy,
+) -> impl std::future::Future<Output = tokio::net::TcpStream>
+where
+    A: tokio::net::ToSocketAddrs + Display + Clone,
+{
+    Retry::with_policy(policy).run_async(move || {
+        let address = address.clone();
+        async move {
+            let connect = tokio::net::TcpStream::connect(address.clone());
+            let result = match tokio::time::timeout(CONNECT_TIMEOUT, connect).await {
+                Ok(result) => result,
+                Err(_) => Err(io::ErrorKind::TimedOut.into()),
+            };
+            match result {
+                Ok(stream) => stream,
+                Err(error) => panic!("failed to connect to {}: {}", address, describe(&error)),
+            }
+        }
+    })
+}
+
+/// Send GET requests to `url` with the retry strategy of `policy`, until it responds with a success status (2xx).
+///
+/// Every request times out after one second. Returns the body of the response.
+///
+/// # Panics
+///
+/// Panics with the status and the beginning of the body of the last response, or the reason why the request failed.
+#[cfg(feature = "http")]
+#[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+#[track_caller]
+pub fn wait_for_http_ok(url: &str, policy: Policy) -> String {
+    Retry::with_policy(policy).run(|| {
+        http_get(url, |status| (200..300).contains(&status))
+            .unwrap_or_else(|message| panic!("{}", message))
+    })
+}
+
+/// Send GET requests to `url` with the retry strategy of `policy`, until it responds with `status`.
+///
+/// # Info
+///
+/// See [`wait_for_http_ok`].
+#[cfg(feature = "http")]
+#[cfg_attr(docsrs, doc(cfg(feature = "http")))]
+#[track_caller]
+pub fn wait_for_http_status(url: &str, status: u16, policy: Policy) -> String {
+    Retry::with_policy(policy).run(|| {
+        http_get(url, |actual| actual == status).unwrap_or_else(|message| panic!("{}", message))
+    })
+}
+
+/// Send GET requests to `url` with the retry strategy of `policy`, until it responds with a success status (2xx).
+///
+/// The requests are sent on the blocking thread pool of tokio, failed requests panic on the calling task.
+///
+/// # Info
+///
+/// See [`wait_for_http_ok`].
+#[cfg(all(feature = "http", feature = "tokio"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "http", feature = "tokio"))))]
+#[track_caller]
+pub fn wait_for_http_ok_async<U>(
+    url: U,
+    policy: Policy,
+) -> impl std::future::Future<Output = String>
+where
+    U: Into<String>,
+{
+    let url = url.into();
+    Retry::with_policy(policy).run_async(move || {
+        let url = url.clone();
+        async move {
+            let request_url = url.clone();
+            let request = tokio::task::spawn_blocking(move || {
+                http_get(&request_url, |status| (200..300).contains(&status))
+            });
+            match request.await {
+                Ok(Ok(body)) => body,
+                Ok(Err(message)) => panic!("{}", message),
+                Err(error) => panic!("request to {} failed: {}", url, error),
+            }
+        }
+    })
+}
+
+/// Send a GET request to `url`, returning why it failed if it fails or the status doesn't match `expected`.
+///
+/// It doesn't panic itself, so the async requests on the blocking thread pool don't print a panic message for every try.
+#[cfg(feature = "http")]
+fn http_get<E>(url: &str, expected: E) -> Result<String, String>
+where
+    E: Fn(u16) -> bool,
+{
+    let response = match ureq::get(url).timeout(CONNECT_TIMEOUT).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(_, response)) => response,
+        Err(ureq::Error::Transport(error)) => {
+            return Err(format!("request to {} failed: {}", url, error))
+        }
+    };
+    let status = response.status();
+    let body = response
+        .into_string()
+        .unwrap_or_else(|error| format!("<failed to read body: {}>", error));
+    if !expected(status) {
+        return Err(format!(
+            "unexpected response from {}: status {}, body: {:?}",
+            url,
+            status,
+            snippet(&body)
+        ));
+    }
+    Ok(body)
+}
+
+/// Shorten `body` to its first 200 characters.
+#[cfg(feature = "http")]
+fn snippet(body: &str) -> &str {
+    match body.char_indices().nth(200) {
+        Some((index, _)) => &body[..index],
+        None => body,
+    }
+}
+
+/// Connect to the first address `address` resolves to that accepts the connection.
+fn connect<A: ToSocketAddrs>(address: &A) -> io::Result<TcpStream> {
+    let mut last_error = io::Error::new(io::ErrorKind::InvalidInput, "address resolved to nothing");
+    for address in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(error) => last_error = error,
+        }
+    }
+    Err(last_error)
+}
+
+/// Describe why a connection attempt failed.
+fn describe(error: &io::Error) -> String {
+    match error.kind() {
+        io::ErrorKind::ConnectionRefused => {
+            "connection refused, nothing is listening on the port".to_string()
+        }
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+            format!("connection timed out after {:?}", CONNECT_TIMEOUT)
+        }
+        _ => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::policy;
+    use std::{net::TcpListener, thread};
+
+    /// Get a port nothing is listening on.
+    fn free_port() -> u16 {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    #[test]
+    fn wait_for_tcp_success() {
+        let port = free_port();
+
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+            let _ = listener.accept();
+        });
+
+        wait_for_tcp(format!("127.0.0.1:{}", port), policy());
+    }
+
+    #[test]
+    #[should_panic(expected = "connection refused, nothing is listening on the port")]
+    fn wait_for_tcp_failure() {
+        wait_for_tcp(format!("127.0.0.1:{}", free_port()), policy());
+    }
+
+    /// Serve `responses` to the requests to a local port, returning the URL.
+    #[cfg(feature = "http")]
+    fn serve(responses: Vec<&'static str>) -> String {
+        use std::io::{Read, Write};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/health", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for response in responses.into_iter().cycle() {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response,
+                    response.len(),
+                    response
+                );
+            }
+        });
+        url
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn wait_for_http_ok_success() {
+        let url = serve(vec!["503 Service Unavailable", "200 OK"]);
+
+        assert_eq!(wait_for_http_ok(&url, policy()), "200 OK");
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    #[should_panic(expected = "status 503, body: \"503 Service Unavailable\"")]
+    fn wait_for_http_ok_failure() {
+        let url = serve(vec!["503 Service Unavailable"]);
+
+        wait_for_http_ok(&url, policy());
+    }
+
+    #[cfg(all(feature = "http", feature = "tokio"))]
+    #[tokio::test]
+    async fn wait_for_http_ok_async_success() {
+        let url = serve(vec!["503 Service Unavailable", "200 OK"]);
+
+        assert_eq!(wait_for_http_ok_async(url, policy()).await, "200 OK");
+    }
+
+    #[cfg(all(feature = "http", feature = "tokio"))]
+    #[tokio::test]
+    #[should_panic(expected = "status 503, body: \"503 Service Unavailable\"")]
+    async fn wait_for_http_ok_async_failure() {
+        let url = serve(vec!["503 Service Unavailable"]);
+
+        wait_for_http_ok_async(url, policy()).await;
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn http_get_error() {
+        let url = serve(vec!["503 Service Unavailable"]);
+
+        let error = http_get(&url, |status| status == 200).unwrap_err();
+
+        assert!(error.starts_with(&format!("unexpected response from {}: status 503", url)));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn wait_for_tcp_async_success() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        wait_for_tcp_async(address, policy()).await;
+    }
+}
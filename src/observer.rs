@@ -0,0 +1,259 @@
+use crate::output::{self, Level};
+use std::{
+    sync::{Arc, OnceLock, RwLock},
+    time::Duration,
+};
+
+/// Observe the tries of repeated assertions, e.g. to collect telemetry.
+///
+/// Observers can be registered per call with [`Retry::observer`](crate::Retry::observer)
+/// or for all calls with [`add_global_observer`].
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # fn record_flaky_test(_attempts: usize, _elapsed: Duration, _message: Option<&str>) {}
+/// struct Telemetry;
+///
+/// impl repeated_assert::RetryObserver for Telemetry {
+///     fn on_failure(&self, attempts: usize, elapsed: Duration, message: Option<&str>) {
+///         record_flaky_test(attempts, elapsed, message);
+///     }
+/// }
+///
+/// repeated_assert::add_global_observer(Telemetry);
+/// ```
+pub trait RetryObserver: Send + Sync {
+    /// Called after every try.
+    fn on_attempt(&self, attempt: &Attempt<'_>) {
+        let _ = attempt;
+    }
+
+    /// Called after a try succeeded.
+    fn on_success(&self, attempts: usize, elapsed: Duration) {
+        let _ = (attempts, elapsed);
+    }
+
+    /// Called after all tries failed, with the panic message of the last try (if it panicked).
+    fn on_failure(&self, attempts: usize, elapsed: Duration, message: Option<&str>) {
+        let _ = (attempts, elapsed, message);
+    }
+}
+
+/// Outcome of a single try, see [`RetryObserver::on_attempt`].
+#[derive(Debug, Clone, Copy)]
+pub struct Attempt<'a> {
+    number: usize,
+    duration: Duration,
+    success: bool,
+    message: Option<&'a str>,
+}
+
+impl<'a> Attempt<'a> {
+    pub(crate) fn new(
+        number: usize,
+        duration: Duration,
+        success: bool,
+        message: Option<&'a str>,
+    ) -> Attempt<'a> {
+        Attempt {
+            number,
+            duration,
+            success,
+            message,
+        }
+    }
+
+    /// Number of the try, starting at 1.
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    /// Time the try took.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Check if the try succeeded.
+    pub fn is_success(&self) -> bool {
+        self.success
+    }
+
+    /// Panic message of the try, if it panicked.
+    pub fn message(&self) -> Option<&'a str> {
+        self.message
+    }
+}
+
+fn observers() -> &'static RwLock<Vec<Arc<dyn RetryObserver>>> {
+    static INSTANCE: OnceLock<RwLock<Vec<Arc<dyn RetryObserver>>>> = OnceLock::new();
+    INSTANCE.get_or_init(|| RwLock::new(Vec::new()))
+}
+
+/// Notify `observer` about the tries of all repeated assertions in the process.
+pub fn add_global_observer<O>(observer: O)
+where
+    O: RetryObserver + 'static,
+{
+    observers()
+        .write()
+        .expect("lock observers")
+        .push(Arc::new(observer));
+}
+
+/// Emits an event for every failed try and records the outcome in the span of the retry.
+#[cfg(feature = "tracing")]
+pub(crate) struct Tracing;
+
+#[cfg(feature = "tracing")]
+impl RetryObserver for Tracing {
+    fn on_attempt(&self, attempt: &Attempt<'_>) {
+        if !attempt.is_success() {
+            tracing::debug!(
+                attempt = attempt.number(),
+                duration = ?attempt.duration(),
+                panic_message = attempt.message(),
+                "try failed"
+            );
+        }
+    }
+
+    fn on_success(&self, attempts: usize, _elapsed: Duration) {
+        let span = tracing::Span::current();
+        span.record("attempts", attempts);
+        span.record("outcome", "success");
+    }
+
+    fn on_failure(&self, attempts: usize, elapsed: Duration, message: Option<&str>) {
+        let span = tracing::Span::current();
+        span.record("attempts", attempts);
+        span.record("outcome", "failure");
+        tracing::debug!(?elapsed, panic_message = message, "all tries failed");
+    }
+}
+
+/// Emits a diagnostic message for every failed try, see [`set_output`](crate::set_output).
+pub(crate) struct Diagnostics<'a> {
+    name: Option<&'a str>,
+}
+
+impl<'a> Diagnostics<'a> {
+    pub(crate) fn new(name: Option<&'a str>) -> Diagnostics<'a> {
+        Diagnostics { name }
+    }
+}
+
+impl RetryObserver for Diagnostics<'_> {
+    fn on_attempt(&self, attempt: &Attempt<'_>) {
+        if attempt.is_success() {
+            return;
+        }
+        let message = attempt.message().unwrap_or("<no panic message>");
+        match self.name {
+            Some(name) => output::emit(
+                Level::Debug,
+                format_args!(
+                    "repeated-assert: try {} of `{}` failed after {:?}: {}",
+                    attempt.number(),
+                    name,
+                    attempt.duration(),
+                    message
+                ),
+            ),
+            None => output::emit(
+                Level::Debug,
+                format_args!(
+                    "repeated-assert: try {} failed after {:?}: {}",
+                    attempt.number(),
+                    attempt.duration(),
+                    message
+                ),
+            ),
+        }
+    }
+}
+
+/// Call `notify` for every global observer.
+pub(crate) fn global_observers<N>(notify: N)
+where
+    N: Fn(&dyn RetryObserver),
+{
+    for observer in observers().read().expect("lock observers").iter() {
+        notify(&**observer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Retry;
+    use std::sync::Mutex;
+
+    struct Failures(Arc<Mutex<Vec<usize>>>);
+
+    impl RetryObserver for Failures {
+        fn on_failure(&self, attempts: usize, _elapsed: Duration, message: Option<&str>) {
+            if message == Some("global observer test") {
+                self.0.lock().unwrap().push(attempts);
+            }
+        }
+    }
+
+    #[test]
+    fn global_observer() {
+        let failures = Arc::new(Mutex::new(Vec::new()));
+        add_global_observer(Failures(failures.clone()));
+
+        let result = Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(10))
+            .try_run(|| {
+                panic!("global observer test");
+            });
+
+        assert!(result.is_err());
+        assert_eq!(*failures.lock().unwrap(), vec![3]);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn tracing_events() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tracing::{span, Event, Metadata, Subscriber};
+
+        struct Events(Arc<AtomicUsize>);
+
+        impl Subscriber for Events {
+            fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+                span::Id::from_u64(1)
+            }
+            fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+            fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+            fn event(&self, _event: &Event<'_>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+            fn enter(&self, _span: &span::Id) {}
+            fn exit(&self, _span: &span::Id) {}
+        }
+
+        let events = Arc::new(AtomicUsize::new(0));
+
+        tracing::subscriber::with_default(Events(events.clone()), || {
+            let mut tries = 0;
+            Retry::new()
+                .repetitions(5)
+                .delay(Duration::from_millis(10))
+                .run(|| {
+                    tries += 1;
+                    assert!(tries > 2);
+                });
+        });
+
+        // one event per failed try
+        assert_eq!(events.load(Ordering::SeqCst), 2);
+    }
+}
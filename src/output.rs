@@ -0,0 +1,133 @@
+use std::{
+    fmt,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+type Output = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Importance of a diagnostic message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Level {
+    /// Printed to stdout by default, e.g. catch block notifications.
+    Info,
+    /// Only logged with the `log` feature, e.g. failed tries.
+    Debug,
+    /// Printed to stderr by default, e.g. the retries of a failure with a non-string panic payload.
+    Error,
+}
+
+fn output() -> &'static RwLock<Option<Output>> {
+    static INSTANCE: OnceLock<RwLock<Option<Output>>> = OnceLock::new();
+    INSTANCE.get_or_init(|| RwLock::new(None))
+}
+
+/// Redirect all diagnostic output of this crate (catch block notifications, failed tries) to `output`.
+///
+/// By default catch block notifications are printed to stdout and the retries of a failure with a non-string panic payload
+/// to stderr (or logged with the `log` feature).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use std::{fs::File, io::Write, sync::Mutex};
+/// let log_file = Mutex::new(File::create("repeated-assert.log")?);
+///
+/// repeated_assert::set_output(move |message| {
+///     writeln!(log_file.lock().unwrap(), "{}", message).unwrap();
+/// });
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn set_output<O>(output_fn: O)
+where
+    O: Fn(&str) + Send + Sync + 'static,
+{
+    *output().write().expect("lock output") = Some(Arc::new(output_fn));
+}
+
+/// Restore the default diagnostic output, see [`set_output`].
+pub fn reset_output() {
+    *output().write().expect("lock output") = None;
+}
+
+/// Emit a diagnostic message.
+pub(crate) fn emit(level: Level, message: fmt::Arguments<'_>) {
+    let output_fn = output().read().expect("lock output").clone();
+    if let Some(output_fn) = output_fn {
+        output_fn(&message.to_string());
+        return;
+    }
+    #[cfg(feature = "log")]
+    match level {
+        Level::Info => log::info!("{}", message),
+        Level::Debug => log::debug!("{}", message),
+        Level::Error => log::error!("{}", message),
+    }
+    #[cfg(not(feature = "log"))]
+    match level {
+        Level::Info => println!("{}", message),
+        Level::Debug => {}
+        Level::Error => eprintln!("{}", message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Retry;
+    use std::{panic, sync::Mutex, time::Duration};
+
+    /// The output is global, so tests setting it have to run one after the other.
+    static OUTPUT: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn custom_output() {
+        let _lock = OUTPUT.lock().unwrap();
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let messages_output = messages.clone();
+        set_output(move |message| {
+            if message.contains("custom output test") {
+                messages_output.lock().unwrap().push(message.to_string());
+            }
+        });
+
+        let result = Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(10))
+            .catch_after(1, || {})
+            .try_run(|| {
+                panic!("custom output test");
+            });
+        reset_output();
+
+        assert!(result.is_err());
+        assert_eq!(messages.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn non_string_payload() {
+        let _lock = OUTPUT.lock().unwrap();
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let messages_output = messages.clone();
+        set_output(move |message| {
+            if message.contains("non-string payload test") {
+                messages_output.lock().unwrap().push(message.to_string());
+            }
+        });
+
+        let payload = panic::catch_unwind(|| {
+            Retry::new()
+                .repetitions(2)
+                .delay(Duration::from_millis(10))
+                .message("non-string payload test")
+                .run(|| panic::panic_any(42_u32))
+        })
+        .unwrap_err();
+        reset_output();
+
+        assert_eq!(payload.downcast_ref::<u32>(), Some(&42));
+        let messages = messages.lock().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("repeated-assert: failed after 2 tries"));
+        assert!(messages[0].contains("called at src/output.rs:"));
+    }
+}
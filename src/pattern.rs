@@ -0,0 +1,109 @@
+use crate::{
+    fs::{excerpt, MAX_EXCERPT},
+    Policy, Retry,
+};
+use regex::Regex;
+
+/// Call `getter` with the retry strategy of `policy` until the returned text matches `regex`.
+///
+/// Returns the matching text.
+///
+/// # Panics
+///
+/// Panics with the last text (its end, if it's long) if it never matched.
+///
+/// # Examples
+///
+/// ```rust
+/// # use repeated_assert::Policy;
+/// # use regex::Regex;
+/// # struct Node;
+/// # impl Node {
+/// #     fn status(&self) -> String { "connected to 3 peers".to_string() }
+/// # }
+/// # let node = Node;
+/// let regex = Regex::new(r"connected to \d+ peers").unwrap();
+/// let status = repeated_assert::wait_for_match(|| node.status(), &regex, Policy::default());
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "regex")))]
+#[track_caller]
+pub fn wait_for_match<G, S>(mut getter: G, regex: &Regex, policy: Policy) -> S
+where
+    G: FnMut() -> S,
+    S: AsRef<str>,
+{
+    Retry::with_policy(policy).run(|| {
+        let text = getter();
+        if let Err(message) = check(text.as_ref(), regex, "text") {
+            panic!("{}", message);
+        }
+        text
+    })
+}
+
+/// Check if `text` matches `regex`, returning the failure message otherwise.
+///
+/// `description` names the text in the message, e.g. the expression of the `matches_re` form of `repeated_assert!`.
+pub fn check(text: &str, regex: &Regex, description: &str) -> Result<(), String> {
+    if regex.is_match(text) {
+        return Ok(());
+    }
+    if text.len() > MAX_EXCERPT {
+        Err(format!(
+            "{} doesn't match /{}/\n  text (last {} of {} bytes): {:?}",
+            description,
+            regex,
+            MAX_EXCERPT,
+            text.len(),
+            excerpt(text)
+        ))
+    } else {
+        Err(format!(
+            "{} doesn't match /{}/\n  text: {:?}",
+            description, regex, text
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::policy;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn wait_for_match_success() {
+        let regex = Regex::new(r"connected to \d+ peers").unwrap();
+        let tries = AtomicUsize::new(0);
+
+        let status = wait_for_match(
+            || match tries.fetch_add(1, Ordering::SeqCst) {
+                0..=2 => "starting".to_string(),
+                peers => format!("connected to {} peers", peers),
+            },
+            &regex,
+            policy(),
+        );
+
+        assert_eq!(status, "connected to 3 peers");
+    }
+
+    #[test]
+    #[should_panic(expected = "text doesn't match /connected/\n  text: \"starting\"")]
+    fn wait_for_match_failure() {
+        let regex = Regex::new("connected").unwrap();
+
+        wait_for_match(|| "starting", &regex, policy());
+    }
+
+    #[test]
+    fn long_text() {
+        let regex = Regex::new("connected").unwrap();
+        let text = "a".repeat(2 * MAX_EXCERPT);
+
+        let message = check(&text, &regex, "`text`").unwrap_err();
+        assert!(message.starts_with(
+            "`text` doesn't match /connected/\n  text (last 1024 of 2048 bytes): \"aaa"
+        ));
+    }
+}
@@ -0,0 +1,388 @@
+//! Waiting for child processes
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! # use repeated_assert::Policy;
+//! # use std::process::Command;
+//! let mut child = Command::new("my-cli").arg("--version").spawn().unwrap();
+//!
+//! let status = repeated_assert::process::wait_for_exit(&mut child, Policy::default());
+//! assert!(status.success());
+//! ```
+//!
+//! Waiting for a server to print a line, with the `regex` feature
+//!
+//! ```rust,no_run
+//! # use repeated_assert::Policy;
+//! # use std::process::{Command, Stdio};
+//! # #[cfg(feature = "regex")]
+//! # fn example() {
+//! # use regex::Regex;
+//! let mut child = Command::new("my-server").stdout(Stdio::piped()).spawn().unwrap();
+//! let mut output = repeated_assert::process::ChildOutput::new(&mut child);
+//!
+//! let regex = Regex::new(r"listening on port \d+").unwrap();
+//! output.wait_for_line(&regex, Policy::default());
+//! # }
+//! ```
+use crate::{Policy, Retry};
+use std::{
+    fmt,
+    io::{BufRead, BufReader, Read},
+    process::{Child, ExitStatus},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+/// Number of output lines included in panic messages.
+const TAIL_LINES: usize = 20;
+
+/// Check with the retry strategy of `policy` if `child` has exited, returning its exit status.
+///
+/// # Panics
+///
+/// Panics if the process is still running after the last try, or its status can't be queried.
+/// The process keeps running, use [`wait_for_exit_or_kill`] to kill it.
+#[track_caller]
+pub fn wait_for_exit(child: &mut Child, policy: Policy) -> ExitStatus {
+    wait(child, policy, false)
+}
+
+/// Check with the retry strategy of `policy` if `child` has exited, returning its exit status.
+///
+/// Kills the process if it's still running when the retry strategy gives up, also with a timeout or deadline.
+///
+/// # Info
+///
+/// See [`wait_for_exit`].
+#[track_caller]
+pub fn wait_for_exit_or_kill(child: &mut Child, policy: Policy) -> ExitStatus {
+    wait(child, policy, true)
+}
+
+#[track_caller]
+fn wait(child: &mut Child, policy: Policy, kill: bool) -> ExitStatus {
+    let retry = Retry::with_policy(policy);
+    if !kill {
+        return retry.run_with_context(|context| try_wait(child, context.attempt()));
+    }
+
+    // the last try isn't known in advance with a timeout or deadline, kill the process after the retry failed
+    let mut attempt = 0;
+    match retry.try_run(|| {
+        attempt += 1;
+        try_wait(child, attempt)
+    }) {
+        Ok(status) => status,
+        Err(error) => {
+            let killed = match child.kill().and_then(|()| child.wait()) {
+                Ok(_) => "killed it".to_string(),
+                Err(error) => format!("failed to kill it: {}", error),
+            };
+            panic!("{}, {}", error.message(), killed);
+        }
+    }
+}
+
+/// Check if `child` has exited, panicking if it's still running after `attempt` attempts.
+fn try_wait(child: &mut Child, attempt: usize) -> ExitStatus {
+    let pid = child.id();
+    match child.try_wait() {
+        Ok(Some(status)) => status,
+        Ok(None) => panic!("process {} still running after {} attempts", pid, attempt),
+        Err(error) => panic!("failed to query status of process {}: {}", pid, error),
+    }
+}
+
+/// Output stream of a child process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    /// Standard output.
+    Stdout,
+    /// Standard error.
+    Stderr,
+}
+
+impl fmt::Display for Stream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stream::Stdout => f.write_str("stdout"),
+            Stream::Stderr => f.write_str("stderr"),
+        }
+    }
+}
+
+/// Lines printed by a child process, collected in the background.
+///
+/// Takes the piped stdout and stderr of the child and reads them on helper threads, so the child never blocks on a full pipe.
+pub struct ChildOutput {
+    receiver: Receiver<(Stream, String)>,
+    lines: Vec<(Stream, String)>,
+    /// Index of the first line not checked by [`wait_for`](ChildOutput::wait_for) yet.
+    position: usize,
+}
+
+impl ChildOutput {
+    /// Start collecting the lines of the piped stdout and stderr of `child`.
+    ///
+    /// Streams that aren't piped (see [`Stdio::piped`](std::process::Stdio::piped)) are ignored.
+    pub fn new(child: &mut Child) -> ChildOutput {
+        let (sender, receiver) = mpsc::channel();
+        if let Some(stdout) = child.stdout.take() {
+            read_lines(stdout, Stream::Stdout, sender.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            read_lines(stderr, Stream::Stderr, sender);
+        }
+        ChildOutput {
+            receiver,
+            lines: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Lines collected so far, without the line breaks.
+    pub fn lines(&mut self) -> &[(Stream, String)] {
+        self.collect();
+        &self.lines
+    }
+
+    /// Wait with the retry strategy of `policy` until a line printed since the last match satisfies `predicate`.
+    ///
+    /// Returns the matching line.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the last collected lines if no line matched,
+    /// without waiting for the remaining tries once the process closed its output.
+    #[track_caller]
+    pub fn wait_for<P>(&mut self, predicate: P, policy: Policy) -> String
+    where
+        P: FnMut(&str) -> bool,
+    {
+        self.wait_matching(predicate, "no line matches", policy)
+    }
+
+    /// Wait with the retry strategy of `policy` until a line printed since the last match matches `regex`.
+    ///
+    /// # Info
+    ///
+    /// See [`wait_for`](ChildOutput::wait_for).
+    #[cfg(feature = "regex")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "regex")))]
+    #[track_caller]
+    pub fn wait_for_line(&mut self, regex: &regex::Regex, policy: Policy) -> String {
+        let description = format!("no line matches /{}/", regex);
+        self.wait_matching(|line| regex.is_match(line), &description, policy)
+    }
+
+    #[track_caller]
+    fn wait_matching<P>(&mut self, mut predicate: P, description: &str, policy: Policy) -> String
+    where
+        P: FnMut(&str) -> bool,
+    {
+        let closed = AtomicBool::new(false);
+        Retry::with_policy(policy)
+            // no new line can arrive once all streams are closed
+            .retry_if(|_| !closed.load(Ordering::SeqCst))
+            .run(|| {
+                let disconnected = self.collect();
+                closed.store(disconnected, Ordering::SeqCst);
+                let position = self.position;
+                if let Some(index) = self.lines[position..]
+                    .iter()
+                    .position(|(_, line)| predicate(line))
+                {
+                    self.position = position + index + 1;
+                    return self.lines[position + index].1.clone();
+                }
+                self.position = self.lines.len();
+                panic!(
+                    "{}{}, last lines of output:\n{}",
+                    description,
+                    if disconnected { " (output closed)" } else { "" },
+                    self.tail()
+                );
+            })
+    }
+
+    /// Move the lines read by the helper threads into the collected lines.
+    ///
+    /// Returns `true` if all streams are closed.
+    fn collect(&mut self) -> bool {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(line) => self.lines.push(line),
+                Err(mpsc::TryRecvError::Empty) => return false,
+                Err(mpsc::TryRecvError::Disconnected) => return true,
+            }
+        }
+    }
+
+    /// Format the last collected lines.
+    fn tail(&self) -> String {
+        let start = self.lines.len().saturating_sub(TAIL_LINES);
+        let mut tail = String::new();
+        for (stream, line) in &self.lines[start..] {
+            tail.push_str(&format!("  {}: {}\n", stream, line));
+        }
+        if tail.is_empty() {
+            tail.push_str("  <empty>\n");
+        }
+        tail
+    }
+}
+
+impl fmt::Debug for ChildOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChildOutput")
+            .field("lines", &self.lines)
+            .finish()
+    }
+}
+
+/// Send the lines of `reader` to `sender` on a helper thread.
+fn read_lines<R>(reader: R, stream: Stream, sender: Sender<(Stream, String)>)
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {
+                    let text = String::from_utf8_lossy(&line);
+                    let text = text.trim_end_matches(['\n', '\r']).to_string();
+                    // keep draining the pipe after the receiver is gone, so the child doesn't block
+                    let _ = sender.send((stream, text));
+                }
+            }
+        }
+    });
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::config::tests::policy;
+    use std::{
+        process::{Command, Stdio},
+        time::Duration,
+    };
+
+    #[test]
+    fn wait_for_exit_success() {
+        let mut child = Command::new("sh").args(["-c", "exit 3"]).spawn().unwrap();
+
+        let status = wait_for_exit(&mut child, policy());
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[test]
+    fn wait_for_output_success() {
+        let mut child = Command::new("sh")
+            .args([
+                "-c",
+                "echo starting; sleep 0.05; echo 'listening on 8080' >&2; sleep 10",
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut output = ChildOutput::new(&mut child);
+
+        let line = output.wait_for(|line| line.starts_with("listening"), policy());
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        assert_eq!(line, "listening on 8080");
+        assert_eq!(output.lines()[0], (Stream::Stdout, "starting".to_string()));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    #[should_panic(
+        expected = "no line matches /^ready$/ (output closed), last lines of output:\n  stdout: starting\n"
+    )]
+    fn wait_for_line_failure() {
+        let mut child = Command::new("echo")
+            .arg("starting")
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut output = ChildOutput::new(&mut child);
+
+        output.wait_for_line(&regex::Regex::new("^ready$").unwrap(), policy());
+    }
+
+    #[test]
+    fn wait_for_output_closed() {
+        let mut child = Command::new("echo")
+            .arg("starting")
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut output = ChildOutput::new(&mut child);
+        child.wait().unwrap();
+        let start = std::time::Instant::now();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            output.wait_for(
+                |line| line == "ready",
+                Policy {
+                    repetitions: Some(50),
+                    delay: Duration::from_millis(200),
+                    ..Policy::default()
+                },
+            );
+        }));
+
+        let message = crate::error::panic_message(&*result.unwrap_err());
+        assert!(message.contains("no line matches (output closed)"));
+        // failed without sleeping through the policy
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn wait_for_exit_or_kill_failure() {
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            wait_for_exit_or_kill(&mut child, policy());
+        }));
+
+        let message = crate::error::panic_message(&*result.unwrap_err());
+        assert!(message.contains("still running after 10 attempts, killed it"));
+        // the process has been reaped
+        assert!(child.try_wait().unwrap().is_some());
+    }
+
+    #[test]
+    fn wait_for_exit_or_kill_timeout() {
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            wait_for_exit_or_kill(
+                &mut child,
+                Policy {
+                    repetitions: None,
+                    timeout: Some(Duration::from_millis(100)),
+                    delay: Duration::from_millis(10),
+                    ..Policy::default()
+                },
+            );
+        }));
+
+        let message = crate::error::panic_message(&*result.unwrap_err());
+        assert!(message.contains("killed it"));
+        // the process has been reaped
+        assert!(child.try_wait().unwrap().is_some());
+    }
+}
@@ -0,0 +1,400 @@
+use crate::{
+    observer::{Attempt, RetryObserver},
+    Instant, SystemTime,
+};
+use std::{
+    fmt::Write as _,
+    fs::OpenOptions,
+    io::{self, Write as _},
+    panic::Location,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, UNIX_EPOCH},
+};
+
+/// Statistics about the tries of a repeated assertion, returned by [`that_with_report`](crate::that_with_report)
+/// and [`Retry::run_with_report`](crate::Retry::run_with_report).
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    elapsed: Duration,
+    tries: Vec<AttemptReport>,
+}
+
+impl Report {
+    /// Number of tries, including the successful one.
+    pub fn attempts(&self) -> usize {
+        self.tries.len()
+    }
+
+    /// Time passed from the first try until the successful one finished.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Outcome of every try.
+    pub fn tries(&self) -> &[AttemptReport] {
+        &self.tries
+    }
+}
+
+/// Pass rate of a repeated assertion, returned by [`sample`](crate::sample) and [`Retry::sample`](crate::Retry::sample).
+#[derive(Debug, Clone, Default)]
+pub struct SampleReport {
+    elapsed: Duration,
+    tries: Vec<AttemptReport>,
+}
+
+impl SampleReport {
+    pub(crate) fn new(elapsed: Duration, tries: Vec<AttemptReport>) -> SampleReport {
+        SampleReport { elapsed, tries }
+    }
+
+    /// Number of tries.
+    pub fn attempts(&self) -> usize {
+        self.tries.len()
+    }
+
+    /// Number of passed tries.
+    pub fn successes(&self) -> usize {
+        self.tries
+            .iter()
+            .filter(|attempt| attempt.is_success())
+            .count()
+    }
+
+    /// Number of failed tries.
+    pub fn failures(&self) -> usize {
+        self.attempts() - self.successes()
+    }
+
+    /// Ratio of passed tries, between 0 and 1.
+    pub fn pass_rate(&self) -> f64 {
+        match self.attempts() {
+            0 => 0.0,
+            attempts => self.successes() as f64 / attempts as f64,
+        }
+    }
+
+    /// Panic messages of the failed tries.
+    pub fn failure_messages(&self) -> impl Iterator<Item = &str> {
+        self.tries.iter().filter_map(AttemptReport::message)
+    }
+
+    /// Time passed from the first try until the last one finished.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Outcome of every try.
+    pub fn tries(&self) -> &[AttemptReport] {
+        &self.tries
+    }
+}
+
+/// Outcome of a single try, see [`Report::tries`] and [`SampleReport::tries`].
+#[derive(Debug, Clone)]
+pub struct AttemptReport {
+    number: usize,
+    started: Duration,
+    duration: Duration,
+    message: Option<String>,
+}
+
+impl AttemptReport {
+    pub(crate) fn new(
+        number: usize,
+        started: Duration,
+        duration: Duration,
+        message: Option<String>,
+    ) -> AttemptReport {
+        AttemptReport {
+            number,
+            started,
+            duration,
+            message,
+        }
+    }
+
+    /// Number of the try, starting at 1.
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    /// Time passed from the first try until the try started.
+    pub fn started(&self) -> Duration {
+        self.started
+    }
+
+    /// Time the try took.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Check if the try succeeded.
+    pub fn is_success(&self) -> bool {
+        self.message.is_none()
+    }
+
+    /// Panic message of the try, if it failed.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+/// Observer collecting a [`Report`].
+#[derive(Clone)]
+pub(crate) struct Recorder {
+    start: Instant,
+    report: Arc<Mutex<Report>>,
+}
+
+impl Recorder {
+    pub(crate) fn new() -> Recorder {
+        Recorder {
+            start: Instant::now(),
+            report: Arc::new(Mutex::new(Report::default())),
+        }
+    }
+
+    /// Take the collected report.
+    pub(crate) fn take(&self) -> Report {
+        std::mem::take(&mut *self.report.lock().expect("lock report"))
+    }
+}
+
+impl RetryObserver for Recorder {
+    fn on_attempt(&self, attempt: &Attempt<'_>) {
+        let started = self.start.elapsed().saturating_sub(attempt.duration());
+        let message = if attempt.is_success() {
+            None
+        } else {
+            Some(attempt.message().unwrap_or_default().to_string())
+        };
+        self.report
+            .lock()
+            .expect("lock report")
+            .tries
+            .push(AttemptReport::new(
+                attempt.number(),
+                started,
+                attempt.duration(),
+                message,
+            ));
+    }
+
+    fn on_success(&self, _attempts: usize, elapsed: Duration) {
+        self.report.lock().expect("lock report").elapsed = elapsed;
+    }
+
+    fn on_failure(&self, _attempts: usize, elapsed: Duration, _message: Option<&str>) {
+        self.report.lock().expect("lock report").elapsed = elapsed;
+    }
+}
+
+/// Destination of the JSON failure report, see [`Retry::failure_report`](crate::Retry::failure_report).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReportOutput {
+    /// Write the report to stderr.
+    Stderr,
+    /// Append the report to the given file as a single line, creating it if necessary.
+    File(PathBuf),
+}
+
+/// Retry strategy included in the failure report.
+#[derive(Debug, Clone)]
+pub(crate) struct ScheduleInfo {
+    pub(crate) repetitions: Option<usize>,
+    pub(crate) delay: Duration,
+    pub(crate) backoff: f64,
+    pub(crate) max_delay: Option<Duration>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) custom: bool,
+}
+
+/// Observer writing a JSON report if all tries failed.
+pub(crate) struct FailureReport {
+    output: ReportOutput,
+    schedule: ScheduleInfo,
+    name: Option<String>,
+    location: &'static Location<'static>,
+    start: SystemTime,
+    recorder: Recorder,
+}
+
+impl FailureReport {
+    pub(crate) fn new(
+        output: ReportOutput,
+        schedule: ScheduleInfo,
+        name: Option<String>,
+        location: &'static Location<'static>,
+    ) -> FailureReport {
+        FailureReport {
+            output,
+            schedule,
+            name,
+            location,
+            start: SystemTime::now(),
+            recorder: Recorder::new(),
+        }
+    }
+
+    fn write(&self, json: &str) -> io::Result<()> {
+        match &self.output {
+            ReportOutput::Stderr => writeln!(io::stderr().lock(), "{}", json),
+            ReportOutput::File(path) => {
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                writeln!(file, "{}", json)
+            }
+        }
+    }
+}
+
+impl RetryObserver for FailureReport {
+    fn on_attempt(&self, attempt: &Attempt<'_>) {
+        self.recorder.on_attempt(attempt);
+    }
+
+    fn on_failure(&self, attempts: usize, elapsed: Duration, message: Option<&str>) {
+        self.recorder.on_failure(attempts, elapsed, message);
+        let report = self.recorder.take();
+        let json = failure_json(
+            &report,
+            &self.schedule,
+            self.name.as_deref(),
+            self.location,
+            self.start,
+            message,
+        );
+        if let Err(error) = self.write(&json) {
+            crate::output::emit(
+                crate::output::Level::Info,
+                format_args!("repeated-assert: failed to write failure report: {}", error),
+            );
+        }
+    }
+}
+
+/// Format the report of the failed tries as a single line JSON document.
+fn failure_json(
+    report: &Report,
+    schedule: &ScheduleInfo,
+    name: Option<&str>,
+    location: &Location<'_>,
+    start: SystemTime,
+    message: Option<&str>,
+) -> String {
+    let start_ms = millis(start.duration_since(UNIX_EPOCH).unwrap_or_default());
+    let mut json = String::new();
+    write!(
+        json,
+        "{{\"name\":{},\"location\":{}",
+        json_option(name.map(json_string)),
+        json_string(&location.to_string())
+    )
+    .unwrap();
+    write!(
+        json,
+        ",\"schedule\":{{\"repetitions\":{},\"delay_ms\":{},\"backoff\":{},\"max_delay_ms\":{},\"timeout_ms\":{},\"custom\":{}}}",
+        json_option(schedule.repetitions),
+        millis(schedule.delay),
+        schedule.backoff,
+        json_option(schedule.max_delay.map(millis)),
+        json_option(schedule.timeout.map(millis)),
+        schedule.custom
+    )
+    .unwrap();
+    write!(
+        json,
+        ",\"attempts\":{},\"elapsed_ms\":{},\"message\":{},\"tries\":[",
+        report.attempts(),
+        millis(report.elapsed()),
+        json_option(message.map(json_string))
+    )
+    .unwrap();
+    for (index, attempt) in report.tries().iter().enumerate() {
+        if index > 0 {
+            json.push(',');
+        }
+        write!(
+            json,
+            "{{\"number\":{},\"timestamp_ms\":{},\"duration_ms\":{},\"message\":{}}}",
+            attempt.number(),
+            start_ms + millis(attempt.started()),
+            millis(attempt.duration()),
+            json_option(attempt.message().map(json_string))
+        )
+        .unwrap();
+    }
+    json.push_str("]}");
+    json
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn json_option<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "null".to_string(), |value| value.to_string())
+}
+
+pub(crate) fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Retry;
+
+    #[test]
+    fn escape_json() {
+        assert_eq!(
+            json_string("say \"hi\"\n\\ \u{1}"),
+            r#""say \"hi\"\n\\ \u0001""#
+        );
+    }
+
+    #[test]
+    fn failure_report() {
+        let path = std::env::temp_dir().join(format!(
+            "repeated-assert-failure-report-{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let result = Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(10))
+            .failure_report(ReportOutput::File(path.clone()))
+            .try_run(|| {
+                panic!("failure \"report\" test");
+            });
+
+        let json = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+        assert_eq!(json.lines().count(), 1);
+        assert!(json.contains(r#""schedule":{"repetitions":3,"delay_ms":10,"#));
+        assert!(json.contains(r#""attempts":3,"#));
+        assert_eq!(
+            json.matches(r#""message":"failure \"report\" test""#)
+                .count(),
+            4
+        );
+        assert!(json.contains(r#"{"number":3,"timestamp_ms":"#));
+    }
+}
@@ -0,0 +1,3830 @@
+#[cfg(feature = "capture")]
+use crate::capture::{Capture, Replay};
+use crate::{
+    clock::{Clock, SystemClock},
+    config::{self, EnvOverrides, Policy, DEFAULT_DELAY, DEFAULT_REPETITIONS},
+    error::panic_message,
+    flakiness,
+    hook::{self, BacktraceGuard, IgnoreGuard, ReportGuard},
+    observer::{self, Attempt, RetryObserver},
+    output,
+    report::{
+        AttemptReport, FailureReport, Recorder, Report, ReportOutput, SampleReport, ScheduleInfo,
+    },
+    schedule::{self, Jitter},
+    CatchAction, CatchContext, Context, Instant, RetryError, Trigger,
+};
+#[cfg(feature = "async")]
+use crate::{
+    hook::ignore_panics,
+    sleep::{self, Sleeper},
+};
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    fmt,
+    ops::ControlFlow,
+    panic::{self, Location},
+    sync::{mpsc, Arc},
+    thread,
+    time::Duration,
+};
+
+const POLL_NONE_MESSAGE: &str = "polled function returned `None`";
+
+type Schedule<'a> = Box<dyn Iterator<Item = Duration> + Send + 'a>;
+type PeriodicCatch<'a> = Box<dyn FnMut(&CatchContext) -> CatchAction + Send + 'a>;
+type RetryIf<'a> = Box<dyn Fn(&str) -> bool + Send + 'a>;
+type FinalFailure<'a> = Box<dyn FnOnce() + Send + 'a>;
+#[cfg(feature = "async")]
+type Wake<'a> = Box<dyn FnMut() -> futures::future::BoxFuture<'a, ()> + Send + 'a>;
+
+/// Number of tries and panic payload of the last try, if all tries failed.
+type Failure = (usize, Box<dyn Any + Send>);
+
+/// Catch block of the free functions like [`with_catch`](crate::with_catch) and the number of failed tries after which it's executed.
+///
+/// It isn't stored in the [`Retry`], so it doesn't need to be `Send`.
+type LocalCatch<C> = Option<(usize, C)>;
+
+enum Catch<'a> {
+    Sync(Box<dyn FnOnce(&CatchContext) -> CatchAction + Send + 'a>),
+    #[cfg(feature = "async")]
+    Async(Box<dyn FnOnce(CatchContext) -> futures::future::BoxFuture<'a, CatchAction> + Send + 'a>),
+}
+
+/// Builder to configure and run repeated assertions.
+///
+/// By default the assertions are run up to 10 times with a delay of 50 ms in between tries,
+/// unless changed with [`set_default_policy`](crate::set_default_policy).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use std::{path::Path, time::Duration};
+/// repeated_assert::Retry::new()
+///     .repetitions(10)
+///     .delay(Duration::from_millis(50))
+///     .catch_after(5, || {
+///         // poke unreliable service
+///     })
+///     .run(|| {
+///         assert!(Path::new("should_appear_soon.txt").exists());
+///     });
+/// ```
+///
+/// Exponential backoff with a total timeout
+///
+/// ```rust,no_run
+/// # use std::{path::Path, time::Duration};
+/// repeated_assert::Retry::new()
+///     .delay(Duration::from_millis(10))
+///     .backoff(2.0)
+///     .max_delay(Duration::from_millis(500))
+///     .timeout(Duration::from_secs(5))
+///     .run(|| {
+///         assert!(Path::new("should_appear_soon.txt").exists());
+///     });
+/// ```
+pub struct Retry<'a> {
+    name: Option<String>,
+    repetitions: Option<usize>,
+    /// Whether `repetitions` was taken from the policy, so a timeout or deadline set in code overrides it.
+    policy_repetitions: bool,
+    /// Whether `repetitions` is an exact number of tries, which the environment variable `REPEATED_ASSERT_MAX_REPETITIONS` doesn't override.
+    exact_repetitions: bool,
+    delay: Duration,
+    backoff: f64,
+    max_delay: Option<Duration>,
+    jitter: Option<Jitter>,
+    spins: usize,
+    adaptive_delay: Option<f64>,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    shared_deadline: Option<Instant>,
+    attempt_timeout: Option<Duration>,
+    stop_at: Option<Instant>,
+    last_wake: Option<Instant>,
+    clock: Option<Arc<dyn Clock>>,
+    #[cfg(feature = "async")]
+    sleeper: Option<Arc<dyn Sleeper>>,
+    schedule: Option<Schedule<'a>>,
+    retry_if: Option<RetryIf<'a>>,
+    observers: Vec<Arc<dyn RetryObserver>>,
+    failure_report: Option<ReportOutput>,
+    messages: Vec<(String, usize)>,
+    consecutive_successes: usize,
+    max_latency: Option<Duration>,
+    capture_backtrace: bool,
+    backtrace: Option<(usize, Backtrace)>,
+    /// Location of the panic of the failed try being reported, if the failure is a panic of a try.
+    panic_location: Option<String>,
+    /// Whether the last try runs without ignoring its panic, because a failure panics instead of returning an error.
+    report_last_try: bool,
+    /// Whether the failure being reported is the panic of the last try, which the panic hook reported already.
+    last_try_reported: bool,
+    final_failure: Option<FinalFailure<'a>>,
+    message: Option<String>,
+    #[cfg(feature = "capture")]
+    capture: Option<Capture>,
+    trigger: Option<Trigger>,
+    #[cfg(feature = "async")]
+    wake: Option<Wake<'a>>,
+    strict: bool,
+    catches: Vec<(usize, Catch<'a>)>,
+    periodic_catches: Vec<(usize, PeriodicCatch<'a>)>,
+    location: &'static Location<'static>,
+}
+
+impl<'a> Retry<'a> {
+    /// Create a new builder with the [default policy](crate::default_policy).
+    ///
+    /// The location of the caller is included in the panic message if the assertions fail.
+    #[track_caller]
+    pub fn new() -> Retry<'a> {
+        Retry::with_policy(config::default_policy())
+    }
+
+    /// Create a new builder with the given `policy`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `backoff` of `policy` is negative, infinite or NaN.
+    #[track_caller]
+    pub fn with_policy(policy: Policy) -> Retry<'a> {
+        config::check_backoff(policy.backoff);
+        Retry {
+            name: None,
+            repetitions: policy.repetitions,
+            policy_repetitions: policy.repetitions.is_some(),
+            exact_repetitions: false,
+            delay: policy.delay,
+            backoff: policy.backoff,
+            max_delay: policy.max_delay,
+            jitter: None,
+            spins: 0,
+            adaptive_delay: None,
+            timeout: policy.timeout,
+            deadline: None,
+            shared_deadline: None,
+            attempt_timeout: None,
+            stop_at: None,
+            last_wake: None,
+            clock: None,
+            #[cfg(feature = "async")]
+            sleeper: None,
+            schedule: None,
+            retry_if: None,
+            observers: Vec::new(),
+            failure_report: None,
+            messages: Vec::new(),
+            consecutive_successes: 1,
+            max_latency: None,
+            capture_backtrace: false,
+            backtrace: None,
+            panic_location: None,
+            report_last_try: false,
+            last_try_reported: false,
+            final_failure: None,
+            message: None,
+            #[cfg(feature = "capture")]
+            capture: None,
+            trigger: None,
+            #[cfg(feature = "async")]
+            wake: None,
+            strict: config::strict(),
+            catches: Vec::new(),
+            periodic_catches: Vec::new(),
+            location: Location::caller(),
+        }
+    }
+
+    /// Label the assertions with `name`, which is included in all diagnostics, reports and the final panic message.
+    ///
+    /// # Info
+    ///
+    /// See [`that_named`](crate::that_named).
+    pub fn name<N: Into<String>>(mut self, name: N) -> Retry<'a> {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Stop re-trying once the `deadline` shared with other retries is reached, which isn't scaled.
+    pub(crate) fn shared_deadline(mut self, deadline: Instant) -> Retry<'a> {
+        self.shared_deadline = Some(deadline);
+        self
+    }
+
+    /// Call `round` with the number of the try on every try of the configured retry strategy until it returns `Some` value.
+    ///
+    /// Returns the value (if any), the number of tries and the elapsed time.
+    pub(crate) fn rounds<F, T>(mut self, mut round: F) -> (Option<T>, usize, Duration)
+    where
+        F: FnMut(usize) -> Option<T>,
+    {
+        let start = self.time().now();
+        let mut delays = self.delays();
+        let mut tries = 0;
+        loop {
+            tries += 1;
+            if let Some(value) = round(tries) {
+                return (Some(value), tries, self.time().elapsed(start));
+            }
+            match delays.next() {
+                // sleep until the next try
+                Some(delay) if !self.strict => self.sleep(delay),
+                _ => return (None, tries, self.time().elapsed(start)),
+            }
+        }
+    }
+
+    /// Sleep for `delay`, waking up early if the [`trigger`](Retry::trigger) is notified.
+    fn sleep(&mut self, delay: Duration) {
+        let delay = self.adjust_delay(delay);
+        let target = self.time().now().checked_add(delay);
+        match &mut self.trigger {
+            Some(trigger) => trigger.wait(delay),
+            // even a zero sleep might take as long as the timer granularity
+            None if delay.is_zero() => thread::yield_now(),
+            None => self.time().sleep(delay),
+        }
+        self.woke(target);
+    }
+
+    /// Record the end of a sleep until `target`.
+    ///
+    /// Oversleeping is made up for by the next delay, which is adjusted from the `target`.
+    fn woke(&mut self, target: Option<Instant>) {
+        let now = self.time().now();
+        self.last_wake = Some(target.map_or(now, |target| now.min(target)));
+    }
+
+    /// Adjust the scheduled `delay` to the time spent since the last sleep, see [`adaptive_delay`](Retry::adaptive_delay).
+    fn adjust_delay(&self, delay: Duration) -> Duration {
+        let Some(factor) = self.adaptive_delay else {
+            return delay;
+        };
+        let now = self.time().now();
+        let spent = self.last_wake.map_or(Duration::ZERO, |last_wake| {
+            now.saturating_duration_since(last_wake)
+        });
+        let interval =
+            Duration::try_from_secs_f64(spent.as_secs_f64() * factor).unwrap_or(Duration::MAX);
+        let delay = delay.max(interval).saturating_sub(spent);
+        match self.stop_at {
+            Some(stop_at) => delay.min(stop_at.saturating_duration_since(now)),
+            None => delay,
+        }
+    }
+
+    /// Clock to read the time and wait between tries, see [`clock`](Retry::clock).
+    fn time(&self) -> &dyn Clock {
+        self.clock.as_deref().unwrap_or(&SystemClock)
+    }
+
+    /// Sleeper to wait between tries of the async functions, see [`sleeper`](Retry::sleeper).
+    ///
+    /// The default tokio sleeper is combined with the tokio clock, so the retries follow paused time.
+    #[cfg(feature = "async")]
+    fn async_sleeper(&mut self) -> Arc<dyn Sleeper> {
+        #[cfg(all(feature = "tokio", not(all(feature = "wasm", target_arch = "wasm32"))))]
+        if self.sleeper.is_none() && self.clock.is_none() {
+            self.clock = Some(Arc::new(crate::clock::Tokio));
+        }
+        self.sleeper.clone().unwrap_or_else(sleep::default_sleeper)
+    }
+
+    /// Sleep for `delay` using `sleeper`, waking up early if the [`wake_on`](Retry::wake_on) future completes.
+    #[cfg(feature = "async")]
+    async fn sleep_async(&mut self, delay: Duration, sleeper: &Arc<dyn Sleeper>) {
+        let delay = self.adjust_delay(delay);
+        let target = self.time().now().checked_add(delay);
+        match &mut self.wake {
+            // even a zero sleep might take as long as the timer granularity
+            _ if delay.is_zero() => sleep::yield_now().await,
+            Some(wake) => {
+                futures::future::select(sleeper.sleep(delay), wake()).await;
+            }
+            None => sleeper.sleep(delay).await,
+        }
+        self.woke(target);
+    }
+
+    /// Clock of the retry, see [`clock`](Retry::clock).
+    pub(crate) fn shared_clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone().unwrap_or_else(|| Arc::new(SystemClock))
+    }
+
+    /// Name of the assertions, see [`name`](Retry::name).
+    pub(crate) fn label(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Run the assertions up to `repetitions` times.
+    ///
+    /// Defaults to 10, or unlimited if a [`timeout`](Retry::timeout) or [`deadline`](Retry::deadline) is set.
+    pub fn repetitions(mut self, repetitions: usize) -> Retry<'a> {
+        self.repetitions = Some(repetitions);
+        self.policy_repetitions = false;
+        self
+    }
+
+    /// Run the assertions exactly `repetitions` times, unless they pass earlier.
+    ///
+    /// Unlike [`repetitions`](Retry::repetitions) not overridden by the environment variable `REPEATED_ASSERT_MAX_REPETITIONS`.
+    #[cfg(any(feature = "harness", test))]
+    pub(crate) fn exact_repetitions(mut self, repetitions: usize) -> Retry<'a> {
+        self.exact_repetitions = true;
+        self.repetitions(repetitions)
+    }
+
+    /// Wait `delay` between tries.
+    ///
+    /// A zero delay yields to other threads (or tasks for the async functions) instead of sleeping,
+    /// for conditions that become true within microseconds.
+    pub fn delay(mut self, delay: Duration) -> Retry<'a> {
+        self.delay = delay;
+        self
+    }
+
+    /// Wait exactly `delay` between tries, ignoring the backoff and maximum delay of the policy.
+    pub(crate) fn fixed_delay(mut self, delay: Duration) -> Retry<'a> {
+        self.delay = delay;
+        self.backoff = 1.0;
+        self.max_delay = None;
+        self
+    }
+
+    /// Multiply the delay by `factor` after every try.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is negative, infinite or NaN.
+    pub fn backoff(mut self, factor: f64) -> Retry<'a> {
+        config::check_backoff(factor);
+        self.backoff = factor;
+        self
+    }
+
+    /// Limit the delay between tries to `max_delay`.
+    pub fn max_delay(mut self, max_delay: Duration) -> Retry<'a> {
+        self.max_delay = Some(max_delay);
+        self
+    }
+
+    /// Randomize the delay between tries, see [`schedule::jitter`].
+    pub fn jitter(mut self, jitter: Jitter) -> Retry<'a> {
+        self.jitter = Some(jitter);
+        self
+    }
+
+    /// Re-try `spins` times without sleeping before the configured delays start, see [`spin_then`](schedule::spin_then).
+    ///
+    /// The spins are additional tries, which don't count towards the [`repetitions`](Retry::repetitions).
+    pub fn spin_first(mut self, spins: usize) -> Retry<'a> {
+        self.spins = spins;
+        self
+    }
+
+    /// Adapt the delay to the duration of the tries, so the next try starts at least `factor` times the duration of the
+    /// last try after its start.
+    ///
+    /// The delay is shortened by the duration of the try, unless that would poll faster. E.g. with a factor of `2.0`,
+    /// tries of a slow service take at most half of the time, without stacking the full delay on top of them.
+    /// A factor below 1 is treated as 1, which is the same as [`fixed_rate`](Retry::fixed_rate).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # #[derive(Debug, PartialEq)]
+    /// # enum Status { Ready }
+    /// # struct Service;
+    /// # impl Service {
+    /// #     fn status(&self) -> Status { Status::Ready }
+    /// # }
+    /// # let slow_service = Service;
+    /// repeated_assert::Retry::new()
+    ///     .delay(Duration::from_millis(100))
+    ///     .adaptive_delay(2.0)
+    ///     .timeout(Duration::from_secs(30))
+    ///     .run(|| {
+    ///         assert_eq!(slow_service.status(), Status::Ready);
+    ///     });
+    /// ```
+    pub fn adaptive_delay(mut self, factor: f64) -> Retry<'a> {
+        self.adaptive_delay = Some(factor.max(1.0));
+        self
+    }
+
+    /// Start the tries at a fixed rate, like [`tokio::time::interval`](https://docs.rs/tokio/latest/tokio/time/fn.interval.html),
+    /// by shortening the delay by the duration of the try.
+    ///
+    /// Without it, the full delay is waited after every try, so slow tries stretch the time between them.
+    /// A try that takes longer than the delay is followed by the next one immediately.
+    pub fn fixed_rate(mut self) -> Retry<'a> {
+        self.adaptive_delay = Some(1.0);
+        self
+    }
+
+    /// Stop re-trying once `timeout` has passed.
+    ///
+    /// The timeout starts when the assertions are run.
+    /// The repetitions of the policy are dropped, unless set explicitly with [`repetitions`](Retry::repetitions).
+    pub fn timeout(mut self, timeout: Duration) -> Retry<'a> {
+        self.timeout = Some(timeout);
+        self.drop_policy_repetitions();
+        self
+    }
+
+    /// Stop re-trying once `deadline` is reached.
+    ///
+    /// The repetitions of the policy are dropped, unless set explicitly with [`repetitions`](Retry::repetitions).
+    pub fn deadline(mut self, deadline: Instant) -> Retry<'a> {
+        self.deadline = Some(deadline);
+        self.drop_policy_repetitions();
+        self
+    }
+
+    /// Re-try until the timeout or deadline set in code, instead of the number of repetitions of the policy.
+    fn drop_policy_repetitions(&mut self) {
+        if self.policy_repetitions {
+            self.repetitions = None;
+            self.policy_repetitions = false;
+        }
+    }
+
+    /// Abandon tries that take longer than `attempt_timeout` and count them as failed.
+    ///
+    /// Only applies to [`run_threaded`](Retry::run_threaded) and the async functions, since a blocking function can't be interrupted otherwise.
+    pub fn attempt_timeout(mut self, attempt_timeout: Duration) -> Retry<'a> {
+        self.attempt_timeout = Some(attempt_timeout);
+        self
+    }
+
+    /// Read the time and wait between tries using `clock`, see [`clock`](crate::clock).
+    ///
+    /// Use a [`MockClock`](crate::clock::MockClock) to test retries without waiting.
+    /// The async functions wait using the [`sleeper`](Retry::sleeper) and a [`trigger`](Retry::trigger) waits in real time.
+    /// Without a clock and sleeper, the async functions use the [tokio clock](crate::clock::Tokio) with the `tokio` feature.
+    pub fn clock<C>(mut self, clock: C) -> Retry<'a>
+    where
+        C: Clock + 'static,
+    {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Use `sleeper` to wait between tries of the async functions, see [`sleep`](crate::sleep).
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub fn sleeper<S>(mut self, sleeper: S) -> Retry<'a>
+    where
+        S: Sleeper + 'static,
+    {
+        self.sleeper = Some(Arc::new(sleeper));
+        self
+    }
+
+    /// Re-check the assertions of the async functions as soon as the future returned by `wake` completes, instead of waiting out the full delay.
+    ///
+    /// `wake` is called before every delay. The delay remains the upper bound of the time between tries.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # async fn query_db() -> &'static str { "success" }
+    /// # #[cfg(feature = "async-std")]
+    /// # async fn example() {
+    /// # use async_std::channel as async_channel;
+    /// let (tx, rx) = async_channel::unbounded::<()>();
+    ///
+    /// repeated_assert::Retry::new()
+    ///     .delay(Duration::from_secs(1))
+    ///     .wake_on(|| {
+    ///         let rx = rx.clone();
+    ///         async move {
+    ///             let _ = rx.recv().await;
+    ///         }
+    ///     })
+    ///     .run_async(|| async {
+    ///         assert_eq!(query_db().await, "success");
+    ///     })
+    ///     .await;
+    /// # }
+    /// ```
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub fn wake_on<W, F>(mut self, mut wake: W) -> Retry<'a>
+    where
+        W: FnMut() -> F + Send + 'a,
+        F: std::future::Future<Output = ()> + Send + 'a,
+    {
+        use futures::future::FutureExt;
+
+        self.wake = Some(Box::new(move || wake().boxed()));
+        self
+    }
+
+    /// Re-check the assertions of the async functions as soon as `notify` is notified, instead of waiting out the full delay.
+    ///
+    /// Use [`Notify::notify_one`](tokio::sync::Notify::notify_one) to signal, so a notification during a try isn't lost.
+    ///
+    /// # Info
+    ///
+    /// See [`wake_on`](Retry::wake_on).
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    pub fn wake_on_notify(self, notify: &'a tokio::sync::Notify) -> Retry<'a> {
+        self.wake_on(move || notify.notified())
+    }
+
+    /// Take the delays between tries from `schedule`, see [`that_scheduled`](crate::that_scheduled).
+    ///
+    /// This replaces [`repetitions`](Retry::repetitions), [`delay`](Retry::delay),
+    /// [`backoff`](Retry::backoff) and [`max_delay`](Retry::max_delay).
+    ///
+    /// The schedule has to be `Send`, because the futures of the async run methods hold the `Retry`.
+    pub fn schedule<S>(mut self, schedule: S) -> Retry<'a>
+    where
+        S: IntoIterator<Item = Duration>,
+        S::IntoIter: Send + 'a,
+    {
+        self.schedule = Some(Box::new(schedule.into_iter()));
+        self
+    }
+
+    /// Only re-try panics whose message matches `predicate`, other panics are propagated immediately.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use std::net::TcpStream;
+    /// repeated_assert::Retry::new()
+    ///     .retry_if(|message| message.contains("connection refused"))
+    ///     .run(|| {
+    ///         TcpStream::connect("localhost:8080").expect("connection refused");
+    ///     });
+    /// ```
+    pub fn retry_if<P>(mut self, predicate: P) -> Retry<'a>
+    where
+        P: Fn(&str) -> bool + Send + 'a,
+    {
+        self.retry_if = Some(Box::new(predicate));
+        self
+    }
+
+    /// Only re-try failed assertions (`assert!`, `assert_eq!`, `assert_ne!`), other panics (e.g. unwrapping `None`) are propagated immediately.
+    ///
+    /// Assertions with a custom message aren't recognized, use [`retry_if`](Retry::retry_if) for them.
+    pub fn only_assertions(self) -> Retry<'a> {
+        self.retry_if(is_assertion_failure)
+    }
+
+    /// Notify `observer` about the tries, in addition to the global observers (see [`add_global_observer`](crate::add_global_observer)).
+    pub fn observer<O>(mut self, observer: O) -> Retry<'a>
+    where
+        O: RetryObserver + 'static,
+    {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    /// Require the assertions to pass on `consecutive_successes` consecutive tries, instead of a single one.
+    ///
+    /// Eventually consistent systems sometimes flap, and a single lucky pass hides bugs.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # fn replica_state() -> u64 { 42 }
+    /// # fn primary_state() -> u64 { 42 }
+    /// repeated_assert::Retry::new()
+    ///     .consecutive_successes(3)
+    ///     .run(|| {
+    ///         assert_eq!(replica_state(), primary_state());
+    ///     });
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `consecutive_successes` is 0.
+    pub fn consecutive_successes(mut self, consecutive_successes: usize) -> Retry<'a> {
+        assert!(
+            consecutive_successes > 0,
+            "at least one successful try is required"
+        );
+        self.consecutive_successes = consecutive_successes;
+        self
+    }
+
+    /// Fail if the assertions pass, but later than `max_latency` after the first try.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use std::{collections::HashMap, time::Duration};
+    /// # let cache: HashMap<&str, String> = HashMap::new();
+    /// repeated_assert::Retry::new()
+    ///     .timeout(Duration::from_secs(5))
+    ///     .max_latency(Duration::from_secs(2))
+    ///     .run(|| {
+    ///         assert_eq!(cache.get("key"), None);
+    ///     });
+    /// ```
+    pub fn max_latency(mut self, max_latency: Duration) -> Retry<'a> {
+        self.max_latency = Some(max_latency);
+        self
+    }
+
+    /// Execute `on_final_failure` after the last try failed, before the assertions panic.
+    ///
+    /// Useful to print diagnostics (e.g. queue contents, process lists or log tails) exactly when they are needed for debugging.
+    /// If the hook panics, its panic is reported and the failed assertions panic as usual.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # fn dump_db_state() {}
+    /// # fn count_rows() -> usize { 10 }
+    /// repeated_assert::Retry::new()
+    ///     .on_final_failure(|| {
+    ///         dump_db_state();
+    ///     })
+    ///     .run(|| {
+    ///         assert_eq!(count_rows(), 10);
+    ///     });
+    /// ```
+    pub fn on_final_failure<F>(mut self, on_final_failure: F) -> Retry<'a>
+    where
+        F: FnOnce() + Send + 'a,
+    {
+        self.final_failure = Some(Box::new(on_final_failure));
+        self
+    }
+
+    /// Put `message` in front of the panic message if the assertions fail, to give the failure some context.
+    ///
+    /// Also included in the [`RetryError`] returned by [`try_run`](Retry::try_run).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # struct Queue;
+    /// # impl Queue {
+    /// #     fn name(&self) -> &str { "jobs" }
+    /// #     fn is_empty(&self) -> bool { true }
+    /// # }
+    /// # let queue = Queue;
+    /// repeated_assert::Retry::new()
+    ///     .message(format!("queue {} never drained", queue.name()))
+    ///     .run(|| {
+    ///         assert!(queue.is_empty());
+    ///     });
+    /// ```
+    pub fn message<M: Into<String>>(mut self, message: M) -> Retry<'a> {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Capture the output written to stdout and stderr during every try, and only print the output of the last try
+    /// (or all tries, see [`Replay`]) if the assertions fail.
+    ///
+    /// The output is captured by redirecting the file descriptors of the process, so it includes the output of child processes.
+    /// `print!` is already captured by the test harness of the standard library, unless it's run with `--nocapture`
+    /// (or a custom harness like the [`harness`](crate::harness) module is used).
+    /// Output of other threads during a try is captured as well, and only one thread can capture at a time.
+    ///
+    /// Only applies to the blocking functions.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use repeated_assert::Replay;
+    /// # fn noisy_health_check() -> bool {
+    /// #     println!("checking health");
+    /// #     true
+    /// # }
+    /// repeated_assert::Retry::new()
+    ///     .capture_output(Replay::LastTry)
+    ///     .run(|| {
+    ///         assert!(noisy_health_check());
+    ///     });
+    /// ```
+    #[cfg(feature = "capture")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "capture")))]
+    pub fn capture_output(mut self, replay: Replay) -> Retry<'a> {
+        self.capture = Some(Capture::new(replay));
+        self
+    }
+
+    /// Re-check the assertions as soon as a [`Notifier`](crate::Notifier) of `trigger` is notified, instead of waiting out the full delay.
+    ///
+    /// Only applies to the blocking functions, see [`Trigger`].
+    pub fn trigger(mut self, trigger: Trigger) -> Retry<'a> {
+        self.trigger = Some(trigger);
+        self
+    }
+
+    /// Include the backtrace of the last ignored panic before the final try in the panic message if all tries failed.
+    ///
+    /// Helps if the final failure differs from the repeated intermediate one.
+    pub fn capture_backtrace(mut self) -> Retry<'a> {
+        self.capture_backtrace = true;
+        self
+    }
+
+    /// Write a JSON report if all tries failed, containing the retry strategy and the timestamp, duration and panic message of every try.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use repeated_assert::ReportOutput;
+    /// # use std::path::Path;
+    /// repeated_assert::Retry::new()
+    ///     .failure_report(ReportOutput::File("target/repeated-assert-failures.json".into()))
+    ///     .run(|| {
+    ///         assert!(Path::new("should_appear_soon.txt").exists());
+    ///     });
+    /// ```
+    pub fn failure_report(mut self, output: ReportOutput) -> Retry<'a> {
+        self.failure_report = Some(output);
+        self
+    }
+
+    /// Execute `catch` after `repetitions` failed tries in order to trigger an alternate strategy,
+    /// see [`with_catch`](crate::with_catch).
+    ///
+    /// Multiple catch blocks can be registered, e.g. to escalate the strategy after more failed tries.
+    /// Catch blocks with the same `repetitions` are executed in the order they were registered.
+    ///
+    /// The catch block has to be `Send`, because the futures of the async run methods hold the `Retry`.
+    /// [`with_catch`](crate::with_catch) also accepts catch blocks borrowing thread-local state (e.g. a `Cell`).
+    pub fn catch_after<C, T>(self, repetitions: usize, catch: C) -> Retry<'a>
+    where
+        C: FnOnce() -> T + Send + 'a,
+        T: Into<CatchAction>,
+    {
+        self.catch_after_with_context(repetitions, move |_| catch())
+    }
+
+    /// Execute `catch` after `repetitions` failed tries, passing information about the failed tries.
+    ///
+    /// The catch block can return a [`CatchAction`] to adjust or abort the remaining tries.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use repeated_assert::CatchAction;
+    /// # use std::path::Path;
+    /// repeated_assert::Retry::new()
+    ///     .catch_after_with_context(5, |context| {
+    ///         println!("service stuck after {:?}: {:?}", context.elapsed(), context.message());
+    ///         // restart service
+    ///     })
+    ///     .catch_after_with_context(10, |_| {
+    ///         CatchAction::Abort("service didn't recover".to_string())
+    ///     })
+    ///     .run(|| {
+    ///         assert!(Path::new("should_appear_soon.txt").exists());
+    ///     });
+    /// ```
+    pub fn catch_after_with_context<C, T>(mut self, repetitions: usize, catch: C) -> Retry<'a>
+    where
+        C: FnOnce(&CatchContext) -> T + Send + 'a,
+        T: Into<CatchAction>,
+    {
+        self.catches.push((
+            repetitions,
+            Catch::Sync(Box::new(move |context| catch(context).into())),
+        ));
+        self
+    }
+
+    /// Execute `catch` after every `every` failed tries, e.g. to re-poke an unreliable service.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `every` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use std::path::Path;
+    /// repeated_assert::Retry::new()
+    ///     .repetitions(20)
+    ///     .catch_every(5, |_| {
+    ///         // poke message broker
+    ///     })
+    ///     .run(|| {
+    ///         assert!(Path::new("should_appear_soon.txt").exists());
+    ///     });
+    /// ```
+    pub fn catch_every<C, T>(mut self, every: usize, mut catch: C) -> Retry<'a>
+    where
+        C: FnMut(&CatchContext) -> T + Send + 'a,
+        T: Into<CatchAction>,
+    {
+        assert!(every > 0, "catch interval must be greater than zero");
+        self.periodic_catches
+            .push((every, Box::new(move |context| catch(context).into())));
+        self
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn location(mut self, location: &'static Location<'static>) -> Retry<'a> {
+        self.location = location;
+        self
+    }
+
+    /// Execute the async function `catch` after `repetitions` failed tries in order to trigger an alternate strategy.
+    ///
+    /// Async catch blocks can be used with all run methods. The sync methods (e.g. [`run`](Retry::run)) block the current thread
+    /// until the catch block completes, using [`futures::executor::block_on`], so it must not rely on runtime specific
+    /// functionality like the timers of tokio. Use a sync catch block with a runtime handle in that case.
+    /// Likewise sync catch blocks can be used with the async run methods.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use std::path::Path;
+    /// # async fn restart_service() {}
+    /// repeated_assert::Retry::new()
+    ///     .catch_after_async(5, || async {
+    ///         restart_service().await;
+    ///     })
+    ///     .run(|| {
+    ///         assert!(Path::new("should_appear_soon.txt").exists());
+    ///     });
+    /// ```
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub fn catch_after_async<C, G, T>(mut self, repetitions: usize, catch: C) -> Retry<'a>
+    where
+        C: FnOnce() -> G + Send + 'a,
+        G: std::future::Future<Output = T> + Send + 'a,
+        T: Into<CatchAction> + 'a,
+    {
+        use futures::future::FutureExt;
+
+        self.catches.push((
+            repetitions,
+            Catch::Async(Box::new(move |_| catch().map(Into::into).boxed())),
+        ));
+        self
+    }
+
+    /// Run the provided function `assert` with the configured retry strategy.
+    ///
+    /// Panics (including failed assertions) will be caught until the last try, which reports them like [`that`](crate::that).
+    ///
+    /// # Info
+    ///
+    /// See [`that`](crate::that).
+    #[track_caller]
+    pub fn run<A, R>(self, mut assert: A) -> R
+    where
+        A: FnMut() -> R,
+    {
+        self.run_with_context(|_| assert())
+    }
+
+    /// Run the provided function `assert` with the configured retry strategy, passing information about the current try.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use std::{path::Path, time::Duration};
+    /// repeated_assert::Retry::new()
+    ///     .timeout(Duration::from_secs(5))
+    ///     .run_with_context(|context| {
+    ///         println!("try {} after {:?}", context.attempt(), context.elapsed());
+    ///         assert!(Path::new("should_appear_soon.txt").exists());
+    ///     });
+    /// ```
+    ///
+    /// # Info
+    ///
+    /// See [`that`](crate::that).
+    #[track_caller]
+    pub fn run_with_context<A, R>(self, assert: A) -> R
+    where
+        A: FnMut(&Context) -> R,
+    {
+        self.run_with_local_catch(&mut no_catch(), assert)
+    }
+
+    /// Run the provided function `assert` with the configured retry strategy,
+    /// executing `catch` after `repetitions` failed tries like a catch block of [`catch_after`](Retry::catch_after).
+    #[track_caller]
+    pub(crate) fn run_with_catch<A, C, R>(self, repetitions: usize, catch: C, mut assert: A) -> R
+    where
+        A: FnMut() -> R,
+        C: FnOnce(),
+    {
+        self.run_with_local_catch(&mut Some((repetitions, catch)), |_: &Context| assert())
+    }
+
+    #[track_caller]
+    fn run_with_local_catch<A, C, R>(mut self, local_catch: &mut LocalCatch<C>, mut assert: A) -> R
+    where
+        A: FnMut(&Context) -> R,
+        C: FnOnce(),
+    {
+        let start = self.time().now();
+        self.report_last_try = true;
+
+        match self.retry(start, &mut assert, local_catch) {
+            Ok(value) => value,
+            Err((tries, payload)) => self.fail(payload, tries, self.time().elapsed(start)),
+        }
+    }
+
+    /// Run the provided function `assert` with the configured retry strategy, returning statistics about the tries alongside the value.
+    ///
+    /// # Info
+    ///
+    /// See [`that_with_report`](crate::that_with_report).
+    #[track_caller]
+    pub fn run_with_report<A, R>(mut self, assert: A) -> (R, Report)
+    where
+        A: FnMut() -> R,
+    {
+        let recorder = Recorder::new();
+        self.observers.push(Arc::new(recorder.clone()));
+        let value = self.run(assert);
+        (value, recorder.take())
+    }
+
+    /// Run the provided function `assert` on every try of the configured retry strategy, requiring it to pass every time.
+    ///
+    /// Panics as soon as a try fails. Returns the value of the last try.
+    ///
+    /// # Info
+    ///
+    /// See [`consistently`](crate::consistently).
+    #[track_caller]
+    pub fn run_consistently<A, R>(mut self, mut assert: A) -> R
+    where
+        A: FnMut() -> R,
+    {
+        let start = self.time().now();
+        let mut delays = self.delays();
+        let mut tries = 0;
+
+        // add current thread to ignore list
+        let ignore_guard = IgnoreGuard::new();
+
+        let result = loop {
+            tries += 1;
+            // stop as soon as the assertions fail
+            let attempt_start = self.time().now();
+            // forget the locations of earlier panics
+            hook::take_panic_location();
+            let value = match panic::catch_unwind(panic::AssertUnwindSafe(&mut assert)) {
+                Ok(value) => value,
+                Err(payload) => {
+                    self.panic_location = hook::take_panic_location();
+                    let panic_message = panic_message(&*payload);
+                    self.observe(|observer| {
+                        observer.on_attempt(&Attempt::new(
+                            tries,
+                            self.time().elapsed(attempt_start),
+                            false,
+                            Some(&panic_message),
+                        ))
+                    });
+                    break Err(payload);
+                }
+            };
+            self.passed(tries, attempt_start);
+            match delays.next() {
+                // sleep until the next try
+                Some(delay) => self.sleep(delay),
+                None => break Ok(value),
+            }
+        };
+
+        // remove current thread from ignore list
+        drop(ignore_guard);
+
+        match result {
+            Ok(value) => value,
+            Err(payload) => self.fail_with(
+                payload,
+                format_args!(
+                    "stopped holding on try {} after {:?}",
+                    tries,
+                    self.time().elapsed(start)
+                ),
+            ),
+        }
+    }
+
+    /// Run the provided function `assert` on every try of the configured retry strategy, requiring it to pass at least `successes` times.
+    ///
+    /// All tries are executed, even if enough of them passed. Returns the ratio of passed tries.
+    ///
+    /// # Info
+    ///
+    /// See [`at_least`](crate::at_least).
+    #[track_caller]
+    pub fn run_at_least<A, R>(mut self, successes: usize, assert: A) -> f64
+    where
+        A: FnMut() -> R,
+    {
+        let report = self.sample_tries(assert);
+        if report.successes() < successes {
+            self.fail_with(
+                Box::new(format!(
+                    "assertions passed on {} of {} tries, but {} are required",
+                    report.successes(),
+                    report.attempts(),
+                    successes
+                )),
+                format_args!(
+                    "failed after {} tries in {:?}",
+                    report.attempts(),
+                    report.elapsed()
+                ),
+            )
+        }
+        report.pass_rate()
+    }
+
+    /// Run the provided function `assert` on every try of the configured retry strategy, measuring the pass rate without panicking.
+    ///
+    /// # Info
+    ///
+    /// See [`sample`](crate::sample).
+    pub fn sample<A, R>(mut self, assert: A) -> SampleReport
+    where
+        A: FnMut() -> R,
+    {
+        let report = self.sample_tries(assert);
+        // forget the location of the last failure
+        hook::take_panic_location();
+        report
+    }
+
+    /// Run all tries, regardless of their outcome.
+    fn sample_tries<A, R>(&mut self, mut assert: A) -> SampleReport
+    where
+        A: FnMut() -> R,
+    {
+        // sampling runs the number of tries passed in code
+        self.exact_repetitions = self.repetitions.is_some();
+        let start = self.time().now();
+        let mut delays = self.delays();
+        let mut tries = Vec::new();
+
+        // add current thread to ignore list
+        let _ignore_guard = IgnoreGuard::new();
+
+        loop {
+            let attempt_start = self.time().now();
+            let result = panic::catch_unwind(panic::AssertUnwindSafe(&mut assert));
+            tries.push(AttemptReport::new(
+                tries.len() + 1,
+                attempt_start.duration_since(start),
+                self.time().elapsed(attempt_start),
+                result.err().map(|payload| panic_message(&*payload)),
+            ));
+            match delays.next() {
+                // sleep until the next try
+                Some(delay) => self.sleep(delay),
+                None => return SampleReport::new(self.time().elapsed(start), tries),
+            }
+        }
+    }
+
+    /// Run the provided function `assert` with the configured retry strategy until it passes,
+    /// then keep running it every [`delay`](Retry::delay) for the whole `window`, requiring it to pass every time.
+    ///
+    /// Returns the value of the last try.
+    ///
+    /// The window keeps the [`jitter`](Retry::jitter), [`spin_first`](Retry::spin_first), [`adaptive_delay`](Retry::adaptive_delay)
+    /// and [`trigger`](Retry::trigger) of the delay, but no backoff or custom [`schedule`](Retry::schedule),
+    /// and none of the settings for failed tries, like catch blocks.
+    ///
+    /// # Info
+    ///
+    /// See [`eventually_then_always`](crate::eventually_then_always).
+    #[track_caller]
+    pub fn run_then_always<A, R>(self, window: Duration, mut assert: A) -> R
+    where
+        A: FnMut() -> R,
+    {
+        let always = self.window(window);
+        self.run(&mut assert);
+        always.run_consistently(assert)
+    }
+
+    /// Retry strategy running the assertions every delay for the whole `window`, see [`run_then_always`](Retry::run_then_always).
+    fn window(&self, window: Duration) -> Retry<'a> {
+        let mut always = Retry::new().timeout(window).fixed_delay(self.delay);
+        always.name = self.name.clone();
+        always.message = self.message.clone();
+        always.location = self.location;
+        always.jitter = self.jitter;
+        always.spins = self.spins;
+        always.adaptive_delay = self.adaptive_delay;
+        always.trigger = self.trigger.as_ref().map(Trigger::share);
+        always.clock = self.clock.clone();
+        #[cfg(feature = "async")]
+        {
+            always.sleeper = self.sleeper.clone();
+        }
+        always.observers = self.observers.clone();
+        always.capture_backtrace = self.capture_backtrace;
+        always
+    }
+
+    /// Run the provided function `assert` with the configured retry strategy, returning the time until it passed alongside the value.
+    ///
+    /// # Info
+    ///
+    /// See [`that_timed`](crate::that_timed).
+    #[track_caller]
+    pub fn run_timed<A, R>(self, assert: A) -> (R, Duration)
+    where
+        A: FnMut() -> R,
+    {
+        let (value, report) = self.run_with_report(assert);
+        (value, report.elapsed())
+    }
+
+    /// Run the provided function `assert` with the configured retry strategy, passing `state` which is kept between tries.
+    ///
+    /// Returns the state after the successful try.
+    ///
+    /// # Info
+    ///
+    /// See [`that_with_state`](crate::that_with_state).
+    #[track_caller]
+    pub fn run_with_state<T, A>(self, state: T, mut assert: A) -> T
+    where
+        A: FnMut(&mut T),
+    {
+        let mut state = state;
+        self.run(|| assert(&mut state));
+        state
+    }
+
+    /// Run the provided function `assert` with the configured retry strategy, stopping early if it returns [`ControlFlow::Break`].
+    ///
+    /// # Info
+    ///
+    /// See [`that_abortable`](crate::that_abortable).
+    #[track_caller]
+    pub fn run_abortable<A, B, R>(self, assert: A) -> Result<R, B>
+    where
+        A: FnMut() -> ControlFlow<B, R>,
+    {
+        match self.run(assert) {
+            ControlFlow::Continue(value) => Ok(value),
+            ControlFlow::Break(error) => Err(error),
+        }
+    }
+
+    /// Call the provided function `poll` with the configured retry strategy until it returns `Some` value.
+    ///
+    /// # Info
+    ///
+    /// See [`poll_until`](crate::poll_until).
+    #[track_caller]
+    pub fn poll<P, T>(self, mut poll: P) -> T
+    where
+        P: FnMut() -> Option<T>,
+    {
+        self.run(|| poll().expect(POLL_NONE_MESSAGE))
+    }
+
+    /// Call the provided function `poll` with the configured retry strategy until it returns `Some` value, without panicking.
+    ///
+    /// # Info
+    ///
+    /// See [`try_poll_until`](crate::try_poll_until).
+    pub fn try_poll<P, T>(self, mut poll: P) -> Result<T, RetryError>
+    where
+        P: FnMut() -> Option<T>,
+    {
+        self.try_run(|| poll().expect(POLL_NONE_MESSAGE))
+    }
+
+    /// Call the provided function `operation` with the configured retry strategy until it returns `Ok`.
+    ///
+    /// Returns the first `Ok` value, or the error of the last try.
+    ///
+    /// # Info
+    ///
+    /// See [`retry_result`](crate::retry_result).
+    pub fn run_result<O, T, E>(mut self, mut operation: O) -> Result<T, E>
+    where
+        O: FnMut() -> Result<T, E>,
+    {
+        #[cfg(feature = "tracing")]
+        let _span = self.span().entered();
+
+        let start = self.time().now();
+        let mut tries = 0;
+        let mut delays = self.delays();
+        let mut last_error = None;
+
+        loop {
+            // execute catch blocks, which might adjust the remaining tries
+            let delay = match self.catch(tries, start, None, &mut delays, &mut no_catch()) {
+                Ok(()) => delays.next(),
+                Err(_) => match last_error.take() {
+                    Some(error) => return Err(self.operation_failed(tries, start, error)),
+                    None => None,
+                },
+            };
+            tries += 1;
+            // return if operation succeeded
+            let attempt_start = self.time().now();
+            let error = match operation() {
+                Ok(value) => return Ok(self.succeeded(tries, start, attempt_start, value)),
+                Err(error) => error,
+            };
+            self.observe(|observer| {
+                observer.on_attempt(&Attempt::new(
+                    tries,
+                    self.time().elapsed(attempt_start),
+                    false,
+                    None,
+                ))
+            });
+            match delay {
+                Some(delay) if !self.strict => {
+                    last_error = Some(error);
+                    // sleep until the next try
+                    self.sleep(delay);
+                }
+                _ => return Err(self.operation_failed(tries, start, error)),
+            }
+        }
+    }
+
+    /// Run the provided function `assert` with the configured retry strategy, without panicking.
+    ///
+    /// # Info
+    ///
+    /// See [`try_that`](crate::try_that).
+    pub fn try_run<A, R>(mut self, mut assert: A) -> Result<R, RetryError>
+    where
+        A: FnMut() -> R,
+    {
+        let start = self.time().now();
+
+        self.retry(start, &mut |_: &Context| assert(), &mut no_catch())
+            .map_err(|(tries, payload)| {
+                RetryError::new(
+                    tries,
+                    self.time().elapsed(start),
+                    self.failure_message(&*payload),
+                    self.panic_location.take(),
+                )
+            })
+    }
+
+    /// Run the provided function `assert` with the configured retry strategy, executing every try on a helper thread.
+    ///
+    /// If a try doesn't finish within the [`attempt_timeout`](Retry::attempt_timeout), it is counted as failed and the helper thread is abandoned.
+    /// This prevents blocking functions (e.g. a hanging `recv()`) from stalling the retry loop.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use std::{sync::{mpsc, Mutex}, time::Duration};
+    /// # let (tx, rx) = mpsc::channel();
+    /// # tx.send("ready").unwrap();
+    /// # let rx = Mutex::new(rx);
+    /// repeated_assert::Retry::new()
+    ///     .attempt_timeout(Duration::from_secs(1))
+    ///     .run_threaded(move || {
+    ///         assert_eq!(rx.lock().unwrap().recv().unwrap(), "ready");
+    ///     });
+    /// ```
+    ///
+    /// # Info
+    ///
+    /// Abandoned helper threads keep running in the background until `assert` returns.
+    #[track_caller]
+    pub fn run_threaded<A, R>(self, assert: A) -> R
+    where
+        A: Fn() -> R + Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        let assert = Arc::new(assert);
+        let attempt_timeout = self.attempt_timeout.map(config::scaled);
+        self.run(move || run_on_helper_thread(&assert, attempt_timeout))
+    }
+
+    /// Run the provided async function `assert` with the configured retry strategy.
+    ///
+    /// # Info
+    ///
+    /// See [`run`](Retry::run).
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn run_async<A, F, R>(self, mut assert: A) -> R
+    where
+        A: FnMut() -> F,
+        F: std::future::Future<Output = R>,
+    {
+        self.run_with_context_async(|_| assert()).await
+    }
+
+    /// Run the provided async function `assert` with the configured retry strategy, passing information about the current try.
+    ///
+    /// The [`Context`] is passed by value, so it can be moved into the returned future.
+    ///
+    /// # Info
+    ///
+    /// See [`run_with_context`](Retry::run_with_context).
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn run_with_context_async<A, F, R>(self, assert: A) -> R
+    where
+        A: FnMut(Context) -> F,
+        F: std::future::Future<Output = R>,
+    {
+        self.run_with_local_catch_async(&mut no_catch_async(), assert)
+            .await
+    }
+
+    /// Run the provided async function `assert` with the configured retry strategy,
+    /// executing the async function `catch` after `repetitions` failed tries like a catch block of [`catch_after_async`](Retry::catch_after_async).
+    #[cfg(feature = "async")]
+    pub(crate) async fn run_with_catch_async<A, F, C, G, R>(
+        self,
+        repetitions: usize,
+        catch: C,
+        mut assert: A,
+    ) -> R
+    where
+        A: FnMut() -> F,
+        F: std::future::Future<Output = R>,
+        C: FnOnce() -> G,
+        G: std::future::Future<Output = ()>,
+    {
+        self.run_with_local_catch_async(&mut Some((repetitions, catch)), |_| assert())
+            .await
+    }
+
+    #[cfg(feature = "async")]
+    async fn run_with_local_catch_async<A, F, C, G, R>(
+        mut self,
+        local_catch: &mut LocalCatch<C>,
+        mut assert: A,
+    ) -> R
+    where
+        A: FnMut(Context) -> F,
+        F: std::future::Future<Output = R>,
+        C: FnOnce() -> G,
+        G: std::future::Future<Output = ()>,
+    {
+        let sleeper = self.async_sleeper();
+        let start = self.time().now();
+        self.report_last_try = true;
+
+        match self
+            .retry_async(start, &mut assert, &sleeper, local_catch)
+            .await
+        {
+            Ok(value) => value,
+            Err((tries, payload)) => self.fail(payload, tries, self.time().elapsed(start)),
+        }
+    }
+
+    /// Call the provided async function `operation` with the configured retry strategy until it returns `Ok`.
+    ///
+    /// # Info
+    ///
+    /// See [`run_result`](Retry::run_result).
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn run_result_async<O, F, T, E>(mut self, mut operation: O) -> Result<T, E>
+    where
+        O: FnMut() -> F,
+        F: std::future::Future<Output = Result<T, E>>,
+    {
+        let sleeper = self.async_sleeper();
+        let start = self.time().now();
+        let mut tries = 0;
+        let mut delays = self.delays();
+        let mut last_error = None;
+
+        #[cfg(feature = "tracing")]
+        let span = self.span();
+
+        let retry = async move {
+            loop {
+                // execute catch blocks, which might adjust the remaining tries
+                let delay = match self
+                    .catch_async(tries, start, None, &mut delays, &mut no_catch_async())
+                    .await
+                {
+                    Ok(()) => delays.next(),
+                    Err(_) => match last_error.take() {
+                        Some(error) => return Err(self.operation_failed(tries, start, error)),
+                        None => None,
+                    },
+                };
+                tries += 1;
+                // return if operation succeeded
+                let attempt_start = self.time().now();
+                let error = match operation().await {
+                    Ok(value) => return Ok(self.succeeded(tries, start, attempt_start, value)),
+                    Err(error) => error,
+                };
+                self.observe(|observer| {
+                    observer.on_attempt(&Attempt::new(
+                        tries,
+                        self.time().elapsed(attempt_start),
+                        false,
+                        None,
+                    ))
+                });
+                match delay {
+                    Some(delay) if !self.strict => {
+                        last_error = Some(error);
+                        // sleep until the next try
+                        self.sleep_async(delay, &sleeper).await;
+                    }
+                    _ => return Err(self.operation_failed(tries, start, error)),
+                }
+            }
+        };
+
+        #[cfg(feature = "tracing")]
+        let retry = tracing::Instrument::instrument(retry, span);
+
+        retry.await
+    }
+
+    /// Run the provided async function `assert` with the configured retry strategy, without panicking.
+    ///
+    /// # Info
+    ///
+    /// See [`try_that`](crate::try_that).
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub async fn try_run_async<A, F, R>(mut self, mut assert: A) -> Result<R, RetryError>
+    where
+        A: FnMut() -> F,
+        F: std::future::Future<Output = R>,
+    {
+        let sleeper = self.async_sleeper();
+        let start = self.time().now();
+
+        self.retry_async(
+            start,
+            &mut |_: Context| assert(),
+            &sleeper,
+            &mut no_catch_async(),
+        )
+        .await
+        .map_err(|(tries, payload)| {
+            RetryError::new(
+                tries,
+                self.time().elapsed(start),
+                self.failure_message(&*payload),
+                self.panic_location.take(),
+            )
+        })
+    }
+
+    /// Run all tries, returning the number of tries and the panic payload of the last try if none succeeded.
+    fn retry<A, C, R>(
+        &mut self,
+        start: Instant,
+        assert: &mut A,
+        local_catch: &mut LocalCatch<C>,
+    ) -> Result<R, Failure>
+    where
+        A: FnMut(&Context) -> R,
+        C: FnOnce(),
+    {
+        #[cfg(feature = "tracing")]
+        let _span = self.span().entered();
+
+        let mut tries = 0;
+        let mut successes = 0;
+        let mut message = None;
+        let mut delays = self.delays();
+        let _backtrace_guard = self.capture_backtrace.then(BacktraceGuard::new);
+
+        // add current thread to ignore list
+        let mut ignore_guard = Some(IgnoreGuard::new());
+
+        loop {
+            // execute catch blocks, which might adjust the remaining tries
+            if let Err(abort) =
+                self.catch(tries, start, message.as_deref(), &mut delays, local_catch)
+            {
+                return Err(self.failed(tries, start, Box::new(abort)));
+            }
+            let remaining = delays.remaining();
+            let delay = delays.next();
+            tries += 1;
+            // run assertions, catching panics
+            let context = Context::new(
+                tries,
+                if delay.is_some() { remaining } else { Some(0) },
+                start,
+                self.shared_clock(),
+            );
+            let attempt_start = self.time().now();
+            // forget the locations of earlier panics
+            hook::take_panic_location();
+            // the panic of the last try is reported by the panic hook where it happens, like without re-trying
+            let reported = delay.is_none() && self.report_last_try;
+            if reported {
+                // remove current thread from ignore list
+                ignore_guard = None;
+            }
+            let result = self.run_try(tries, || {
+                panic::catch_unwind(panic::AssertUnwindSafe(|| assert(&context)))
+            });
+            // return if assertions succeeded or failed permanently
+            let payload = match result {
+                Ok(value) => {
+                    successes += 1;
+                    if successes >= self.consecutive_successes {
+                        if let Some(late) = self.too_late(start) {
+                            return Err(self.failed(tries, start, late));
+                        }
+                        return Ok(self.succeeded(tries, start, attempt_start, value));
+                    }
+                    self.passed(tries, attempt_start);
+                    match delay {
+                        Some(delay) => {
+                            // sleep until the next try
+                            self.sleep(delay);
+                            continue;
+                        }
+                        None => return Err(self.failed(tries, start, self.unstable(successes))),
+                    }
+                }
+                Err(payload) => {
+                    successes = 0;
+                    payload
+                }
+            };
+            let location = hook::take_panic_location();
+            let panic_message = panic_message(&*payload);
+            self.observe(|observer| {
+                observer.on_attempt(&Attempt::new(
+                    tries,
+                    self.time().elapsed(attempt_start),
+                    false,
+                    Some(&panic_message),
+                ))
+            });
+            self.record_message(&panic_message);
+            let backtrace = hook::take_panic_backtrace();
+            // strict mode doesn't re-try, to reveal assertions that only pass because of re-trying
+            match delay {
+                Some(delay) if !self.strict && self.should_retry(&*payload) => {
+                    self.backtrace = backtrace.map(|backtrace| (tries, backtrace));
+                    message = Some(panic_message);
+                    // sleep until the next try
+                    self.sleep(delay);
+                }
+                _ => {
+                    drop(ignore_guard);
+                    self.panic_location = location;
+                    self.last_try_reported = reported;
+                    return Err(self.failed(tries, start, payload));
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    async fn retry_async<A, F, C, G, R>(
+        &mut self,
+        start: Instant,
+        assert: &mut A,
+        sleeper: &Arc<dyn Sleeper>,
+        local_catch: &mut LocalCatch<C>,
+    ) -> Result<R, Failure>
+    where
+        A: FnMut(Context) -> F,
+        F: std::future::Future<Output = R>,
+        C: FnOnce() -> G,
+        G: std::future::Future<Output = ()>,
+    {
+        use futures::future::FutureExt;
+
+        let mut tries = 0;
+        let mut successes = 0;
+        let mut message = None;
+        let mut delays = self.delays();
+
+        #[cfg(feature = "tracing")]
+        let span = self.span();
+
+        let retry = async move {
+            let _backtrace_guard = self.capture_backtrace.then(BacktraceGuard::new);
+            loop {
+                // execute catch blocks, which might adjust the remaining tries
+                if let Err(abort) = self
+                    .catch_async(tries, start, message.as_deref(), &mut delays, local_catch)
+                    .await
+                {
+                    return Err(self.failed(tries, start, Box::new(abort)));
+                }
+                let remaining = delays.remaining();
+                let delay = delays.next();
+                tries += 1;
+                // run assertions, catching panics
+                let context = Context::new(
+                    tries,
+                    if delay.is_some() { remaining } else { Some(0) },
+                    start,
+                    self.shared_clock(),
+                );
+                let attempt_start = self.time().now();
+                // forget the locations of earlier panics
+                hook::take_panic_location();
+                let attempt = attempt_async(assert(context), self.attempt_timeout, sleeper);
+                // the panic of the last try is reported by the panic hook where it happens, like without re-trying
+                let reported = delay.is_none() && self.report_last_try;
+                let result = if reported {
+                    panic::AssertUnwindSafe(attempt).catch_unwind().await
+                } else {
+                    panic::AssertUnwindSafe(ignore_panics(attempt))
+                        .catch_unwind()
+                        .await
+                };
+                // return if assertions succeeded or failed permanently
+                let payload = match result {
+                    Ok(value) => {
+                        successes += 1;
+                        if successes >= self.consecutive_successes {
+                            if let Some(late) = self.too_late(start) {
+                                return Err(self.failed(tries, start, late));
+                            }
+                            return Ok(self.succeeded(tries, start, attempt_start, value));
+                        }
+                        self.passed(tries, attempt_start);
+                        match delay {
+                            Some(delay) => {
+                                // sleep until the next try
+                                self.sleep_async(delay, sleeper).await;
+                                continue;
+                            }
+                            None => {
+                                return Err(self.failed(tries, start, self.unstable(successes)))
+                            }
+                        }
+                    }
+                    Err(payload) => {
+                        successes = 0;
+                        payload
+                    }
+                };
+                // the panic was caught in the same poll, so its location is stored on the current thread
+                let location = hook::take_panic_location();
+                let panic_message = panic_message(&*payload);
+                self.observe(|observer| {
+                    observer.on_attempt(&Attempt::new(
+                        tries,
+                        self.time().elapsed(attempt_start),
+                        false,
+                        Some(&panic_message),
+                    ))
+                });
+                self.record_message(&panic_message);
+                let backtrace = hook::take_panic_backtrace();
+                match delay {
+                    Some(delay) if !self.strict && self.should_retry(&*payload) => {
+                        self.backtrace = backtrace.map(|backtrace| (tries, backtrace));
+                        message = Some(panic_message);
+                        // sleep until the next try
+                        self.sleep_async(delay, sleeper).await;
+                    }
+                    _ => {
+                        self.panic_location = location;
+                        self.last_try_reported = reported;
+                        return Err(self.failed(tries, start, payload));
+                    }
+                }
+            }
+        };
+
+        #[cfg(feature = "tracing")]
+        let retry = tracing::Instrument::instrument(retry, span);
+
+        retry.await
+    }
+
+    #[cfg(feature = "tracing")]
+    fn span(&self) -> tracing::Span {
+        tracing::info_span!(
+            "repeated_assert",
+            name = self.name.as_deref(),
+            repetitions = ?self.repetitions,
+            delay = ?self.delay,
+            timeout = ?self.timeout,
+            location = %self.location,
+            attempts = tracing::field::Empty,
+            outcome = tracing::field::Empty,
+        )
+    }
+
+    /// Notify the observers about the successful try and record it in the flakiness registry.
+    fn succeeded<R>(&self, tries: usize, start: Instant, attempt_start: Instant, value: R) -> R {
+        flakiness::record(self.location, self.name.as_deref(), tries, true);
+        self.observe(|observer| {
+            observer.on_attempt(&Attempt::new(
+                tries,
+                self.time().elapsed(attempt_start),
+                true,
+                None,
+            ));
+            observer.on_success(tries, self.time().elapsed(start));
+        });
+        value
+    }
+
+    /// Notify the observers about a try that passed, but not often enough in a row.
+    fn passed(&self, tries: usize, attempt_start: Instant) {
+        self.observe(|observer| {
+            observer.on_attempt(&Attempt::new(
+                tries,
+                self.time().elapsed(attempt_start),
+                true,
+                None,
+            ))
+        });
+    }
+
+    /// Panic payload if the assertions passed later than the maximum latency.
+    fn too_late(&self, start: Instant) -> Option<Box<dyn Any + Send>> {
+        let max_latency = config::scaled(self.max_latency?);
+        let elapsed = self.time().elapsed(start);
+        (elapsed > max_latency).then(|| {
+            Box::new(format!(
+                "assertions passed after {:?}, but the maximum latency is {:?}",
+                elapsed, max_latency
+            )) as Box<dyn Any + Send>
+        })
+    }
+
+    /// Panic payload if the last tries passed, but not often enough in a row.
+    fn unstable(&self, successes: usize) -> Box<dyn Any + Send> {
+        Box::new(format!(
+            "assertions passed on {} consecutive tries, but {} are required",
+            successes, self.consecutive_successes
+        ))
+    }
+
+    /// Run a single try, capturing its output if requested.
+    #[cfg_attr(not(feature = "capture"), allow(unused_variables))]
+    fn run_try<F, T>(&mut self, tries: usize, run: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        #[cfg(feature = "capture")]
+        if let Some(capture) = &mut self.capture {
+            return capture.run(tries, run);
+        }
+        run()
+    }
+
+    /// Replay the captured output, execute the final failure hook, notify the observers about the failed tries and record them in the flakiness registry.
+    fn failed(&mut self, tries: usize, start: Instant, payload: Box<dyn Any + Send>) -> Failure {
+        #[cfg(feature = "capture")]
+        if let Some(capture) = &mut self.capture {
+            capture.replay();
+        }
+        self.final_failure();
+        flakiness::record(self.location, self.name.as_deref(), tries, false);
+        let message = panic_message(&*payload);
+        self.observe(|observer| {
+            observer.on_failure(tries, self.time().elapsed(start), Some(&message))
+        });
+        (tries, payload)
+    }
+
+    /// Execute the final failure hook, notify the observers about the failed tries of an operation and record them in the flakiness registry.
+    fn operation_failed<E>(&mut self, tries: usize, start: Instant, error: E) -> E {
+        self.final_failure();
+        flakiness::record(self.location, self.name.as_deref(), tries, false);
+        self.observe(|observer| observer.on_failure(tries, self.time().elapsed(start), None));
+        error
+    }
+
+    /// Execute the final failure hook.
+    ///
+    /// Panics of the hook are reported, but don't replace the failure of the tries.
+    fn final_failure(&mut self) {
+        if let Some(final_failure) = self.final_failure.take() {
+            let _report_guard = ReportGuard::new();
+            let _ = panic::catch_unwind(panic::AssertUnwindSafe(final_failure));
+        }
+    }
+
+    /// Call `notify` for the observers of this retry and the global observers.
+    fn observe<N>(&self, notify: N)
+    where
+        N: Fn(&dyn RetryObserver),
+    {
+        // panics of observers aren't failed tries
+        let _report_guard = ReportGuard::new();
+        for observer in &self.observers {
+            notify(&**observer);
+        }
+        observer::global_observers(|observer| notify(observer));
+        #[cfg(feature = "tracing")]
+        notify(&observer::Tracing);
+        notify(&observer::Diagnostics::new(self.name.as_deref()));
+    }
+
+    fn delays(&mut self) -> Delays<'a> {
+        self.apply_env_overrides(config::env_overrides());
+
+        // record the tries for the failure report
+        if let Some(output) = self.failure_report.take() {
+            let schedule = ScheduleInfo {
+                repetitions: self.repetitions,
+                delay: self.delay,
+                backoff: self.backoff,
+                max_delay: self.max_delay,
+                timeout: self.timeout,
+                custom: self.schedule.is_some(),
+            };
+            let report = FailureReport::new(output, schedule, self.name.clone(), self.location);
+            self.observers.push(Arc::new(report));
+        }
+
+        // scale the deadline relative to now
+        let now = self.time().now();
+        // deadlines too far in the future to be represented don't limit the tries
+        let deadline = self.deadline.and_then(|deadline| {
+            now.checked_add(config::scaled(deadline.saturating_duration_since(now)))
+        });
+        let timeout = self
+            .timeout
+            .and_then(|timeout| now.checked_add(config::scaled(timeout)));
+        let deadline = match (timeout, deadline) {
+            (Some(timeout), Some(deadline)) => Some(deadline.min(timeout)),
+            (timeout, deadline) => timeout.or(deadline),
+        };
+        let deadline = match (deadline, self.shared_deadline) {
+            (Some(deadline), Some(shared_deadline)) => Some(deadline.min(shared_deadline)),
+            (deadline, shared_deadline) => deadline.or(shared_deadline),
+        };
+        self.stop_at = deadline;
+        self.last_wake = Some(now);
+        self.attempt_timeout = self.attempt_timeout.map(config::scaled);
+
+        let mut delays = match self.schedule.take() {
+            Some(schedule) => schedule,
+            None => {
+                let limited = self.timeout.is_some() || self.deadline.is_some();
+                let repetitions = self
+                    .repetitions
+                    .unwrap_or(if limited || deadline.is_some() {
+                        usize::MAX
+                    } else {
+                        DEFAULT_REPETITIONS
+                    });
+                let mut delays = schedule::exponential(self.delay, self.backoff);
+                if let Some(max_delay) = self.max_delay {
+                    delays = delays.max_delay(max_delay);
+                }
+                Box::new(delays.take(repetitions.saturating_sub(1)))
+            }
+        };
+        if let Some(jitter) = self.jitter {
+            delays = Box::new(schedule::jitter(delays, jitter));
+        }
+        if self.spins > 0 {
+            delays = Box::new(schedule::spin_then(self.spins, delays));
+        }
+        if config::time_scale() != 1.0 {
+            delays = Box::new(delays.map(config::scaled));
+        }
+        if let Some(deadline) = deadline {
+            delays = Box::new(schedule::deadline_with_clock(
+                deadline,
+                delays,
+                self.shared_clock(),
+            ));
+        }
+        Delays::new(delays)
+    }
+
+    /// Override the repetitions and delay with the values of the environment variables, if set.
+    ///
+    /// The repetitions of retries limited by a timeout or deadline only are kept unlimited, exact repetitions are kept as they are.
+    fn apply_env_overrides(&mut self, overrides: &EnvOverrides) {
+        if let Some(repetitions) = overrides.repetitions {
+            let limited =
+                self.timeout.is_some() || self.deadline.is_some() || self.shared_deadline.is_some();
+            if !self.exact_repetitions && (self.repetitions.is_some() || !limited) {
+                self.repetitions = Some(repetitions);
+            }
+        }
+        if let Some(delay) = overrides.delay {
+            self.delay = delay;
+        }
+    }
+
+    /// Count the panic message of a failed try, merging it with the previous one if they are equal.
+    fn record_message(&mut self, message: &str) {
+        match self.messages.last_mut() {
+            Some((last, count)) if last == message => *count += 1,
+            _ => self.messages.push((message.to_string(), 1)),
+        }
+    }
+
+    fn should_retry(&self, payload: &(dyn Any + Send)) -> bool {
+        match &self.retry_if {
+            Some(retry_if) => retry_if(&panic_message(payload)),
+            None => true,
+        }
+    }
+
+    /// Execute the catch blocks due after `tries` failed tries, returning the message if one of them aborted.
+    fn catch<C>(
+        &mut self,
+        tries: usize,
+        start: Instant,
+        message: Option<&str>,
+        delays: &mut Delays<'a>,
+        local_catch: &mut LocalCatch<C>,
+    ) -> Result<(), String>
+    where
+        C: FnOnce(),
+    {
+        self.catch_periodic(tries, start, message, delays)?;
+        let local_catch = take_local_catch(local_catch, tries);
+        let catches = self.take_catches(tries, local_catch.is_some());
+        if let Some(local_catch) = local_catch {
+            // panics of catch blocks aren't failed tries
+            let _report_guard = ReportGuard::new();
+            local_catch();
+        }
+        for catch in catches {
+            // panics of catch blocks aren't failed tries
+            let _report_guard = ReportGuard::new();
+            let action = match catch {
+                Catch::Sync(catch) => catch(&CatchContext::new(
+                    tries,
+                    self.time().elapsed(start),
+                    message,
+                )),
+                #[cfg(feature = "async")]
+                Catch::Async(catch) => futures::executor::block_on(catch(CatchContext::new(
+                    tries,
+                    self.time().elapsed(start),
+                    message,
+                ))),
+            };
+            delays.apply(action)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    async fn catch_async<C, G>(
+        &mut self,
+        tries: usize,
+        start: Instant,
+        message: Option<&str>,
+        delays: &mut Delays<'a>,
+        local_catch: &mut LocalCatch<C>,
+    ) -> Result<(), String>
+    where
+        C: FnOnce() -> G,
+        G: std::future::Future<Output = ()>,
+    {
+        self.catch_periodic(tries, start, message, delays)?;
+        let local_catch = take_local_catch(local_catch, tries);
+        let catches = self.take_catches(tries, local_catch.is_some());
+        if let Some(local_catch) = local_catch {
+            // panics of catch blocks aren't failed tries
+            hook::report_panics(local_catch()).await;
+        }
+        for catch in catches {
+            // panics of catch blocks aren't failed tries
+            let action = match catch {
+                Catch::Sync(catch) => {
+                    let _report_guard = ReportGuard::new();
+                    catch(&CatchContext::new(
+                        tries,
+                        self.time().elapsed(start),
+                        message,
+                    ))
+                }
+                Catch::Async(catch) => {
+                    hook::report_panics(catch(CatchContext::new(
+                        tries,
+                        self.time().elapsed(start),
+                        message,
+                    )))
+                    .await
+                }
+            };
+            delays.apply(action)?;
+        }
+        Ok(())
+    }
+
+    fn catch_periodic(
+        &mut self,
+        tries: usize,
+        start: Instant,
+        message: Option<&str>,
+        delays: &mut Delays<'a>,
+    ) -> Result<(), String> {
+        if tries == 0 {
+            return Ok(());
+        }
+        let elapsed = self.time().elapsed(start);
+        for (every, catch) in &mut self.periodic_catches {
+            if tries % *every == 0 {
+                crate::print_catch_message(self.name.as_deref());
+                let _report_guard = ReportGuard::new();
+                delays.apply(catch(&CatchContext::new(tries, elapsed, message)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the catch blocks due after `tries` failed tries, announcing them if they or the `local_catch` are due.
+    fn take_catches(&mut self, tries: usize, local_catch: bool) -> Vec<Catch<'a>> {
+        if !local_catch
+            && !self
+                .catches
+                .iter()
+                .any(|(repetitions, _)| *repetitions == tries)
+        {
+            return Vec::new();
+        }
+        crate::print_catch_message(self.name.as_deref());
+        let (due, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.catches)
+            .into_iter()
+            .partition(|(repetitions, _)| *repetitions == tries);
+        self.catches = pending;
+        due.into_iter().map(|(_, catch)| catch).collect()
+    }
+
+    /// Message of the last try, preceded by the custom [`message`](Retry::message).
+    fn failure_message(&self, payload: &(dyn Any + Send)) -> String {
+        match &self.message {
+            Some(message) => format!("{}\n{}", message, panic_message(payload)),
+            None => panic_message(payload),
+        }
+    }
+
+    /// Panic with the message of the last try, enriched with information about the retries.
+    #[track_caller]
+    fn fail(&self, payload: Box<dyn Any + Send>, tries: usize, elapsed: Duration) -> ! {
+        self.fail_with(
+            payload,
+            format_args!("failed after {} tries in {:?}", tries, elapsed),
+        )
+    }
+
+    /// Panic with the message of the last try, enriched with the `outcome` and information about the retries.
+    ///
+    /// Other payloads than strings are resumed as they are, after emitting the information about the retries.
+    /// If the panic hook reported the panic of the last try already, the information about the retries is emitted
+    /// and the enriched message is resumed without reporting it again.
+    #[track_caller]
+    fn fail_with(&self, payload: Box<dyn Any + Send>, outcome: fmt::Arguments<'_>) -> ! {
+        let string = payload.is::<&str>() || payload.is::<String>();
+        if !string || self.last_try_reported {
+            let message = self.failure_context(self.message.as_deref().unwrap_or(""), outcome);
+            // panics of nested retries are ignored by the outer retry
+            if !string || !hook::thread_ignored() {
+                output::emit(
+                    output::Level::Error,
+                    format_args!("{}", message.trim_start()),
+                );
+            }
+            if !string {
+                panic::resume_unwind(payload);
+            }
+            panic::resume_unwind(Box::new(
+                self.failure_context(&self.failure_message(&*payload), outcome),
+            ));
+        }
+        panic!(
+            "{}",
+            self.failure_context(&self.failure_message(&*payload), outcome)
+        )
+    }
+
+    /// Append the `outcome` and information about the retries to `message`.
+    fn failure_context(&self, message: &str, outcome: fmt::Arguments<'_>) -> String {
+        let mut message = message.to_string();
+        match &self.name {
+            Some(name) => message.push_str(&format!("\nrepeated-assert: `{}` {}", name, outcome)),
+            None => message.push_str(&format!("\nrepeated-assert: {}", outcome)),
+        }
+        // summarize the distinct messages to show if the state was progressing or stuck
+        if self.messages.len() > 1 {
+            let observed: Vec<_> = self
+                .messages
+                .iter()
+                .map(|(message, count)| {
+                    let message: Vec<_> = message.lines().map(str::trim).collect();
+                    match count {
+                        1 => format!("`{}` once", message.join(" ")),
+                        count => format!("`{}` {} times", message.join(" "), count),
+                    }
+                })
+                .collect();
+            message.push_str(&format!("\n  observed {}", observed.join(", then ")));
+        }
+        if let Some(location) = &self.panic_location {
+            message.push_str(&format!("\n  assertion at {}", location));
+        }
+        message.push_str(&format!("\n  called at {}", self.location));
+        if self.strict {
+            message.push_str("\n  re-trying is disabled by strict mode");
+        }
+        if let Some((tries, backtrace)) = &self.backtrace {
+            message.push_str(&format!("\n  backtrace of try {}:\n{}", tries, backtrace));
+        }
+        message
+    }
+}
+
+/// Check if `message` is the panic message of a failed assertion without custom message.
+fn is_assertion_failure(message: &str) -> bool {
+    message.starts_with("assertion failed") || message.starts_with("assertion `")
+}
+
+/// Delays between tries, which can be adjusted by catch blocks.
+struct Delays<'a> {
+    schedule: Schedule<'a>,
+    delay: Option<Duration>,
+    extra: usize,
+    last: Duration,
+}
+
+impl<'a> Delays<'a> {
+    fn new(schedule: Schedule<'a>) -> Delays<'a> {
+        Delays {
+            schedule,
+            delay: None,
+            extra: 0,
+            last: DEFAULT_DELAY,
+        }
+    }
+
+    /// Number of remaining tries after the next one, if known.
+    fn remaining(&self) -> Option<usize> {
+        match self.schedule.size_hint() {
+            (lower, Some(upper)) if lower == upper => lower.checked_add(self.extra),
+            _ => None,
+        }
+    }
+
+    /// Apply the action returned by a catch block, returning the message if it aborted.
+    fn apply(&mut self, action: CatchAction) -> Result<(), String> {
+        match action {
+            CatchAction::Continue => {}
+            CatchAction::Abort(message) => return Err(message),
+            CatchAction::Extend(tries) => self.extra = self.extra.saturating_add(tries),
+            CatchAction::Delay(delay) => self.delay = Some(delay),
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for Delays<'_> {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        let delay = match self.schedule.next() {
+            Some(delay) => delay,
+            None if self.extra > 0 => {
+                self.extra -= 1;
+                self.last
+            }
+            None => return None,
+        };
+        self.last = delay;
+        Some(self.delay.unwrap_or(delay))
+    }
+}
+
+/// No catch block of the free functions.
+fn no_catch() -> LocalCatch<fn()> {
+    None
+}
+
+/// No async catch block of the free functions.
+#[cfg(feature = "async")]
+fn no_catch_async() -> LocalCatch<fn() -> futures::future::Ready<()>> {
+    None
+}
+
+/// Remove the `local_catch` if it's due after `tries` failed tries.
+fn take_local_catch<C>(local_catch: &mut LocalCatch<C>, tries: usize) -> Option<C> {
+    match local_catch {
+        Some((repetitions, _)) if *repetitions == tries => {
+            local_catch.take().map(|(_, catch)| catch)
+        }
+        _ => None,
+    }
+}
+
+/// Run `assert` on a new thread, waiting up to `timeout` for it to finish.
+fn run_on_helper_thread<A, R>(assert: &Arc<A>, timeout: Option<Duration>) -> R
+where
+    A: Fn() -> R + Send + Sync + 'static,
+    R: Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    let assert = assert.clone();
+    let ignored = hook::thread_ignored();
+
+    thread::spawn(move || {
+        // ignore panics like on the current thread
+        let _ignore_guard = ignored.then(IgnoreGuard::new);
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(&*assert));
+        let _ = sender.send(result);
+    });
+
+    let result = match timeout {
+        Some(timeout) => match receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(_) => panic!("attempt timed out after {:?}", timeout),
+        },
+        None => receiver.recv().expect("receive result from helper thread"),
+    };
+
+    match result {
+        Ok(value) => value,
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
+
+/// Await `future`, panicking if it doesn't finish within `timeout`.
+#[cfg(feature = "async")]
+async fn attempt_async<F, R>(future: F, timeout: Option<Duration>, sleeper: &Arc<dyn Sleeper>) -> R
+where
+    F: std::future::Future<Output = R>,
+{
+    use futures::future::{self, Either};
+
+    match timeout {
+        Some(timeout) => {
+            let future = std::pin::pin!(future);
+            match future::select(future, sleeper.sleep(timeout)).await {
+                Either::Left((value, _)) => value,
+                Either::Right(_) => panic!("attempt timed out after {:?}", timeout),
+            }
+        }
+        None => future.await,
+    }
+}
+
+impl Default for Retry<'_> {
+    #[track_caller]
+    fn default() -> Self {
+        Retry::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        clock::MockClock, config::EnvOverrides, error::panic_message, hook, schedule::Jitter,
+        Attempt, CatchAction, Policy, Retry, RetryObserver, Trigger,
+    };
+    use std::panic;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    static STEP_MS: u64 = 100;
+
+    fn spawn_thread(x: Arc<Mutex<i32>>) {
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(10 * STEP_MS));
+            if let Ok(mut x) = x.lock() {
+                *x += 1;
+            }
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "backoff factor must be finite and not negative, got NaN")]
+    fn backoff_nan() {
+        let _ = Retry::new().backoff(f64::NAN);
+    }
+
+    #[test]
+    #[should_panic(expected = "backoff factor must be finite and not negative, got -1")]
+    fn policy_backoff_invalid() {
+        let _ = Retry::with_policy(Policy {
+            backoff: -1.0,
+            ..Policy::default()
+        });
+    }
+
+    #[test]
+    fn backoff_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        Retry::new()
+            .repetitions(6)
+            .delay(Duration::from_millis(STEP_MS))
+            .backoff(2.0)
+            .run(|| {
+                assert!(*x.lock().unwrap() > 0);
+            });
+    }
+
+    #[test]
+    #[should_panic(expected = "assertion failed: *x.lock().unwrap() > 0")]
+    fn timeout_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        Retry::new()
+            .delay(Duration::from_millis(STEP_MS))
+            .timeout(Duration::from_millis(3 * STEP_MS))
+            .run(|| {
+                assert!(*x.lock().unwrap() > 0);
+            });
+    }
+
+    #[test]
+    fn context_timeout() {
+        let remaining = Mutex::new(Vec::new());
+
+        Retry::new()
+            .delay(Duration::from_millis(STEP_MS))
+            .timeout(Duration::from_millis(3 * STEP_MS))
+            .run_with_context(|context| {
+                remaining.lock().unwrap().push(context.remaining());
+                assert!(context.is_last());
+                assert!(context.elapsed() >= Duration::from_millis(3 * STEP_MS));
+            });
+
+        let remaining = remaining.into_inner().unwrap();
+        assert!(remaining[..remaining.len() - 1].iter().all(Option::is_none));
+        assert_eq!(remaining.last(), Some(&Some(0)));
+    }
+
+    #[test]
+    fn only_assertions_success() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        Retry::new()
+            .repetitions(5)
+            .delay(Duration::from_millis(5 * STEP_MS))
+            .only_assertions()
+            .run(|| {
+                assert!(*x.lock().unwrap() > 0);
+            });
+    }
+
+    #[test]
+    #[should_panic(expected = "repeated-assert: failed after 1 tries")]
+    fn only_assertions_failure() {
+        Retry::new()
+            .delay(Duration::from_millis(STEP_MS))
+            .only_assertions()
+            .run(|| {
+                let values: Vec<i32> = Vec::new();
+                values.first().unwrap();
+            });
+    }
+
+    #[test]
+    fn retry_if_failure() {
+        let mut tries = 0;
+
+        let error = Retry::new()
+            .delay(Duration::from_millis(STEP_MS))
+            .retry_if(|message| message.starts_with("transient"))
+            .try_run(|| {
+                tries += 1;
+                if tries < 3 {
+                    panic!("transient error");
+                }
+                panic!("permanent error");
+            })
+            .unwrap_err();
+
+        assert_eq!(error.attempts(), 3);
+        assert_eq!(error.message(), "permanent error");
+    }
+
+    #[test]
+    fn catch_success() {
+        let x = Arc::new(Mutex::new(-1_000));
+
+        spawn_thread(x.clone());
+
+        Retry::new()
+            .repetitions(10)
+            .delay(Duration::from_millis(5 * STEP_MS))
+            .catch_after(5, || {
+                *x.lock().unwrap() = 0;
+            })
+            .run(|| {
+                assert!(*x.lock().unwrap() > 0);
+            });
+    }
+
+    #[test]
+    fn catch_context_success() {
+        let x = Arc::new(Mutex::new(-1_000));
+
+        spawn_thread(x.clone());
+
+        Retry::new()
+            .repetitions(10)
+            .delay(Duration::from_millis(STEP_MS))
+            .catch_after_with_context(3, |context| {
+                assert_eq!(context.attempts(), 3);
+                assert!(context.elapsed() >= Duration::from_millis(2 * STEP_MS));
+                assert_eq!(
+                    context.message(),
+                    Some("assertion failed: *x.lock().unwrap() > 0")
+                );
+                *x.lock().unwrap() = 1;
+            })
+            .run(|| {
+                assert!(*x.lock().unwrap() > 0);
+            });
+    }
+
+    #[test]
+    fn staged_catch_success() {
+        let x = Arc::new(Mutex::new(-1_000));
+        let stages = Mutex::new(Vec::new());
+
+        spawn_thread(x.clone());
+
+        Retry::new()
+            .repetitions(10)
+            .delay(Duration::from_millis(STEP_MS))
+            .catch_after(5, || {
+                stages.lock().unwrap().push(5);
+                *x.lock().unwrap() = 1;
+            })
+            .catch_after(2, || {
+                stages.lock().unwrap().push(2);
+            })
+            .run(|| {
+                assert!(*x.lock().unwrap() > 0);
+            });
+
+        assert_eq!(*stages.lock().unwrap(), vec![2, 5]);
+    }
+
+    #[test]
+    fn periodic_catch_failure() {
+        let attempts = Mutex::new(Vec::new());
+
+        let result = Retry::new()
+            .repetitions(10)
+            .delay(Duration::from_millis(STEP_MS / 10))
+            .catch_every(3, |context| {
+                attempts.lock().unwrap().push(context.attempts());
+            })
+            .try_run(|| {
+                panic!("not ready");
+            });
+
+        assert!(result.is_err());
+        assert_eq!(*attempts.lock().unwrap(), vec![3, 6, 9]);
+    }
+
+    #[test]
+    #[should_panic(expected = "service is broken\nrepeated-assert: failed after 3 tries")]
+    fn catch_abort_failure() {
+        Retry::new()
+            .repetitions(10)
+            .delay(Duration::from_millis(STEP_MS))
+            .catch_after(3, || CatchAction::Abort("service is broken".to_string()))
+            .run(|| {
+                panic!("not ready");
+            });
+    }
+
+    #[test]
+    fn catch_panic() {
+        let reported = Mutex::new(None);
+
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            Retry::new()
+                .repetitions(3)
+                .delay(Duration::from_millis(10))
+                .catch_after(1, || -> () {
+                    *reported.lock().unwrap() = Some(!hook::thread_ignored());
+                    panic!("catch block failed");
+                })
+                .run(|| {
+                    panic!("not ready");
+                })
+        }));
+
+        // the panic of the catch block is reported and propagated
+        assert!(result.is_err());
+        assert_eq!(*reported.lock().unwrap(), Some(true));
+    }
+
+    #[test]
+    fn abort_location() {
+        let payload = panic::catch_unwind(|| {
+            Retry::new()
+                .repetitions(10)
+                .delay(Duration::from_millis(10))
+                .catch_after(2, || CatchAction::Abort("service is broken".to_string()))
+                .run(|| {
+                    panic!("not ready");
+                })
+        })
+        .unwrap_err();
+
+        // the abort isn't a panic of a try, so no assertion location is reported
+        let message = panic_message(&*payload);
+        assert!(message.starts_with("service is broken\nrepeated-assert: failed after 2 tries"));
+        assert!(!message.contains("assertion at"));
+    }
+
+    #[test]
+    fn max_latency_location() {
+        let mut tries = 0;
+
+        let payload = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            Retry::new()
+                .repetitions(5)
+                .delay(Duration::from_millis(STEP_MS))
+                .max_latency(Duration::from_millis(STEP_MS))
+                .run(|| {
+                    tries += 1;
+                    assert!(tries > 3);
+                })
+        }))
+        .unwrap_err();
+
+        // the earlier failed tries aren't the reported failure
+        let message = panic_message(&*payload);
+        assert!(message.contains("but the maximum latency is 100ms"));
+        assert!(!message.contains("assertion at"));
+    }
+
+    #[test]
+    fn panic_location() {
+        let error = Retry::new()
+            .repetitions(2)
+            .delay(Duration::from_millis(10))
+            .try_run(|| {
+                panic!("not ready");
+            })
+            .unwrap_err();
+
+        assert!(error.location().unwrap().starts_with(file!()));
+    }
+
+    #[test]
+    fn env_repetitions() {
+        let overrides = EnvOverrides {
+            repetitions: Some(50),
+            ..EnvOverrides::default()
+        };
+
+        let mut retry = Retry::new().repetitions(3);
+        retry.apply_env_overrides(&overrides);
+        assert_eq!(retry.repetitions, Some(50));
+
+        // retries limited by a timeout only stay unlimited
+        let mut retry = Retry::new().timeout(Duration::from_secs(1));
+        retry.apply_env_overrides(&overrides);
+        assert_eq!(retry.repetitions, None);
+    }
+
+    #[test]
+    fn env_repetitions_exact() {
+        let overrides = EnvOverrides {
+            repetitions: Some(50),
+            ..EnvOverrides::default()
+        };
+
+        let mut retry = Retry::new().exact_repetitions(1);
+        retry.apply_env_overrides(&overrides);
+        assert_eq!(retry.repetitions, Some(1));
+
+        // sampling runs the tries passed in code
+        let mut retry = Retry::new().repetitions(3).delay(Duration::from_millis(10));
+        retry.sample_tries(|| ());
+        retry.apply_env_overrides(&overrides);
+        assert_eq!(retry.repetitions, Some(3));
+    }
+
+    #[test]
+    fn last_try_reported() {
+        let mut ignored = Vec::new();
+
+        let payload = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            Retry::new()
+                .repetitions(3)
+                .delay(Duration::from_millis(10))
+                .run(|| {
+                    ignored.push(hook::thread_ignored());
+                    panic!("not ready");
+                })
+        }))
+        .unwrap_err();
+
+        assert_eq!(ignored, [true, true, false]);
+        let message = panic_message(&*payload);
+        assert!(message.starts_with("not ready"));
+        assert!(message.contains("repeated-assert: failed after 3 tries"));
+    }
+
+    #[test]
+    fn last_try_ignored() {
+        let mut ignored = Vec::new();
+
+        Retry::new()
+            .repetitions(2)
+            .delay(Duration::from_millis(10))
+            .try_run(|| {
+                ignored.push(hook::thread_ignored());
+                panic!("not ready");
+            })
+            .unwrap_err();
+
+        assert_eq!(ignored, [true, true]);
+    }
+
+    #[test]
+    fn policy_success() {
+        let mut tries = 0;
+
+        Retry::with_policy(Policy {
+            repetitions: Some(3),
+            delay: Duration::from_millis(10),
+            ..Policy::default()
+        })
+        .run(|| {
+            tries += 1;
+            assert_eq!(tries, 3);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "repeated-assert: failed after 2 tries")]
+    fn policy_failure() {
+        Retry::with_policy(Policy {
+            repetitions: Some(2),
+            delay: Duration::from_millis(10),
+            ..Policy::default()
+        })
+        .run(|| {
+            panic!("not ready");
+        });
+    }
+
+    fn strict(retry: Retry) -> Retry {
+        Retry {
+            strict: true,
+            ..retry
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "re-trying is disabled by strict mode")]
+    fn strict_failure() {
+        let mut tries = 0;
+
+        strict(Retry::new().repetitions(3).delay(Duration::from_millis(10))).run(|| {
+            tries += 1;
+            assert!(tries > 1);
+        });
+    }
+
+    #[test]
+    fn strict_single_try() {
+        let (tries, sleeps) = failing_tries(strict(
+            Retry::new().repetitions(3).timeout(Duration::from_secs(5)),
+        ));
+
+        assert_eq!(tries, 1);
+        assert!(sleeps.is_empty());
+    }
+
+    #[test]
+    fn strict_consistently() {
+        let mut tries = 0;
+
+        strict(Retry::new().repetitions(3).delay(Duration::from_millis(10))).run_consistently(
+            || {
+                tries += 1;
+            },
+        );
+
+        assert_eq!(tries, 3);
+    }
+
+    #[test]
+    fn strict_at_least() {
+        let mut tries = 0;
+
+        let rate = strict(Retry::new().repetitions(3).delay(Duration::from_millis(10)))
+            .run_at_least(2, || {
+                tries += 1;
+            });
+
+        assert_eq!(tries, 3);
+        assert_eq!(rate, 1.0);
+    }
+
+    #[test]
+    fn strict_consecutive_successes() {
+        let mut tries = 0;
+
+        strict(Retry::new().delay(Duration::from_millis(10)))
+            .consecutive_successes(2)
+            .run(|| {
+                tries += 1;
+            });
+
+        assert_eq!(tries, 2);
+    }
+
+    #[test]
+    fn consistently_success() {
+        let mut tries = 0;
+
+        Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(10))
+            .run_consistently(|| {
+                tries += 1;
+            });
+
+        assert_eq!(tries, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "repeated-assert: stopped holding on try 2 after")]
+    fn consistently_failure() {
+        let mut tries = 0;
+
+        Retry::new()
+            .repetitions(5)
+            .delay(Duration::from_millis(10))
+            .run_consistently(|| {
+                tries += 1;
+                assert!(tries < 2);
+            });
+    }
+
+    #[test]
+    fn consecutive_success() {
+        let mut tries = 0;
+
+        Retry::new()
+            .repetitions(10)
+            .delay(Duration::from_millis(10))
+            .consecutive_successes(3)
+            .run(|| {
+                tries += 1;
+                // flaps on the 4th try
+                assert!(tries != 1 && tries != 4);
+            });
+
+        assert_eq!(tries, 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "assertions passed on 2 consecutive tries, but 3 are required")]
+    fn consecutive_failure() {
+        let mut tries = 0;
+
+        Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(10))
+            .consecutive_successes(3)
+            .run(|| {
+                tries += 1;
+                assert!(tries > 1);
+            });
+    }
+
+    #[test]
+    fn then_always_success() {
+        let mut tries = 0;
+
+        Retry::new()
+            .repetitions(5)
+            .delay(Duration::from_millis(10))
+            .run_then_always(Duration::from_millis(50), || {
+                tries += 1;
+                assert!(tries > 2);
+            });
+
+        assert!(tries > 4);
+    }
+
+    #[test]
+    fn then_always_settings() {
+        let clock = MockClock::new();
+        let events = Arc::new(Events::default());
+        let mut tries = 0;
+
+        Retry::new()
+            .repetitions(5)
+            .delay(Duration::from_secs(1))
+            .clock(clock.clone())
+            .observer(events.clone())
+            .run_then_always(Duration::from_secs(2), || {
+                tries += 1;
+                assert!(tries > 1, "not ready");
+            });
+
+        // the window uses the clock and the observers of the retry
+        assert_eq!(clock.sleeps(), [1, 1, 1].map(Duration::from_secs).to_vec());
+        assert_eq!(
+            *events.0.lock().unwrap(),
+            vec![
+                "attempt 1 false Some(\"not ready\")",
+                "attempt 2 true None",
+                "success 2",
+                "attempt 1 true None",
+                "attempt 2 true None",
+                "attempt 3 true None",
+            ]
+        );
+    }
+
+    #[test]
+    fn then_always_schedule() {
+        let retry = Retry::new()
+            .delay(Duration::from_secs(1))
+            .backoff(2.0)
+            .jitter(Jitter::Full)
+            .spin_first(2)
+            .fixed_rate()
+            .trigger(Trigger::new());
+
+        let always = retry.window(Duration::from_secs(5));
+
+        assert_eq!(always.timeout, Some(Duration::from_secs(5)));
+        assert_eq!(always.delay, Duration::from_secs(1));
+        assert_eq!(always.backoff, 1.0);
+        assert_eq!(always.jitter, Some(Jitter::Full));
+        assert_eq!(always.spins, 2);
+        assert_eq!(always.adaptive_delay, Some(1.0));
+        assert!(always.trigger.is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "repeated-assert: stopped holding on try 3 after")]
+    fn then_always_failure() {
+        let mut tries = 0;
+
+        Retry::new()
+            .repetitions(5)
+            .delay(Duration::from_millis(10))
+            .run_then_always(Duration::from_millis(100), || {
+                tries += 1;
+                // briefly ready on the 2nd to 4th try
+                assert!((2..5).contains(&tries));
+            });
+    }
+
+    #[test]
+    fn at_least_success() {
+        let mut tries = 0;
+
+        let ratio = Retry::new()
+            .repetitions(10)
+            .delay(Duration::from_millis(10))
+            .run_at_least(8, || {
+                tries += 1;
+                assert!(tries % 5 != 0);
+            });
+
+        assert_eq!(ratio, 0.8);
+    }
+
+    #[test]
+    #[should_panic(expected = "assertions passed on 7 of 10 tries, but 8 are required")]
+    fn at_least_failure() {
+        let mut tries = 0;
+
+        Retry::new()
+            .repetitions(10)
+            .delay(Duration::from_millis(10))
+            .run_at_least(8, || {
+                tries += 1;
+                assert!(tries % 3 != 0);
+            });
+    }
+
+    #[test]
+    fn sample_success() {
+        let mut tries = 0;
+
+        let report = Retry::new()
+            .repetitions(4)
+            .delay(Duration::from_millis(10))
+            .sample(|| {
+                tries += 1;
+                assert!(tries % 2 == 0, "odd try");
+            });
+
+        assert_eq!(report.attempts(), 4);
+        assert_eq!(report.successes(), 2);
+        assert_eq!(report.failures(), 2);
+        assert_eq!(report.pass_rate(), 0.5);
+        assert_eq!(
+            report.failure_messages().collect::<Vec<_>>(),
+            vec!["odd try", "odd try"]
+        );
+        assert!(report.tries()[3].started() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn timed_success() {
+        let mut tries = 0;
+
+        let (value, elapsed) = Retry::new()
+            .repetitions(5)
+            .delay(Duration::from_millis(STEP_MS))
+            .max_latency(Duration::from_millis(5 * STEP_MS))
+            .run_timed(|| {
+                tries += 1;
+                assert!(tries > 2);
+                tries
+            });
+
+        assert_eq!(value, 3);
+        assert!(elapsed >= Duration::from_millis(2 * STEP_MS));
+        assert!(elapsed < Duration::from_millis(5 * STEP_MS));
+    }
+
+    #[test]
+    #[should_panic(expected = "but the maximum latency is 200ms")]
+    fn max_latency_failure() {
+        let mut tries = 0;
+
+        Retry::new()
+            .repetitions(5)
+            .delay(Duration::from_millis(STEP_MS))
+            .max_latency(Duration::from_millis(2 * STEP_MS))
+            .run(|| {
+                tries += 1;
+                assert!(tries > 3);
+            });
+    }
+
+    #[test]
+    fn unlimited_durations() {
+        let tries = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        Retry::new()
+            .timeout(Duration::MAX)
+            .deadline(Instant::now() + Duration::from_secs(3600))
+            .attempt_timeout(Duration::MAX)
+            .delay(Duration::from_millis(10))
+            .run_threaded(move || {
+                assert!(tries.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1 >= 3);
+            });
+
+        Retry::new().timeout(Duration::MAX).run(|| ());
+        Retry::new()
+            .attempt_timeout(Duration::MAX)
+            .run_threaded(|| ());
+    }
+
+    #[test]
+    fn final_failure_hook() {
+        let mut final_failures = 0;
+
+        let result = Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(10))
+            .on_final_failure(|| final_failures += 1)
+            .try_run(|| {
+                panic!("not ready");
+            });
+
+        assert!(result.is_err());
+        assert_eq!(final_failures, 1);
+    }
+
+    #[test]
+    fn final_failure_hook_panic() {
+        let mut reported = false;
+
+        let result = Retry::new()
+            .repetitions(2)
+            .delay(Duration::from_millis(10))
+            .on_final_failure(|| {
+                reported = !hook::thread_ignored();
+                panic!("hook failed");
+            })
+            .try_run(|| {
+                panic!("not ready");
+            });
+
+        // the panic of the hook is reported, the failure of the tries is kept
+        assert!(reported);
+        assert!(result.unwrap_err().to_string().contains("not ready"));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "queue never drained\nassertion `left == right` failed\n  left: 2\n right: 3\nrepeated-assert: failed after 3 tries"
+    )]
+    fn message_failure() {
+        Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(10))
+            .message("queue never drained")
+            .run(|| {
+                assert_eq!(1 + 1, 3);
+            });
+    }
+
+    #[cfg(feature = "capture")]
+    #[test]
+    fn capture_output_success() {
+        use crate::Replay;
+        use std::io::Write;
+
+        let _lock = crate::capture::tests::lock();
+        let mut tries = 0;
+
+        Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(10))
+            .capture_output(Replay::AllTries)
+            .run(|| {
+                tries += 1;
+                writeln!(std::io::stdout(), "try {}", tries).unwrap();
+                assert!(tries > 1);
+            });
+
+        assert_eq!(tries, 2);
+    }
+
+    #[cfg(feature = "capture")]
+    #[test]
+    #[should_panic(expected = "not ready")]
+    fn capture_output_failure() {
+        let _lock = crate::capture::tests::lock();
+
+        Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(10))
+            .capture_output(crate::Replay::LastTry)
+            .run(|| {
+                panic!("not ready");
+            });
+    }
+
+    #[test]
+    fn message_try_run() {
+        let error = Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(10))
+            .message(format!("queue {} never drained", "jobs"))
+            .try_run(|| {
+                panic!("not ready");
+            })
+            .unwrap_err();
+
+        assert_eq!(error.message(), "queue jobs never drained\nnot ready");
+    }
+
+    fn failing_tries(retry: Retry) -> (usize, Vec<Duration>) {
+        let clock = MockClock::new();
+        let error = retry
+            .clock(clock.clone())
+            .try_run(|| {
+                panic!("not ready");
+            })
+            .unwrap_err();
+        (error.attempts(), clock.sleeps())
+    }
+
+    #[test]
+    fn policy_backoff() {
+        let policy = Policy {
+            repetitions: Some(4),
+            delay: Duration::from_secs(1),
+            backoff: 2.0,
+            max_delay: Some(Duration::from_secs(3)),
+            ..Policy::default()
+        };
+
+        assert_eq!(
+            failing_tries(Retry::with_policy(policy)),
+            (4, [1, 2, 3].map(Duration::from_secs).to_vec())
+        );
+    }
+
+    #[test]
+    fn policy_repetitions_timeout() {
+        let policy = Policy {
+            repetitions: Some(3),
+            delay: Duration::from_millis(10),
+            ..Policy::default()
+        };
+        let clock = MockClock::new();
+        let mut tries = 0;
+
+        // the timeout set in code replaces the repetitions of the policy
+        Retry::with_policy(policy.clone())
+            .clock(clock.clone())
+            .timeout(Duration::from_millis(500))
+            .run_consistently(|| tries += 1);
+
+        assert_eq!(tries, 51);
+        assert_eq!(clock.elapsed(), Duration::from_millis(500));
+
+        // repetitions set in code are kept
+        let (tries, _) = failing_tries(
+            Retry::with_policy(policy)
+                .repetitions(3)
+                .timeout(Duration::from_secs(5)),
+        );
+        assert_eq!(tries, 3);
+    }
+
+    #[test]
+    fn policy_fixed_delay() {
+        let policy = Policy {
+            repetitions: Some(3),
+            backoff: 2.0,
+            max_delay: Some(Duration::from_millis(15)),
+            ..Policy::default()
+        };
+
+        assert_eq!(
+            failing_tries(
+                Retry::with_policy(policy)
+                    .repetitions(4)
+                    .fixed_delay(Duration::from_millis(10))
+            ),
+            (4, vec![Duration::from_millis(10); 3])
+        );
+    }
+
+    #[test]
+    fn mock_clock_timeout() {
+        let clock = MockClock::new();
+        let start = Instant::now();
+
+        let error = Retry::new()
+            .clock(clock.clone())
+            .delay(Duration::from_secs(1))
+            .timeout(Duration::from_secs(60))
+            .try_run(|| {
+                panic!("not ready");
+            })
+            .unwrap_err();
+
+        assert_eq!(error.attempts(), 61);
+        assert_eq!(error.elapsed(), Duration::from_secs(60));
+        assert_eq!(clock.elapsed(), Duration::from_secs(60));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn mock_clock_backoff() {
+        let clock = MockClock::new();
+        let mut elapsed = Vec::new();
+
+        Retry::new()
+            .clock(clock.clone())
+            .repetitions(5)
+            .delay(Duration::from_secs(1))
+            .backoff(2.0)
+            .run_with_context(|context| {
+                elapsed.push(context.elapsed().as_secs());
+                assert!(context.attempt() == 5);
+            });
+
+        assert_eq!(
+            clock.sleeps(),
+            [1, 2, 4, 8].map(Duration::from_secs).to_vec()
+        );
+        assert_eq!(elapsed, [0, 1, 3, 7, 15]);
+    }
+
+    #[test]
+    fn mock_clock_context() {
+        let clock = MockClock::new();
+
+        Retry::new()
+            .clock(clock.clone())
+            .run_with_context(|context| {
+                // the real time passing during the try doesn't count
+                thread::sleep(Duration::from_millis(20));
+                assert_eq!(context.elapsed(), Duration::ZERO);
+                clock.advance(Duration::from_secs(1));
+                assert_eq!(context.elapsed(), Duration::from_secs(1));
+            });
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn mock_clock_async() {
+        let clock = MockClock::new();
+        let tries = Mutex::new(0);
+
+        Retry::new()
+            .clock(clock.clone())
+            .sleeper(clock.clone())
+            .delay(Duration::from_secs(10))
+            .timeout(Duration::from_secs(60))
+            .run_async(|| async {
+                let tries = {
+                    let mut tries = tries.lock().unwrap();
+                    *tries += 1;
+                    *tries
+                };
+                assert!(tries == 3);
+            })
+            .await;
+
+        assert_eq!(clock.elapsed(), Duration::from_secs(20));
+    }
+
+    #[test]
+    fn spin_first_success() {
+        let clock = MockClock::new();
+        let mut tries = 0;
+
+        Retry::new()
+            .clock(clock.clone())
+            .repetitions(3)
+            .delay(Duration::from_secs(1))
+            .spin_first(5)
+            .run(|| {
+                tries += 1;
+                assert!(tries == 7);
+            });
+
+        assert_eq!(clock.sleeps(), [Duration::from_secs(1)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "failed after 8 tries")]
+    fn spin_first_failure() {
+        Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(STEP_MS / 10))
+            .spin_first(5)
+            .run(|| {
+                panic!("not ready");
+            });
+    }
+
+    #[test]
+    fn adaptive_delay() {
+        let clock = MockClock::new();
+        let durations = [100, 3000, 700].map(Duration::from_millis);
+        let mut durations = durations.iter();
+
+        let error = Retry::new()
+            .clock(clock.clone())
+            .repetitions(3)
+            .delay(Duration::from_secs(1))
+            .adaptive_delay(2.0)
+            .try_run(|| {
+                clock.advance(*durations.next().unwrap());
+                panic!("not ready");
+            })
+            .unwrap_err();
+
+        assert_eq!(error.attempts(), 3);
+        // shortened by a fast try, stretched after a slow one
+        assert_eq!(
+            clock.sleeps(),
+            [900, 3000].map(Duration::from_millis).to_vec()
+        );
+    }
+
+    #[test]
+    fn adaptive_delay_timeout() {
+        let clock = MockClock::new();
+
+        let error = Retry::new()
+            .clock(clock.clone())
+            .delay(Duration::from_secs(1))
+            .adaptive_delay(2.0)
+            .timeout(Duration::from_secs(10))
+            .try_run(|| {
+                clock.advance(Duration::from_secs(3));
+                panic!("not ready");
+            })
+            .unwrap_err();
+
+        // the second delay is cut short, so the last try starts at the timeout
+        assert_eq!(error.attempts(), 3);
+        assert_eq!(
+            clock.sleeps(),
+            [Duration::from_secs(3), Duration::from_secs(1)]
+        );
+        assert_eq!(error.elapsed(), Duration::from_secs(13));
+    }
+
+    #[test]
+    fn fixed_rate() {
+        let clock = MockClock::new();
+        let durations = [300, 700, 1500, 100].map(Duration::from_millis);
+        let mut durations = durations.iter();
+        let mut starts = Vec::new();
+
+        let error = Retry::new()
+            .clock(clock.clone())
+            .repetitions(4)
+            .delay(Duration::from_secs(1))
+            .fixed_rate()
+            .try_run(|| {
+                starts.push(clock.elapsed().as_millis());
+                clock.advance(*durations.next().unwrap());
+                panic!("not ready");
+            })
+            .unwrap_err();
+
+        assert_eq!(starts, [0, 1000, 2000, 3500]);
+        assert_eq!(error.elapsed(), Duration::from_millis(3600));
+    }
+
+    #[test]
+    fn zero_delay() {
+        let start = Instant::now();
+
+        let error = Retry::new()
+            .repetitions(10_000)
+            .delay(Duration::ZERO)
+            .try_run(|| {
+                panic!("not ready");
+            })
+            .unwrap_err();
+
+        assert_eq!(error.attempts(), 10_000);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn zero_delay_async() {
+        let start = Instant::now();
+
+        let error = Retry::new()
+            .repetitions(10_000)
+            .delay(Duration::ZERO)
+            .try_run_async(|| async {
+                panic!("not ready");
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.attempts(), 10_000);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(start_paused = true)]
+    async fn paused_time_async() {
+        let start = Instant::now();
+        let paused_start = tokio::time::Instant::now();
+
+        let error = Retry::new()
+            .delay(Duration::from_secs(1))
+            .timeout(Duration::from_secs(60))
+            .try_run_async(|| async {
+                panic!("not ready");
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.attempts(), 61);
+        assert_eq!(paused_start.elapsed(), Duration::from_secs(60));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn catch_extend_success() {
+        let mut tries = 0;
+
+        Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(STEP_MS / 10))
+            .catch_after(2, || CatchAction::Extend(5))
+            .run(|| {
+                tries += 1;
+                assert!(tries > 6);
+            });
+
+        assert_eq!(tries, 7);
+    }
+
+    #[test]
+    fn catch_delay_success() {
+        let start = Instant::now();
+
+        let error = Retry::new()
+            .repetitions(6)
+            .delay(Duration::from_millis(10 * STEP_MS))
+            .catch_after(0, || {
+                CatchAction::Delay(Duration::from_millis(STEP_MS / 10))
+            })
+            .try_run(|| {
+                panic!("not ready");
+            })
+            .unwrap_err();
+
+        assert_eq!(error.attempts(), 6);
+        assert!(start.elapsed() < Duration::from_millis(10 * STEP_MS));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn catch_async_success() {
+        let x = Arc::new(Mutex::new(-1_000));
+
+        spawn_thread(x.clone());
+
+        Retry::new()
+            .repetitions(10)
+            .delay(Duration::from_millis(5 * STEP_MS))
+            .catch_after_async(5, || async {
+                *x.lock().unwrap() = 0;
+            })
+            .run(|| {
+                assert!(*x.lock().unwrap() > 0);
+            });
+    }
+
+    #[derive(Default)]
+    struct Events(Mutex<Vec<String>>);
+
+    impl RetryObserver for Arc<Events> {
+        fn on_attempt(&self, attempt: &Attempt<'_>) {
+            self.0.lock().unwrap().push(format!(
+                "attempt {} {} {:?}",
+                attempt.number(),
+                attempt.is_success(),
+                attempt.message()
+            ));
+        }
+
+        fn on_success(&self, attempts: usize, _elapsed: Duration) {
+            self.0.lock().unwrap().push(format!("success {}", attempts));
+        }
+
+        fn on_failure(&self, attempts: usize, _elapsed: Duration, message: Option<&str>) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("failure {} {:?}", attempts, message));
+        }
+    }
+
+    #[test]
+    fn observer_success() {
+        let events = Arc::new(Events::default());
+        let mut tries = 0;
+
+        Retry::new()
+            .repetitions(5)
+            .delay(Duration::from_millis(STEP_MS / 10))
+            .observer(events.clone())
+            .run(|| {
+                tries += 1;
+                assert!(tries > 1, "not ready");
+            });
+
+        assert_eq!(
+            *events.0.lock().unwrap(),
+            vec![
+                "attempt 1 false Some(\"not ready\")",
+                "attempt 2 true None",
+                "success 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn observer_panic() {
+        struct Failing(Arc<Mutex<Option<bool>>>);
+
+        impl RetryObserver for Failing {
+            fn on_attempt(&self, _attempt: &Attempt<'_>) {
+                *self.0.lock().unwrap() = Some(!hook::thread_ignored());
+                panic!("observer failed");
+            }
+        }
+
+        let reported = Arc::new(Mutex::new(None));
+
+        let result = panic::catch_unwind(|| {
+            Retry::new()
+                .repetitions(3)
+                .delay(Duration::from_millis(10))
+                .observer(Failing(reported.clone()))
+                .run(|| ())
+        });
+
+        // the panic of the observer is reported and propagated
+        assert!(result.is_err());
+        assert_eq!(*reported.lock().unwrap(), Some(true));
+    }
+
+    #[test]
+    fn observer_failure() {
+        let events = Arc::new(Events::default());
+
+        let result = Retry::new()
+            .repetitions(2)
+            .delay(Duration::from_millis(STEP_MS / 10))
+            .observer(events.clone())
+            .try_run(|| {
+                panic!("not ready");
+            });
+
+        assert!(result.is_err());
+        assert_eq!(
+            *events.0.lock().unwrap(),
+            vec![
+                "attempt 1 false Some(\"not ready\")",
+                "attempt 2 false Some(\"not ready\")",
+                "failure 2 Some(\"not ready\")",
+            ]
+        );
+    }
+
+    #[test]
+    fn failure_message() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        let line = line!() + 1;
+        let retry = Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(STEP_MS));
+        let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            retry.run(|| {
+                assert!(*x.lock().unwrap() > 0);
+            })
+        }))
+        .unwrap_err();
+        let message = payload.downcast_ref::<String>().unwrap();
+
+        assert!(message.starts_with("assertion failed: *x.lock().unwrap() > 0\n"));
+        assert!(message.contains("failed after 3 tries"));
+        assert!(message.contains(&format!("called at {}:{}", file!(), line)));
+    }
+
+    #[test]
+    #[should_panic(expected = "repeated-assert: `service ready` failed after 3 tries")]
+    fn failure_message_named() {
+        Retry::new()
+            .name("service ready")
+            .repetitions(3)
+            .delay(Duration::from_millis(10))
+            .run(|| {
+                panic!("not ready");
+            });
+    }
+
+    #[test]
+    fn failure_message_backtrace() {
+        let payload = std::panic::catch_unwind(|| {
+            Retry::new()
+                .repetitions(3)
+                .delay(Duration::from_millis(10))
+                .capture_backtrace()
+                .run(|| {
+                    panic!("backtrace test");
+                })
+        })
+        .unwrap_err();
+        let message = payload.downcast_ref::<String>().unwrap();
+
+        assert!(message.contains("\n  backtrace of try 2:\n"));
+    }
+
+    #[test]
+    fn failure_message_observed() {
+        let mut tries = 0;
+
+        let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            Retry::new()
+                .repetitions(5)
+                .delay(Duration::from_millis(10))
+                .run(|| {
+                    tries += 1;
+                    assert_eq!(tries.min(3), 0);
+                })
+        }))
+        .unwrap_err();
+        let message = payload.downcast_ref::<String>().unwrap();
+
+        assert!(message.contains(
+            "observed `assertion `left == right` failed left: 1 right: 0` once, \
+             then `assertion `left == right` failed left: 2 right: 0` once, \
+             then `assertion `left == right` failed left: 3 right: 0` 3 times"
+        ));
+    }
+
+    #[test]
+    fn attempt_timeout_success() {
+        let tries = Arc::new(Mutex::new(0));
+
+        Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(STEP_MS))
+            .attempt_timeout(Duration::from_millis(STEP_MS))
+            .run_threaded(move || {
+                *tries.lock().unwrap() += 1;
+                // the first try blocks
+                if *tries.lock().unwrap() == 1 {
+                    thread::sleep(Duration::from_millis(100 * STEP_MS));
+                }
+            });
+    }
+
+    #[test]
+    #[should_panic(expected = "attempt timed out after 100ms")]
+    fn attempt_timeout_failure() {
+        Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(STEP_MS))
+            .attempt_timeout(Duration::from_millis(STEP_MS))
+            .run_threaded(|| {
+                thread::sleep(Duration::from_millis(100 * STEP_MS));
+            });
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn attempt_timeout_success_async() {
+        let tries = Mutex::new(0);
+
+        Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(STEP_MS))
+            .attempt_timeout(Duration::from_millis(STEP_MS))
+            .run_async(|| async {
+                *tries.lock().unwrap() += 1;
+                // the first try never finishes
+                if *tries.lock().unwrap() == 1 {
+                    futures::future::pending::<()>().await;
+                }
+            })
+            .await;
+    }
+
+    #[test]
+    fn trigger_success() {
+        let x = Arc::new(Mutex::new(0));
+        let trigger = Trigger::new();
+        let notifier = trigger.notifier();
+
+        let y = x.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(STEP_MS));
+            *y.lock().unwrap() += 1;
+            notifier.notify();
+        });
+
+        let start = Instant::now();
+        Retry::new()
+            .repetitions(2)
+            .delay(Duration::from_millis(100 * STEP_MS))
+            .trigger(trigger)
+            .run(|| {
+                assert!(*x.lock().unwrap() > 0);
+            });
+
+        // woken up by the notifier instead of waiting out the delay
+        assert!(start.elapsed() < Duration::from_millis(50 * STEP_MS));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn wake_on_notify_success_async() {
+        let x = Arc::new(Mutex::new(0));
+        let notify = Arc::new(tokio::sync::Notify::new());
+
+        let (y, producer) = (x.clone(), notify.clone());
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(STEP_MS)).await;
+            *y.lock().unwrap() += 1;
+            producer.notify_one();
+        });
+
+        let start = Instant::now();
+        Retry::new()
+            .repetitions(2)
+            .delay(Duration::from_millis(100 * STEP_MS))
+            .wake_on_notify(&notify)
+            .run_async(|| async {
+                assert!(*x.lock().unwrap() > 0);
+            })
+            .await;
+
+        // woken up by the notification instead of waiting out the delay
+        assert!(start.elapsed() < Duration::from_millis(50 * STEP_MS));
+    }
+
+    #[test]
+    fn try_run_failure() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        let error = Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(STEP_MS))
+            .try_run(|| {
+                assert!(*x.lock().unwrap() > 0);
+            })
+            .unwrap_err();
+
+        assert_eq!(error.attempts(), 3);
+        assert!(error.elapsed() >= Duration::from_millis(2 * STEP_MS));
+        assert_eq!(error.message(), "assertion failed: *x.lock().unwrap() > 0");
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn catch_success_async() {
+        let x = Arc::new(Mutex::new(-1_000));
+
+        spawn_thread(x.clone());
+
+        Retry::new()
+            .repetitions(10)
+            .delay(Duration::from_millis(5 * STEP_MS))
+            .catch_after(5, || {
+                *x.lock().unwrap() = 0;
+            })
+            .run_async(|| async {
+                assert!(*x.lock().unwrap() > 0);
+            })
+            .await;
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn context_success_async() {
+        Retry::new()
+            .repetitions(3)
+            .delay(Duration::from_millis(STEP_MS))
+            .run_with_context_async(|context| async move {
+                assert_eq!(context.attempt(), 3);
+                assert_eq!(context.remaining(), Some(0));
+            })
+            .await;
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn result_success_async() {
+        let x = Arc::new(Mutex::new(0));
+
+        spawn_thread(x.clone());
+
+        let result = Retry::new()
+            .repetitions(5)
+            .delay(Duration::from_millis(5 * STEP_MS))
+            .run_result_async(|| async {
+                let x = *x.lock().unwrap();
+                if x > 0 {
+                    Ok(x)
+                } else {
+                    Err(x)
+                }
+            })
+            .await;
+
+        assert!(result.unwrap() > 0);
+    }
+}
@@ -0,0 +1,331 @@
+//! Delay schedules
+//!
+//! A schedule is any iterator of [`Duration`]s. Every item is the delay before the next try,
+//! so a schedule with `n` items results in up to `n + 1` tries.
+//!
+//! The functions in this module create common schedules, which can be combined with the usual iterator adapters.
+//!
+//! # Examples
+//!
+//! Ramp up from 10 ms to 100 ms, then keep polling every 100 ms (re-try up to 20 times)
+//!
+//! ```rust,no_run
+//! # use std::{path::Path, time::Duration};
+//! let schedule = repeated_assert::schedule::exponential(Duration::from_millis(10), 2.0)
+//!     .max_delay(Duration::from_millis(100))
+//!     .take(20);
+//!
+//! repeated_assert::that_scheduled(schedule, || {
+//!     assert!(Path::new("should_appear_soon.txt").exists());
+//! });
+//! ```
+//!
+//! Spread the tries of tests polling the same service
+//!
+//! ```rust
+//! # use repeated_assert::schedule::Jitter;
+//! # use std::time::Duration;
+//! let schedule = repeated_assert::schedule::jitter(
+//!     repeated_assert::schedule::fixed(10, Duration::from_millis(50)),
+//!     Jitter::Full,
+//! );
+//! ```
+use crate::{
+    clock::{Clock, SystemClock},
+    Instant,
+};
+use std::{
+    cell::Cell,
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    iter,
+    sync::Arc,
+    time::Duration,
+};
+
+/// Wait `delay` between tries (re-try up to `repetitions - 1` times).
+///
+/// This is the schedule used by [`that`](crate::that).
+pub fn fixed(repetitions: usize, delay: Duration) -> iter::RepeatN<Duration> {
+    iter::repeat_n(delay, repetitions.saturating_sub(1))
+}
+
+/// Start with a delay of `initial` and multiply it by `factor` after every try.
+///
+/// The returned schedule is infinite, limit it with [`Iterator::take`].
+///
+/// # Panics
+///
+/// Panics if `factor` is negative, infinite or NaN.
+pub fn exponential(initial: Duration, factor: f64) -> Exponential {
+    assert!(
+        factor.is_finite() && factor >= 0.0,
+        "backoff factor must be finite and not negative, got {}",
+        factor
+    );
+    Exponential {
+        next: initial,
+        factor,
+        max_delay: None,
+    }
+}
+
+/// Re-try `tries` times without sleeping, then continue with `schedule`.
+///
+/// Most conditions in the same process become true within microseconds, so only the slow runs have to sleep.
+/// The tries without delay yield to other threads instead, see [`Retry::delay`](crate::Retry::delay).
+pub fn spin_then<S>(tries: usize, schedule: S) -> iter::Chain<iter::RepeatN<Duration>, S::IntoIter>
+where
+    S: IntoIterator<Item = Duration>,
+{
+    iter::repeat_n(Duration::ZERO, tries).chain(schedule)
+}
+
+/// Randomize the delays of `schedule` according to `jitter`.
+///
+/// Jitter spreads the tries of concurrently running tests, which would otherwise poll shared services in sync.
+pub fn jitter<S>(schedule: S, jitter: Jitter) -> Jittered<S::IntoIter>
+where
+    S: IntoIterator<Item = Duration>,
+{
+    Jittered {
+        schedule: schedule.into_iter(),
+        jitter,
+    }
+}
+
+/// Kind of random jitter applied to a delay, see [`jitter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jitter {
+    /// Wait a random duration between zero and the delay.
+    Full,
+    /// Wait half of the delay plus a random duration between zero and the other half.
+    Equal,
+}
+
+impl Jitter {
+    fn apply(self, delay: Duration) -> Duration {
+        match self {
+            Jitter::Full => delay.mul_f64(random()),
+            Jitter::Equal => delay / 2 + (delay / 2).mul_f64(random()),
+        }
+    }
+}
+
+/// Random number in `[0, 1)`, which is good enough to spread delays.
+///
+/// Uses a xorshift generator per thread, seeded with the random keys of [`RandomState`].
+fn random() -> f64 {
+    thread_local! {
+        // the state of xorshift must not be zero
+        static STATE: Cell<u64> = Cell::new(RandomState::new().build_hasher().finish() | 1);
+    }
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.set(x);
+        // the upper 53 bits fill the mantissa of an `f64`
+        (x >> 11) as f64 / (1_u64 << 53) as f64
+    })
+}
+
+/// Randomized delays, see [`jitter`].
+#[derive(Debug, Clone)]
+pub struct Jittered<S> {
+    schedule: S,
+    jitter: Jitter,
+}
+
+impl<S> Iterator for Jittered<S>
+where
+    S: Iterator<Item = Duration>,
+{
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        self.schedule.next().map(|delay| self.jitter.apply(delay))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.schedule.size_hint()
+    }
+}
+
+/// Stop `schedule` once `deadline` is reached.
+///
+/// The last delay is shortened, so the final try happens at the `deadline`.
+pub fn deadline<S>(deadline: Instant, schedule: S) -> Deadline<S::IntoIter>
+where
+    S: IntoIterator<Item = Duration>,
+{
+    deadline_with_clock(deadline, schedule, Arc::new(SystemClock))
+}
+
+/// Stop `schedule` once `deadline` is reached on `clock`.
+pub(crate) fn deadline_with_clock<S>(
+    deadline: Instant,
+    schedule: S,
+    clock: Arc<dyn Clock>,
+) -> Deadline<S::IntoIter>
+where
+    S: IntoIterator<Item = Duration>,
+{
+    Deadline {
+        schedule: schedule.into_iter(),
+        deadline,
+        clock,
+    }
+}
+
+/// Delays limited by a deadline, see [`deadline`](fn@deadline).
+#[derive(Clone)]
+pub struct Deadline<S> {
+    schedule: S,
+    deadline: Instant,
+    clock: Arc<dyn Clock>,
+}
+
+impl<S: fmt::Debug> fmt::Debug for Deadline<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Deadline")
+            .field("schedule", &self.schedule)
+            .field("deadline", &self.deadline)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> Iterator for Deadline<S>
+where
+    S: Iterator<Item = Duration>,
+{
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        let remaining = self.deadline.checked_duration_since(self.clock.now())?;
+        if remaining.is_zero() {
+            return None;
+        }
+        self.schedule.next().map(|delay| delay.min(remaining))
+    }
+}
+
+/// Exponentially growing delays, see [`exponential`].
+#[derive(Debug, Clone)]
+pub struct Exponential {
+    next: Duration,
+    factor: f64,
+    max_delay: Option<Duration>,
+}
+
+impl Exponential {
+    /// Limit the delay to `max_delay`.
+    pub fn max_delay(mut self, max_delay: Duration) -> Exponential {
+        self.max_delay = Some(max_delay);
+        self
+    }
+}
+
+impl Iterator for Exponential {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        let delay = match self.max_delay {
+            Some(max_delay) if self.next > max_delay => max_delay,
+            _ => self.next,
+        };
+        self.next =
+            Duration::try_from_secs_f64(delay.as_secs_f64() * self.factor).unwrap_or(Duration::MAX);
+        Some(delay)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_delays() {
+        let delays: Vec<_> = fixed(4, Duration::from_millis(10)).collect();
+        assert_eq!(delays, vec![Duration::from_millis(10); 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "backoff factor must be finite and not negative, got -1")]
+    fn exponential_negative_factor() {
+        exponential(Duration::from_millis(10), -1.0);
+    }
+
+    #[test]
+    fn exponential_delays() {
+        let delays: Vec<_> = exponential(Duration::from_millis(10), 2.0)
+            .max_delay(Duration::from_millis(50))
+            .take(5)
+            .collect();
+        assert_eq!(
+            delays,
+            vec![
+                Duration::from_millis(10),
+                Duration::from_millis(20),
+                Duration::from_millis(40),
+                Duration::from_millis(50),
+                Duration::from_millis(50),
+            ]
+        );
+    }
+
+    #[test]
+    fn deadline_delays() {
+        let start = Instant::now();
+        let mut tries = 0;
+
+        for delay in deadline(
+            start + Duration::from_millis(250),
+            fixed(100, Duration::from_millis(100)),
+        ) {
+            std::thread::sleep(delay);
+            tries += 1;
+        }
+        assert_eq!(tries, 3);
+        assert!(start.elapsed() < Duration::from_millis(300));
+
+        let delays: Vec<_> = deadline(start, fixed(100, Duration::from_millis(100))).collect();
+        assert!(delays.is_empty());
+    }
+
+    #[test]
+    fn spin_then_delays() {
+        let delays: Vec<_> = spin_then(2, exponential(Duration::from_millis(10), 2.0))
+            .take(4)
+            .collect();
+        assert_eq!(delays, [0, 0, 10, 20].map(Duration::from_millis).to_vec());
+    }
+
+    #[test]
+    fn jittered_delays() {
+        let delay = Duration::from_millis(100);
+
+        for delay_jittered in jitter(fixed(100, delay), Jitter::Full) {
+            assert!(delay_jittered <= delay);
+        }
+        for delay_jittered in jitter(fixed(100, delay), Jitter::Equal) {
+            assert!(delay_jittered >= delay / 2 && delay_jittered <= delay);
+        }
+    }
+
+    #[test]
+    fn random_numbers() {
+        let numbers: Vec<_> = (0..1000).map(|_| random()).collect();
+
+        assert!(numbers.iter().all(|number| (0.0..1.0).contains(number)));
+        // spread over the whole range
+        assert!(numbers.iter().any(|number| *number < 0.1));
+        assert!(numbers.iter().any(|number| *number > 0.9));
+    }
+}
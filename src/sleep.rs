@@ -0,0 +1,189 @@
+//! Asynchronous sleeping between tries
+//!
+//! The async functions don't depend on a specific runtime. They use a [`Sleeper`] to wait between tries,
+//! which can be set with [`Retry::sleeper`](crate::Retry::sleeper).
+//!
+//! Any function taking a [`Duration`] and returning a `Send` future implements [`Sleeper`],
+//! so the sleep functions of the runtimes can be used directly.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # async fn query_db() -> &'static str { "success" }
+//! # #[cfg(feature = "async-std")]
+//! # async fn example() {
+//! repeated_assert::Retry::new()
+//!     .sleeper(async_std::task::sleep)
+//!     .run_async(|| async {
+//!         assert_eq!(query_db().await, "success");
+//!     })
+//!     .await;
+//! # }
+//! ```
+use futures::future::{self, BoxFuture, FutureExt};
+use std::{future::Future, sync::Arc, task::Poll, time::Duration};
+
+/// Asynchronously sleep for a given duration.
+pub trait Sleeper: Send + Sync {
+    /// Return a future that completes after `duration`.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+impl<S, F> Sleeper for S
+where
+    S: Fn(Duration) -> F + Send + Sync,
+    F: Future<Output = ()> + Send + 'static,
+{
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        self(duration).boxed()
+    }
+}
+
+/// Sleep using the timer of the `futures-timer` crate, which works with any runtime.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FuturesTimer;
+
+impl Sleeper for FuturesTimer {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        futures_timer::Delay::new(duration).boxed()
+    }
+}
+
+/// Sleep using the timer of the tokio runtime.
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tokio;
+
+#[cfg(feature = "tokio")]
+impl Sleeper for Tokio {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        tokio::time::sleep(duration).boxed()
+    }
+}
+
+/// Sleep using the timer of the async-std runtime.
+#[cfg(feature = "async-std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async-std")))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsyncStd;
+
+#[cfg(feature = "async-std")]
+impl Sleeper for AsyncStd {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        async_std::task::sleep(duration).boxed()
+    }
+}
+
+/// Sleep using the timer of the smol runtime.
+#[cfg(feature = "smol")]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol")))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Smol;
+
+#[cfg(feature = "smol")]
+impl Sleeper for Smol {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        smol::Timer::after(duration).map(|_| ()).boxed()
+    }
+}
+
+/// Sleep using the timers of the JavaScript host (`setTimeout`), for `wasm32` targets.
+#[cfg(feature = "wasm")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wasm")))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Gloo;
+
+#[cfg(feature = "wasm")]
+impl Sleeper for Gloo {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        // JavaScript timers can't be sent to other threads, which is fine as long as wasm is single-threaded
+        send_wrapper::SendWrapper::new(gloo_timers::future::sleep(duration)).boxed()
+    }
+}
+
+/// Yield to the executor once, so other tasks can run before the next try.
+///
+/// Works with any runtime, unlike sleeping for a zero duration, which waits for the next timer tick.
+pub(crate) async fn yield_now() {
+    let mut yielded = false;
+    future::poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+/// The sleeper used if none is set, depending on the enabled features:
+/// [`Gloo`] for `wasm` (on `wasm32` targets), [`Tokio`] for `tokio`, [`AsyncStd`] for `async-std`,
+/// [`Smol`] for `smol` and [`FuturesTimer`] otherwise.
+pub(crate) fn default_sleeper() -> Arc<dyn Sleeper> {
+    #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+    return Arc::new(Gloo);
+    #[cfg(all(feature = "tokio", not(all(feature = "wasm", target_arch = "wasm32"))))]
+    return Arc::new(Tokio);
+    #[cfg(all(
+        feature = "async-std",
+        not(feature = "tokio"),
+        not(all(feature = "wasm", target_arch = "wasm32"))
+    ))]
+    return Arc::new(AsyncStd);
+    #[cfg(all(
+        feature = "smol",
+        not(any(feature = "tokio", feature = "async-std")),
+        not(all(feature = "wasm", target_arch = "wasm32"))
+    ))]
+    return Arc::new(Smol);
+    #[cfg(all(
+        not(any(feature = "tokio", feature = "async-std", feature = "smol")),
+        not(all(feature = "wasm", target_arch = "wasm32"))
+    ))]
+    return Arc::new(FuturesTimer);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn futures_timer() {
+        let start = Instant::now();
+        FuturesTimer.sleep(Duration::from_millis(100)).await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[cfg(feature = "async-std")]
+    #[test]
+    fn async_std() {
+        let start = Instant::now();
+        futures::executor::block_on(AsyncStd.sleep(Duration::from_millis(100)));
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[cfg(feature = "smol")]
+    #[test]
+    fn smol() {
+        let start = Instant::now();
+        smol::block_on(Smol.sleep(Duration::from_millis(100)));
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn yield_now_once() {
+        let mut yield_now = Box::pin(yield_now());
+        assert!(futures::poll!(&mut yield_now).is_pending());
+        assert!(futures::poll!(&mut yield_now).is_ready());
+    }
+
+    #[tokio::test]
+    async fn function() {
+        let start = Instant::now();
+        tokio::time::sleep.sleep(Duration::from_millis(100)).await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
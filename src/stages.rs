@@ -0,0 +1,248 @@
+use crate::{config, Instant, Retry};
+use std::{fmt::Write as _, panic::Location, time::Duration};
+
+type Assert<'a> = Box<dyn FnMut() + 'a>;
+
+/// Builder to assert that multiple conditions become true in order, each with its own retry strategy.
+///
+/// If a stage fails, the panic message tells which one and how long the previous stages took.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # struct Files;
+/// # impl Files {
+/// #     fn contains(&self, _name: &str) -> bool { true }
+/// # }
+/// # let (storage, index) = (Files, Files);
+/// # fn search(_query: &str) -> Vec<&'static str> { vec!["file.txt"] }
+/// repeated_assert::Stages::new()
+///     .step("upload", || {
+///         assert!(storage.contains("file.txt"));
+///     })
+///     .step("index", || {
+///         assert!(index.contains("file.txt"));
+///     })
+///     .step("searchable", || {
+///         assert_eq!(search("file").len(), 1);
+///     })
+///     .total_timeout(Duration::from_secs(30))
+///     .run();
+/// ```
+///
+/// Different retry strategies per stage
+///
+/// ```rust
+/// # use repeated_assert::Retry;
+/// # use std::time::Duration;
+/// # struct Files;
+/// # impl Files {
+/// #     fn contains(&self, _name: &str) -> bool { true }
+/// # }
+/// # let (storage, index) = (Files, Files);
+/// repeated_assert::Stages::new()
+///     .stage(Retry::new().name("uploaded").timeout(Duration::from_secs(5)), || {
+///         assert!(storage.contains("file.txt"));
+///     })
+///     .stage(Retry::new().name("indexed").timeout(Duration::from_secs(10)), || {
+///         assert!(index.contains("file.txt"));
+///     })
+///     .run();
+/// ```
+pub struct Stages<'a> {
+    stages: Vec<(Retry<'a>, Assert<'a>)>,
+    total_timeout: Option<Duration>,
+    location: &'static Location<'static>,
+}
+
+impl<'a> Stages<'a> {
+    /// Create a new builder without stages.
+    ///
+    /// The location of the caller is included in the panic message if a stage fails.
+    #[track_caller]
+    pub fn new() -> Stages<'a> {
+        Stages {
+            stages: Vec::new(),
+            total_timeout: None,
+            location: Location::caller(),
+        }
+    }
+
+    /// Add a stage, running `assert` with the retry strategy of `retry` after the previous stages passed.
+    pub fn stage<A, R>(mut self, retry: Retry<'a>, mut assert: A) -> Stages<'a>
+    where
+        A: FnMut() -> R + 'a,
+    {
+        self.stages.push((
+            retry,
+            Box::new(move || {
+                assert();
+            }),
+        ));
+        self
+    }
+
+    /// Add a stage named `name`, running `assert` with the [default policy](crate::default_policy) after the previous stages passed.
+    ///
+    /// If a [`total_timeout`](Stages::total_timeout) is set, the stage is re-tried until it's reached instead.
+    #[track_caller]
+    pub fn step<N, A, R>(self, name: N, assert: A) -> Stages<'a>
+    where
+        N: Into<String>,
+        A: FnMut() -> R + 'a,
+    {
+        self.stage(Retry::new().name(name), assert)
+    }
+
+    /// Fail if all stages together take longer than `total_timeout`.
+    pub fn total_timeout(mut self, total_timeout: Duration) -> Stages<'a> {
+        self.total_timeout = Some(total_timeout);
+        self
+    }
+
+    /// Run the stages in order, panicking as soon as one of them fails.
+    pub fn run(self) {
+        let deadline = self
+            .total_timeout
+            .and_then(|total_timeout| Instant::now().checked_add(config::scaled(total_timeout)));
+        let labels: Vec<_> = self
+            .stages
+            .iter()
+            .enumerate()
+            .map(|(index, (retry, _))| stage_label(index, retry.label()))
+            .collect();
+        let mut passed = Vec::new();
+
+        for (index, (mut retry, mut assert)) in self.stages.into_iter().enumerate() {
+            if let Some(deadline) = deadline {
+                retry = retry.shared_deadline(deadline);
+            }
+            let start = Instant::now();
+            let error = match retry.try_run(&mut assert) {
+                Ok(()) => {
+                    passed.push(start.elapsed());
+                    continue;
+                }
+                Err(error) => error,
+            };
+
+            let mut message = error.message().to_string();
+            write!(
+                message,
+                "\nrepeated-assert: {} of {} failed after {} tries in {:?}",
+                labels[index],
+                labels.len(),
+                error.attempts(),
+                error.elapsed()
+            )
+            .unwrap();
+            for (label, elapsed) in labels.iter().zip(&passed) {
+                write!(message, "\n  {} passed in {:?}", label, elapsed).unwrap();
+            }
+            for label in &labels[index + 1..] {
+                write!(message, "\n  {} not run", label).unwrap();
+            }
+            if let (Some(total_timeout), Some(deadline)) = (self.total_timeout, deadline) {
+                if Instant::now() >= deadline {
+                    write!(message, "\n  total timeout of {:?} exceeded", total_timeout).unwrap();
+                }
+            }
+            if let Some(location) = error.location() {
+                write!(message, "\n  assertion at {}", location).unwrap();
+            }
+            write!(message, "\n  called at {}", self.location).unwrap();
+            panic!("{}", message);
+        }
+    }
+}
+
+impl Default for Stages<'_> {
+    #[track_caller]
+    fn default() -> Self {
+        Stages::new()
+    }
+}
+
+/// Describe the stage with the given `index` and `name`.
+fn stage_label(index: usize, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("stage {} (`{}`)", index + 1, name),
+        None => format!("stage {}", index + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_success() {
+        let mut a = 0;
+        let mut b = 0;
+
+        Stages::new()
+            .stage(
+                Retry::new().repetitions(5).delay(Duration::from_millis(10)),
+                || {
+                    a += 1;
+                    assert!(a > 2);
+                },
+            )
+            .stage(
+                Retry::new().repetitions(5).delay(Duration::from_millis(10)),
+                || {
+                    b += 1;
+                    assert!(b > 1);
+                },
+            )
+            .run();
+
+        assert_eq!((a, b), (3, 2));
+    }
+
+    #[test]
+    #[should_panic(expected = "repeated-assert: stage 2 (`indexed`) of 3 failed after 3 tries")]
+    fn stages_failure() {
+        Stages::new()
+            .stage(Retry::new().name("uploaded"), || {})
+            .stage(
+                Retry::new()
+                    .name("indexed")
+                    .repetitions(3)
+                    .delay(Duration::from_millis(10)),
+                || {
+                    panic!("not indexed");
+                },
+            )
+            .stage(Retry::new(), || {})
+            .run();
+    }
+
+    #[test]
+    fn steps_success() {
+        let mut tries = 0;
+
+        Stages::new()
+            .step("first", || {})
+            .step("second", || {
+                tries += 1;
+                assert!(tries > 2);
+            })
+            .total_timeout(Duration::from_secs(1))
+            .run();
+    }
+
+    #[test]
+    #[should_panic(expected = "  stage 1 (`upload`) passed in")]
+    fn steps_failure() {
+        Stages::new()
+            .step("upload", || {})
+            .step("index", || {
+                panic!("not indexed");
+            })
+            .step("searchable", || {})
+            .total_timeout(Duration::from_millis(200))
+            .run();
+    }
+}
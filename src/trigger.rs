@@ -0,0 +1,132 @@
+use crate::Instant;
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::Duration,
+};
+
+#[derive(Debug, Default)]
+struct Shared {
+    notifications: Mutex<u64>,
+    condvar: Condvar,
+}
+
+/// Wake source of a repeated assertion, see [`Retry::trigger`](crate::Retry::trigger).
+///
+/// Instead of sleeping for the full delay, the assertions are re-checked as soon as a [`Notifier`] of the trigger is notified.
+/// The delay remains the upper bound of the time between tries.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use std::{path::Path, thread, time::Duration};
+/// # fn write_file(_path: &str) {}
+/// let trigger = repeated_assert::Trigger::new();
+/// let notifier = trigger.notifier();
+///
+/// thread::spawn(move || {
+///     write_file("should_appear_soon.txt");
+///     notifier.notify();
+/// });
+///
+/// repeated_assert::Retry::new()
+///     .delay(Duration::from_secs(1))
+///     .trigger(trigger)
+///     .run(|| {
+///         assert!(Path::new("should_appear_soon.txt").exists());
+///     });
+/// ```
+#[derive(Debug, Default)]
+pub struct Trigger {
+    shared: Arc<Shared>,
+    seen: u64,
+}
+
+impl Trigger {
+    /// Create a new trigger.
+    pub fn new() -> Trigger {
+        Trigger::default()
+    }
+
+    /// Get a handle to wake the repeated assertion waiting on this trigger.
+    pub fn notifier(&self) -> Notifier {
+        Notifier {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Create a trigger woken by the same notifiers, e.g. for the window of [`run_then_always`](crate::Retry::run_then_always).
+    pub(crate) fn share(&self) -> Trigger {
+        Trigger {
+            shared: self.shared.clone(),
+            seen: self.seen,
+        }
+    }
+
+    /// Wait until a notifier is notified or `timeout` has passed.
+    ///
+    /// Returns immediately if a notifier has been notified since the last wait.
+    pub(crate) fn wait(&mut self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        let mut notifications = self.shared.notifications.lock().expect("lock trigger");
+        while *notifications == self.seen {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            notifications = self
+                .shared
+                .condvar
+                .wait_timeout(notifications, remaining)
+                .expect("lock trigger")
+                .0;
+        }
+        self.seen = *notifications;
+    }
+}
+
+/// Handle to wake a repeated assertion waiting on a [`Trigger`].
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    shared: Arc<Shared>,
+}
+
+impl Notifier {
+    /// Wake the repeated assertion, so it re-checks the assertions immediately.
+    pub fn notify(&self) {
+        *self.shared.notifications.lock().expect("lock trigger") += 1;
+        self.shared.condvar.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn wait_notified() {
+        let mut trigger = Trigger::new();
+        let notifier = trigger.notifier();
+
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            notifier.notify();
+        });
+
+        let start = Instant::now();
+        trigger.wait(Duration::from_secs(10));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn wait_timeout() {
+        let mut trigger = Trigger::new();
+        trigger.notifier().notify();
+
+        // the earlier notification is consumed by the first wait
+        trigger.wait(Duration::from_secs(10));
+        let start = Instant::now();
+        trigger.wait(Duration::from_millis(20));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+}
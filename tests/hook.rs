@@ -0,0 +1,63 @@
+//! The panic hook is global, so its tests run in their own test binary, one after another.
+
+use std::{
+    panic,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Register a panic hook chaining to the current one, counting the panics it's called for.
+fn counting_hook() -> Arc<AtomicUsize> {
+    let panics = Arc::new(AtomicUsize::new(0));
+    let counter = panics.clone();
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |panic_info| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        previous_hook(panic_info);
+    }));
+    panics
+}
+
+/// Panic and catch the panic, calling the panic hook once.
+fn panic_once() {
+    let _ = panic::catch_unwind(|| panic!("expected panic"));
+}
+
+#[test]
+fn install_and_uninstall_hook() {
+    // reinstall
+    repeated_assert::install_hook();
+    repeated_assert::install_hook();
+    repeated_assert::uninstall_hook();
+    repeated_assert::uninstall_hook();
+
+    // the hook is installed again on demand, ignoring failed tries and chaining to the previous hook
+    let previous_panics = counting_hook();
+    let mut tries = 0;
+    repeated_assert::that(5, Duration::from_millis(10), || {
+        tries += 1;
+        assert!(tries > 2);
+    });
+    assert_eq!(previous_panics.load(Ordering::SeqCst), 0);
+    panic_once();
+    assert_eq!(previous_panics.load(Ordering::SeqCst), 1);
+
+    // hooks registered afterwards are kept
+    let later_panics = counting_hook();
+    repeated_assert::uninstall_hook();
+    panic_once();
+    assert_eq!(later_panics.load(Ordering::SeqCst), 1);
+    assert_eq!(previous_panics.load(Ordering::SeqCst), 2);
+
+    // and failed tries are still ignored
+    let mut tries = 0;
+    repeated_assert::that(5, Duration::from_millis(10), || {
+        tries += 1;
+        assert!(tries > 2);
+    });
+    assert_eq!(later_panics.load(Ordering::SeqCst), 3);
+    assert_eq!(previous_panics.load(Ordering::SeqCst), 2);
+}